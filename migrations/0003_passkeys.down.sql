@@ -0,0 +1,3 @@
+-- Add down migration script here
+DROP TABLE webauthn_challenges;
+DROP TABLE passkeys;
\ No newline at end of file
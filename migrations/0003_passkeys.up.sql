@@ -0,0 +1,22 @@
+-- Add up migration script here
+CREATE TABLE passkeys (
+    id_passkey INT AUTO_INCREMENT,
+    username VARCHAR(25) NOT NULL,
+    name VARCHAR(50) NULL,
+    credential_id VARCHAR(255) NOT NULL,
+    public_key VARBINARY(65) NOT NULL,
+    sign_count BIGINT NOT NULL DEFAULT 0,
+    created_at BIGINT NOT NULL,
+    last_used_at BIGINT NULL,
+    PRIMARY KEY (id_passkey),
+    UNIQUE (credential_id),
+    FOREIGN KEY (username) REFERENCES user (username) ON DELETE CASCADE ON UPDATE CASCADE
+);
+
+CREATE TABLE webauthn_challenges (
+    challenge VARCHAR(64) NOT NULL,
+    username VARCHAR(25) NULL,
+    ceremony VARCHAR(20) NOT NULL,
+    expires_at BIGINT NOT NULL,
+    PRIMARY KEY (challenge)
+);
\ No newline at end of file
@@ -0,0 +1,6 @@
+-- Add down migration script here
+ALTER TABLE credentials
+    ADD COLUMN refresh_token VARCHAR(255) NULL,
+    ADD COLUMN max_age BIGINT NULL;
+
+DROP TABLE sessions;
\ No newline at end of file
@@ -0,0 +1,18 @@
+-- Add up migration script here
+CREATE TABLE sessions (
+    id_session INT AUTO_INCREMENT,
+    username VARCHAR(25) NOT NULL,
+    refresh_token VARCHAR(255) NOT NULL,
+    max_age BIGINT NOT NULL,
+    ip_address VARCHAR(45) NULL,
+    user_agent VARCHAR(255) NULL,
+    created_at BIGINT NOT NULL,
+    last_refreshed_at BIGINT NULL,
+    PRIMARY KEY (id_session),
+    UNIQUE (refresh_token),
+    FOREIGN KEY (username) REFERENCES user (username) ON DELETE CASCADE ON UPDATE CASCADE
+);
+
+ALTER TABLE credentials
+    DROP COLUMN refresh_token,
+    DROP COLUMN max_age;
\ No newline at end of file
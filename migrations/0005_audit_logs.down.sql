@@ -0,0 +1,5 @@
+-- Add down migration script here
+DROP TABLE audit_logs;
+
+ALTER TABLE user
+    DROP COLUMN role;
\ No newline at end of file
@@ -0,0 +1,20 @@
+-- Add up migration script here
+ALTER TABLE user
+    ADD COLUMN role VARCHAR(20) NOT NULL DEFAULT 'user';
+
+CREATE TABLE audit_logs (
+    id_audit_log BIGINT AUTO_INCREMENT,
+    event VARCHAR(50) NOT NULL,
+    actor VARCHAR(25) NULL,
+    target VARCHAR(25) NULL,
+    success BOOLEAN NOT NULL,
+    reason VARCHAR(255) NULL,
+    ip_address VARCHAR(45) NULL,
+    user_agent VARCHAR(255) NULL,
+    created_at BIGINT NOT NULL,
+    PRIMARY KEY (id_audit_log),
+    INDEX (event),
+    INDEX (actor),
+    INDEX (target),
+    INDEX (created_at)
+);
\ No newline at end of file
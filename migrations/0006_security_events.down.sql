@@ -0,0 +1,7 @@
+-- Add down migration script here
+ALTER TABLE credentials
+    DROP COLUMN password_reset_required;
+
+DROP TABLE password_resets;
+DROP TABLE security_events;
+DROP TABLE known_devices;
\ No newline at end of file
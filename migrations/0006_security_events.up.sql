@@ -0,0 +1,36 @@
+-- Add up migration script here
+CREATE TABLE known_devices (
+    id_known_device INT AUTO_INCREMENT,
+    username VARCHAR(25) NOT NULL,
+    ip_address VARCHAR(45) NULL,
+    user_agent VARCHAR(255) NULL,
+    first_seen_at BIGINT NOT NULL,
+    last_seen_at BIGINT NOT NULL,
+    PRIMARY KEY (id_known_device),
+    FOREIGN KEY (username) REFERENCES user (username) ON DELETE CASCADE ON UPDATE CASCADE
+);
+
+CREATE TABLE security_events (
+    id_security_event BIGINT AUTO_INCREMENT,
+    username VARCHAR(25) NOT NULL,
+    event_type VARCHAR(50) NOT NULL,
+    ip_address VARCHAR(45) NULL,
+    user_agent VARCHAR(255) NULL,
+    disavow_token VARCHAR(64) NULL,
+    disavowed_at BIGINT NULL,
+    created_at BIGINT NOT NULL,
+    PRIMARY KEY (id_security_event),
+    UNIQUE (disavow_token),
+    FOREIGN KEY (username) REFERENCES user (username) ON DELETE CASCADE ON UPDATE CASCADE
+);
+
+CREATE TABLE password_resets (
+    token VARCHAR(64) NOT NULL,
+    username VARCHAR(25) NOT NULL,
+    expires_at BIGINT NOT NULL,
+    PRIMARY KEY (token),
+    FOREIGN KEY (username) REFERENCES user (username) ON DELETE CASCADE ON UPDATE CASCADE
+);
+
+ALTER TABLE credentials
+    ADD COLUMN password_reset_required BOOLEAN NOT NULL DEFAULT FALSE;
\ No newline at end of file
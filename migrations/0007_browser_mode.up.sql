@@ -0,0 +1,3 @@
+-- Add up migration script here
+ALTER TABLE sessions
+    ADD COLUMN browser_mode BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,3 @@
+-- Add up migration script here
+ALTER TABLE sessions
+    ADD COLUMN dpop_jkt VARCHAR(43) NULL;
@@ -0,0 +1,3 @@
+-- Add up migration script here
+ALTER TABLE sessions
+    ADD COLUMN scope VARCHAR(255) NULL;
@@ -0,0 +1,3 @@
+-- Add down migration script here
+ALTER TABLE sessions
+    MODIFY COLUMN refresh_token VARCHAR(255) NOT NULL;
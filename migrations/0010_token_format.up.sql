@@ -0,0 +1,3 @@
+-- Add up migration script here
+ALTER TABLE sessions
+    MODIFY COLUMN refresh_token VARCHAR(512) NOT NULL;
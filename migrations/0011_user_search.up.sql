@@ -0,0 +1,3 @@
+-- Add up migration script here
+ALTER TABLE user
+    ADD FULLTEXT INDEX ft_user_search (full_name, username, email);
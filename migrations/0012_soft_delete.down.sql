@@ -0,0 +1,4 @@
+-- Add down migration script here
+ALTER TABLE user
+    DROP INDEX idx_user_deleted_at,
+    DROP COLUMN deleted_at;
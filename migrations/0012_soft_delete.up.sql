@@ -0,0 +1,4 @@
+-- Add up migration script here
+ALTER TABLE user
+    ADD COLUMN deleted_at BIGINT NULL DEFAULT NULL,
+    ADD INDEX idx_user_deleted_at (deleted_at);
@@ -0,0 +1,3 @@
+-- Add up migration script here
+ALTER TABLE user
+    ADD COLUMN version INT UNSIGNED NOT NULL DEFAULT 1;
@@ -0,0 +1,12 @@
+-- Add down migration script here
+ALTER TABLE credentials
+    DROP COLUMN last_login_at,
+    DROP COLUMN updated_at,
+    DROP COLUMN created_at;
+
+ALTER TABLE user
+    DROP INDEX idx_user_last_login_at,
+    DROP INDEX idx_user_created_at,
+    DROP COLUMN last_login_at,
+    DROP COLUMN updated_at,
+    DROP COLUMN created_at;
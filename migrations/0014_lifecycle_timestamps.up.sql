@@ -0,0 +1,13 @@
+-- Add up migration script here
+-- existing rows predate lifecycle tracking and keep NULL timestamps
+ALTER TABLE user
+    ADD COLUMN created_at BIGINT NULL DEFAULT NULL,
+    ADD COLUMN updated_at BIGINT NULL DEFAULT NULL,
+    ADD COLUMN last_login_at BIGINT NULL DEFAULT NULL,
+    ADD INDEX idx_user_created_at (created_at),
+    ADD INDEX idx_user_last_login_at (last_login_at);
+
+ALTER TABLE credentials
+    ADD COLUMN created_at BIGINT NULL DEFAULT NULL,
+    ADD COLUMN updated_at BIGINT NULL DEFAULT NULL,
+    ADD COLUMN last_login_at BIGINT NULL DEFAULT NULL;
@@ -0,0 +1,13 @@
+-- Add up migration script here
+CREATE TABLE user_history (
+    id_user_history BIGINT AUTO_INCREMENT,
+    id_user INT NOT NULL,
+    field VARCHAR(50) NOT NULL,
+    old_value VARCHAR(255) NULL,
+    new_value VARCHAR(255) NULL,
+    actor VARCHAR(25) NULL,
+    created_at BIGINT NOT NULL,
+    PRIMARY KEY (id_user_history),
+    INDEX (id_user, created_at),
+    FOREIGN KEY (id_user) REFERENCES user (id_user) ON DELETE CASCADE
+);
@@ -0,0 +1,19 @@
+-- Add up migration script here
+CREATE TABLE email_changes (
+    id_email_change BIGINT AUTO_INCREMENT,
+    id_user INT NOT NULL,
+    old_email VARCHAR(50) NULL,
+    new_email VARCHAR(50) NOT NULL,
+    confirm_token VARCHAR(64) NOT NULL,
+    revert_token VARCHAR(64) NOT NULL,
+    actor VARCHAR(25) NULL,
+    expires_at BIGINT NOT NULL,
+    confirmed_at BIGINT NULL,
+    reverted_at BIGINT NULL,
+    created_at BIGINT NOT NULL,
+    PRIMARY KEY (id_email_change),
+    UNIQUE (confirm_token),
+    UNIQUE (revert_token),
+    INDEX (new_email),
+    FOREIGN KEY (id_user) REFERENCES user (id_user) ON DELETE CASCADE
+);
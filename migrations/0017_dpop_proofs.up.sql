@@ -0,0 +1,7 @@
+-- Add up migration script here
+CREATE TABLE dpop_proofs (
+    jti VARCHAR(64) NOT NULL,
+    expires_at BIGINT NOT NULL,
+    PRIMARY KEY (jti),
+    INDEX (expires_at)
+);
@@ -0,0 +1,4 @@
+-- Add down migration script here
+ALTER TABLE audit_logs
+    MODIFY COLUMN actor VARCHAR(25) NULL,
+    MODIFY COLUMN target VARCHAR(25) NULL;
@@ -0,0 +1,4 @@
+-- Add up migration script here
+ALTER TABLE audit_logs
+    MODIFY COLUMN actor VARCHAR(255) NULL,
+    MODIFY COLUMN target VARCHAR(255) NULL;
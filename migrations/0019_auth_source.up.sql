@@ -0,0 +1,5 @@
+-- Add up migration script here
+-- credential backend that owns the account (db, ldap, htpasswd), other backends may not sign into it
+-- users provisioned by ldap or htpasswd before this migration default to db and must be updated by hand
+ALTER TABLE user
+    ADD COLUMN auth_source VARCHAR(20) NOT NULL DEFAULT 'db';
@@ -0,0 +1,4 @@
+-- Add up migration script here
+-- public profile search, non-admins may not match on email
+ALTER TABLE user
+    ADD FULLTEXT INDEX ft_user_public_search (full_name, username);
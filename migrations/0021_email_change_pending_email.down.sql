@@ -0,0 +1,4 @@
+-- Add down migration script here
+ALTER TABLE email_changes
+    DROP INDEX uq_email_changes_pending_email,
+    DROP COLUMN pending_email;
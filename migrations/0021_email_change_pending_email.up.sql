@@ -0,0 +1,9 @@
+-- Add up migration script here
+-- new address of a change that is still pending, NULL once confirmed, reverted or released after expiry
+-- unique so two accounts cannot claim the same address concurrently, backfill skips duplicates of older changes
+ALTER TABLE email_changes
+    ADD COLUMN pending_email VARCHAR(50) NULL,
+    ADD UNIQUE INDEX uq_email_changes_pending_email (pending_email);
+UPDATE IGNORE email_changes
+    SET pending_email = new_email
+    WHERE confirmed_at IS NULL AND reverted_at IS NULL AND expires_at > UNIX_TIMESTAMP();
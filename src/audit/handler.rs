@@ -0,0 +1,48 @@
+use actix_web::{
+    web,
+    Responder,
+    HttpResponse,
+    http::StatusCode
+};
+use serde_json::json;
+
+use crate::{
+    types::AppState,
+    audit::{
+        services::get_audit_logs::get_audit_logs_service,
+        model::AuditLogQuery
+    },
+    auth::{
+        JwtAuth,
+        SCOPE_AUDIT_READ
+    }
+};
+
+pub async fn get_audit_logs(
+    auth: JwtAuth,
+    app_state: web::Data<AppState>,
+    query: web::Query<AuditLogQuery>
+) -> impl Responder
+{
+    if let Err(e) = auth.require_admin().and_then(|_| auth.require_scope(SCOPE_AUDIT_READ)) {
+        return HttpResponse::from_error(e);
+    }
+    let app_state = app_state.get_ref();
+    let query = query.into_inner();
+
+    let get_audit_logs_service = get_audit_logs_service(app_state, query).await;
+    match get_audit_logs_service {
+        Ok(audit_logs) => {
+            let status_code = StatusCode::OK;
+            let success_message = json!({
+                "success": true,
+                "code": status_code.as_u16(),
+                "message": "successfully retrieved audit logs.",
+                "data": audit_logs,
+                "length": audit_logs.len()
+            });
+            HttpResponse::build(status_code).json(success_message)
+        },
+        Err(e) => HttpResponse::from_error(e)
+    }
+}
\ No newline at end of file
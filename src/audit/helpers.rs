@@ -0,0 +1,44 @@
+use chrono::Utc;
+
+use crate::{
+    db::DbPool,
+    audit::model::AuditEntry
+};
+
+/* * write audit trail entry, failure is only logged and never breaks the request */
+pub async fn record_audit_log(
+    db_pool: &DbPool,
+    entry: AuditEntry<'_>
+) {
+    let time_now = Utc::now().timestamp();
+    let actor = entry.actor.map(|actor| actor.chars().take(255).collect::<String>());
+    let target = entry.target.map(|target| target.chars().take(255).collect::<String>());
+    let reason = entry.reason.map(|reason| reason.chars().take(255).collect::<String>());
+
+    let sql_query = sqlx::query("INSERT INTO audit_logs (
+        event,
+        actor,
+        target,
+        success,
+        reason,
+        ip_address,
+        user_agent,
+        created_at
+    ) VALUES (?, ?, ?, ?, ?, ?, ?, ?);");
+    let query_result = sql_query
+        .bind(entry.event)
+        .bind(&actor)
+        .bind(&target)
+        .bind(entry.success)
+        .bind(&reason)
+        .bind(&entry.client.ip_address)
+        .bind(&entry.client.user_agent)
+        .bind(time_now)
+        .execute(db_pool)
+        .await;
+
+    if let Err(e) = query_result {
+        log::error!("failed to write audit log '{}': {}", entry.event, e);
+    }
+}
+/* * end write audit trail entry, failure is only logged and never breaks the request */
\ No newline at end of file
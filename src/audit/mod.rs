@@ -0,0 +1,10 @@
+mod handler;
+mod helpers;
+mod model;
+mod routes;
+mod services;
+
+pub use routes::scoped_audit;
+
+pub use helpers::record_audit_log;
+pub use model::AuditEntry;
\ No newline at end of file
@@ -0,0 +1,42 @@
+use serde::{
+    Serialize,
+    Deserialize
+};
+use sqlx::FromRow;
+
+use crate::auth::ClientInfo;
+
+pub struct AuditEntry<'a> {
+    pub event: &'a str,
+    pub actor: Option<&'a str>,
+    pub target: Option<&'a str>,
+    pub success: bool,
+    pub reason: Option<String>,
+    pub client: &'a ClientInfo
+}
+
+#[derive(Serialize, FromRow)]
+pub struct AuditLog {
+    pub id_audit_log: i64,
+    pub event: String,
+    pub actor: Option<String>,
+    pub target: Option<String>,
+    pub success: bool,
+    pub reason: Option<String>,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    pub created_at: i64
+}
+
+#[derive(Deserialize)]
+pub struct AuditLogQuery {
+    pub event: Option<String>,
+    pub actor: Option<String>,
+    pub target: Option<String>,
+    pub success: Option<bool>,
+    pub ip_address: Option<String>,
+    pub from: Option<i64>,
+    pub to: Option<i64>,
+    pub limit: Option<u32>,
+    pub offset: Option<u32>
+}
\ No newline at end of file
@@ -0,0 +1,10 @@
+use actix_web::web;
+
+use crate::audit::handler::get_audit_logs;
+
+pub fn scoped_audit(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/audit-logs")
+            .route("", web::get().to(get_audit_logs))
+    );
+}
\ No newline at end of file
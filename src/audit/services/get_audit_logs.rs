@@ -0,0 +1,67 @@
+use sqlx::{
+    MySql,
+    QueryBuilder
+};
+
+use crate::{
+    types::AppState,
+    errors::AppError,
+    audit::model::{
+        AuditLog,
+        AuditLogQuery
+    }
+};
+
+const DEFAULT_LIMIT: u32 = 50;
+const MAX_LIMIT: u32 = 200;
+
+pub async fn get_audit_logs_service(
+    app_state: &AppState,
+    query: AuditLogQuery
+) -> Result<Vec<AuditLog>, AppError>
+{
+    /* * take db pool from handler */
+    let db_pool = &app_state.db_pool;
+    /* * end take db pool from handler */
+
+    /* * build filtered sql query */
+    let mut query_builder: QueryBuilder<MySql> = QueryBuilder::new("SELECT * FROM audit_logs WHERE 1 = 1");
+    if let Some(event) = query.event {
+        query_builder.push(" AND event = ").push_bind(event);
+    }
+    if let Some(actor) = query.actor {
+        query_builder.push(" AND actor = ").push_bind(actor);
+    }
+    if let Some(target) = query.target {
+        query_builder.push(" AND target = ").push_bind(target);
+    }
+    if let Some(success) = query.success {
+        query_builder.push(" AND success = ").push_bind(success);
+    }
+    if let Some(ip_address) = query.ip_address {
+        query_builder.push(" AND ip_address = ").push_bind(ip_address);
+    }
+    if let Some(from) = query.from {
+        query_builder.push(" AND created_at >= ").push_bind(from);
+    }
+    if let Some(to) = query.to {
+        query_builder.push(" AND created_at <= ").push_bind(to);
+    }
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+    let offset = query.offset.unwrap_or(0);
+    query_builder
+        .push(" ORDER BY id_audit_log DESC LIMIT ")
+        .push_bind(limit)
+        .push(" OFFSET ")
+        .push_bind(offset);
+    /* * end build filtered sql query */
+
+    /* * sql query retrieved filtered audit logs */
+    let query_result = query_builder
+        .build_query_as::<AuditLog>()
+        .fetch_all(db_pool)
+        .await?;
+    /* * end sql query retrieved filtered audit logs */
+
+    Ok(query_result)
+}
\ No newline at end of file
@@ -0,0 +1 @@
+pub mod get_audit_logs;
\ No newline at end of file
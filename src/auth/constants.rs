@@ -1,15 +1,46 @@
 use chrono::Duration as ChronoDuration;
 use once_cell::sync::Lazy;
-use actix_web::cookie::time::Duration as ActixDuration;
 
-const ACCESS_TOKEN_EXPIRED: i64 = 1;
-const REFRESH_TOKEN_EXPIRED: i64 = 2;
+const WEBAUTHN_CHALLENGE_EXPIRED: i64 = 5;
+const SECURITY_EVENT_DISAVOW_EXPIRED: i64 = 7;
+const PASSWORD_RESET_EXPIRED: i64 = 30;
+const IMPERSONATION_EXPIRED: i64 = 15;
+const DPOP_PROOF_MAX_AGE: i64 = 60;
+const DPOP_NONCE_EXPIRED: i64 = 5;
 
-pub const CHRONO_ACCESS_TOKEN_EXPIRED: Lazy<ChronoDuration> = Lazy::new(|| {
-    ChronoDuration::minutes(ACCESS_TOKEN_EXPIRED)
+pub const ROLE_ADMIN: &str = "admin";
+pub const ROLE_SERVICE: &str = "service";
+pub const SERVICE_PRINCIPAL_PREFIX: &str = "service:";
+pub const SCOPE_USERS_READ: &str = "users:read";
+pub const SCOPE_USERS_WRITE: &str = "users:write";
+pub const SCOPE_PROFILE_WRITE: &str = "profile:write";
+pub const SCOPE_AUDIT_READ: &str = "audit:read";
+pub const ACCESS_TOKEN_COOKIE: &str = "access_token";
+pub const CSRF_TOKEN_COOKIE: &str = "csrf_token";
+pub const CSRF_TOKEN_HEADER: &str = "X-CSRF-Token";
+pub const TOKEN_TYPE_BEARER: &str = "Bearer";
+pub const TOKEN_TYPE_DPOP: &str = "DPoP";
+pub const DPOP_HEADER: &str = "DPoP";
+pub const DPOP_NONCE_HEADER: &str = "DPoP-Nonce";
+pub const REFRESH_TOKEN_COOKIE: &str = "refresh_token";
+pub const REFRESH_TOKEN_HEADER: &str = "X-Refresh-Token";
+pub const CLIENT_ID_HEADER: &str = "X-Client-Id";
+
+pub static CHRONO_WEBAUTHN_CHALLENGE_EXPIRED: Lazy<ChronoDuration> = Lazy::new(|| {
+    ChronoDuration::minutes(WEBAUTHN_CHALLENGE_EXPIRED)
 });
-pub const CHRONO_REFRESH_TOKEN_EXPIRED: Lazy<ChronoDuration> = Lazy::new(|| {
-    ChronoDuration::minutes(REFRESH_TOKEN_EXPIRED)
+pub static CHRONO_SECURITY_EVENT_DISAVOW_EXPIRED: Lazy<ChronoDuration> = Lazy::new(|| {
+    ChronoDuration::days(SECURITY_EVENT_DISAVOW_EXPIRED)
 });
-
-pub static ACTIX_REFRESH_TOKEN_EXPIRED: ActixDuration = ActixDuration::minutes(REFRESH_TOKEN_EXPIRED);
\ No newline at end of file
+pub static CHRONO_PASSWORD_RESET_EXPIRED: Lazy<ChronoDuration> = Lazy::new(|| {
+    ChronoDuration::minutes(PASSWORD_RESET_EXPIRED)
+});
+pub static CHRONO_IMPERSONATION_EXPIRED: Lazy<ChronoDuration> = Lazy::new(|| {
+    ChronoDuration::minutes(IMPERSONATION_EXPIRED)
+});
+pub static CHRONO_DPOP_PROOF_MAX_AGE: Lazy<ChronoDuration> = Lazy::new(|| {
+    ChronoDuration::seconds(DPOP_PROOF_MAX_AGE)
+});
+pub static CHRONO_DPOP_NONCE_EXPIRED: Lazy<ChronoDuration> = Lazy::new(|| {
+    ChronoDuration::minutes(DPOP_NONCE_EXPIRED)
+});
\ No newline at end of file
@@ -0,0 +1,179 @@
+use actix_web::{
+    HttpRequest,
+    http::{
+        StatusCode,
+        header::{
+            ORIGIN,
+            REFERER
+        }
+    },
+    cookie::{
+        Cookie,
+        SameSite,
+        time::{
+            Duration as ActixDuration,
+            OffsetDateTime
+        }
+    }
+};
+
+use crate::{
+    types::CsrfConfig,
+    errors::{
+        AppError,
+        AppErrorMessage
+    },
+    auth::constants::{
+        CSRF_TOKEN_COOKIE,
+        CSRF_TOKEN_HEADER
+    }
+};
+
+/* * build csrf AppError */
+fn csrf_error(message: String) -> AppError {
+    let app_err_message: AppErrorMessage<Option<bool>> = AppErrorMessage {
+        code: StatusCode::FORBIDDEN.as_u16(),
+        message: format!("CSRF validation failed: {}", message),
+        details: None
+    };
+    AppError::Forbidden(app_err_message.into())
+}
+/* * end build csrf AppError */
+
+/* * get request origin from Origin header, fallback to Referer header */
+fn get_request_origin(request: &HttpRequest) -> Option<String> {
+    let headers = request.headers();
+    if let Some(origin) = headers.get(ORIGIN).and_then(|hv| hv.to_str().ok()) {
+        return Some(origin.trim_end_matches('/').to_string());
+    }
+
+    /* * * referer is a full url, keep only scheme://host[:port] */
+    let referer = headers.get(REFERER).and_then(|hv| hv.to_str().ok())?;
+    let (scheme, rest) = referer.split_once("://")?;
+    let host = rest.split(['/', '?', '#']).next()?;
+    Some(format!("{}://{}", scheme, host))
+    /* * * end referer is a full url, keep only scheme://host[:port] */
+}
+/* * end get request origin from Origin header, fallback to Referer header */
+
+/* * browsers always send Origin on cross-site requests, reject the ones outside the allowlist */
+/* * * a request without Origin & Referer passes, it comes from a non-browser client or carries no cookie (signin) */
+pub fn verify_origin(request: &HttpRequest, config: &CsrfConfig) -> Result<(), AppError> {
+    match get_request_origin(request) {
+        Some(origin) if !config.is_allowed_origin(&origin) => {
+            Err(csrf_error(format!("origin '{}' is not allowed.", origin)))
+        },
+        _ => Ok(())
+    }
+}
+/* * end browsers always send Origin on cross-site requests, reject the ones outside the allowlist */
+
+/* * cookie authenticated requests come from a browser, so Origin or Referer is required */
+fn verify_required_origin(request: &HttpRequest, config: &CsrfConfig) -> Result<(), AppError> {
+    match get_request_origin(request) {
+        None => Err(csrf_error("missing Origin and Referer header.".to_string())),
+        Some(_) => verify_origin(request, config)
+    }
+}
+/* * end cookie authenticated requests come from a browser, so Origin or Referer is required */
+
+/* * double-submit check: X-CSRF-Token header must match csrf_token cookie */
+pub fn verify_csrf(request: &HttpRequest, config: &CsrfConfig) -> Result<(), AppError> {
+    verify_required_origin(request, config)?;
+
+    let cookie_token = request.cookie(CSRF_TOKEN_COOKIE)
+        .map(|cookie| cookie.value().to_string())
+        .ok_or_else(|| csrf_error(format!("missing {} cookie.", CSRF_TOKEN_COOKIE)))?;
+    let header_token = request.headers()
+        .get(CSRF_TOKEN_HEADER)
+        .and_then(|hv| hv.to_str().ok())
+        .ok_or_else(|| csrf_error(format!("missing {} header.", CSRF_TOKEN_HEADER)))?;
+
+    /* * * constant time comparison */
+    let is_token_match = cookie_token.len() == header_token.len() && cookie_token
+        .bytes()
+        .zip(header_token.bytes())
+        .fold(0u8, |diff, (a, b)| diff | (a ^ b)) == 0;
+    /* * * end constant time comparison */
+    if cookie_token.is_empty() || !is_token_match {
+        return Err(csrf_error(format!("{} header does not match {} cookie.", CSRF_TOKEN_HEADER, CSRF_TOKEN_COOKIE)));
+    }
+
+    Ok(())
+}
+/* * end double-submit check: X-CSRF-Token header must match csrf_token cookie */
+
+/* * build csrf_token cookie, lives as long as refresh_token cookie, readable by js for the double-submit header */
+pub fn build_csrf_token_cookie(csrf_token: String, max_age: ActixDuration) -> Cookie<'static> {
+    Cookie::build(CSRF_TOKEN_COOKIE, csrf_token)
+        .secure(true)
+        .same_site(SameSite::None)
+        .max_age(max_age)
+        .path("/")
+        .finish()
+}
+/* * end build csrf_token cookie, lives as long as refresh_token cookie, readable by js for the double-submit header */
+
+/* * build expired csrf_token cookie for removing it from client */
+pub fn delete_csrf_token_cookie() -> Cookie<'static> {
+    Cookie::build(CSRF_TOKEN_COOKIE, "")
+        .secure(true)
+        .same_site(SameSite::None)
+        .path("/")
+        .expires(OffsetDateTime::now_utc())
+        .finish()
+}
+/* * end build expired csrf_token cookie for removing it from client */
+
+#[cfg(test)]
+mod tests {
+    use actix_web::test::TestRequest;
+
+    use super::*;
+
+    fn config() -> CsrfConfig {
+        CsrfConfig::from_list("https://app.example.com").unwrap()
+    }
+
+    fn cookie_request(origin: Option<(&str, &'static str)>, header_token: &str) -> HttpRequest {
+        let mut request = TestRequest::post()
+            .cookie(Cookie::new(CSRF_TOKEN_COOKIE, "token-value"))
+            .insert_header((CSRF_TOKEN_HEADER, header_token));
+        if let Some((value, name)) = origin {
+            request = request.insert_header((name, value));
+        }
+        request.to_http_request()
+    }
+
+    #[test]
+    fn verify_origin_allows_listed_and_missing_origin() {
+        let listed = TestRequest::post().insert_header((ORIGIN, "https://app.example.com/")).to_http_request();
+        let referer = TestRequest::post().insert_header((REFERER, "https://app.example.com/settings?tab=1")).to_http_request();
+        let foreign = TestRequest::post().insert_header((ORIGIN, "https://evil.example.com")).to_http_request();
+
+        assert!(verify_origin(&listed, &config()).is_ok());
+        assert!(verify_origin(&referer, &config()).is_ok());
+        assert!(verify_origin(&TestRequest::post().to_http_request(), &config()).is_ok());
+        assert!(verify_origin(&foreign, &config()).is_err());
+    }
+
+    #[test]
+    fn verify_csrf_requires_origin_and_matching_token() {
+        let origin = Some(("https://app.example.com", "origin"));
+
+        assert!(verify_csrf(&cookie_request(origin, "token-value"), &config()).is_ok());
+        assert!(verify_csrf(&cookie_request(Some(("https://app.example.com/page", "referer")), "token-value"), &config()).is_ok());
+        assert!(verify_csrf(&cookie_request(None, "token-value"), &config()).is_err());
+        assert!(verify_csrf(&cookie_request(Some(("https://evil.example.com", "origin")), "token-value"), &config()).is_err());
+        assert!(verify_csrf(&cookie_request(origin, "token-other"), &config()).is_err());
+        assert!(verify_csrf(&cookie_request(origin, "token"), &config()).is_err());
+    }
+
+    #[test]
+    fn csrf_cookie_is_readable_by_scripts() {
+        let cookie = build_csrf_token_cookie("token-value".to_string(), ActixDuration::days(1));
+
+        assert_ne!(cookie.http_only(), Some(true));
+        assert_eq!(cookie.secure(), Some(true));
+    }
+}
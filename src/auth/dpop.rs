@@ -0,0 +1,458 @@
+use actix_web::{
+    HttpRequest,
+    http::StatusCode
+};
+use base64::{
+    Engine,
+    engine::general_purpose::URL_SAFE_NO_PAD
+};
+use chrono::Utc;
+use hmac::{
+    Hmac,
+    Mac
+};
+use p256::{
+    EncodedPoint,
+    ecdsa::{
+        Signature,
+        VerifyingKey,
+        signature::Verifier
+    }
+};
+use serde::Deserialize;
+use serde_json::json;
+use sha2::{
+    Digest,
+    Sha256
+};
+
+use crate::{
+    db::DbPool,
+    types::AppState,
+    errors::{
+        AppError,
+        AppErrorMessage
+    },
+    auth::{
+        helpers::hash_token,
+        constants::{
+            DPOP_HEADER,
+            CHRONO_DPOP_PROOF_MAX_AGE,
+            CHRONO_DPOP_NONCE_EXPIRED
+        }
+    }
+};
+
+/* * verified proof, jti must still be consumed before the proof is accepted */
+pub struct DpopProof {
+    pub jkt: String,
+    pub jti: String
+}
+
+#[derive(Deserialize)]
+struct DpopHeader {
+    typ: String,
+    alg: String,
+    jwk: DpopJwk
+}
+
+#[derive(Deserialize)]
+struct DpopJwk {
+    kty: String,
+    crv: String,
+    x: String,
+    y: String
+}
+
+#[derive(Deserialize)]
+struct DpopClaims {
+    jti: String,
+    htm: String,
+    htu: String,
+    iat: i64,
+    nonce: Option<String>,
+    ath: Option<String>
+}
+
+/* * build invalid_dpop_proof AppError */
+fn dpop_error(message: &str) -> AppError {
+    let app_err_message = AppErrorMessage {
+        code: StatusCode::UNAUTHORIZED.as_u16(),
+        message: format!("DPoP proof verification failed. {}", message),
+        details: Some(json!({ "error": "invalid_dpop_proof" }))
+    };
+    AppError::Unauthorized(app_err_message.into())
+}
+/* * end build invalid_dpop_proof AppError */
+
+/* * build use_dpop_nonce AppError, response carries a fresh nonce in DPoP-Nonce header (RFC 9449 section 8) */
+fn use_dpop_nonce_error(app_state: &AppState, is_resource_request: bool) -> AppError {
+    /* * * token endpoints answer 400, resource requests answer 401 with WWW-Authenticate */
+    let status_code = match is_resource_request {
+        true => StatusCode::UNAUTHORIZED,
+        false => StatusCode::BAD_REQUEST
+    };
+    let app_err_message = AppErrorMessage {
+        code: status_code.as_u16(),
+        message: String::from("DPoP proof verification failed. proof nonce is missing or expired. retry with the nonce from the DPoP-Nonce header."),
+        details: Some(json!({
+            "error": "use_dpop_nonce",
+            "dpop_nonce": generate_dpop_nonce(app_state)
+        }))
+    };
+    AppError::UseDpopNonce(app_err_message.into())
+}
+/* * end build use_dpop_nonce AppError, response carries a fresh nonce in DPoP-Nonce header (RFC 9449 section 8) */
+
+/* * sign nonce issued time, nonce is stateless and verified by signature */
+/* * * nonce key is derived from the token secret, the secret itself only ever signs tokens */
+fn dpop_nonce_key(app_state: &AppState) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(app_state.secret_access_token.as_bytes())
+        .expect("hmac accepts keys of any length");
+    mac.update(b"rst04_jwt dpop nonce key v1");
+
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn dpop_nonce_mac(app_state: &AppState, issued_at: i64) -> Hmac<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(&dpop_nonce_key(app_state))
+        .expect("hmac accepts keys of any length");
+    mac.update(b"dpop-nonce:");
+    mac.update(issued_at.to_string().as_bytes());
+
+    mac
+}
+
+pub fn generate_dpop_nonce(app_state: &AppState) -> String {
+    let issued_at = Utc::now().timestamp();
+    let signature = dpop_nonce_mac(app_state, issued_at).finalize().into_bytes();
+
+    format!("{}.{}", issued_at, URL_SAFE_NO_PAD.encode(signature))
+}
+
+fn verify_dpop_nonce(app_state: &AppState, nonce: &str) -> bool {
+    let Some((issued_at, signature)) = nonce.split_once('.') else {
+        return false;
+    };
+    let (Ok(issued_at), Ok(signature)) = (issued_at.parse::<i64>(), URL_SAFE_NO_PAD.decode(signature)) else {
+        return false;
+    };
+    let time_now = Utc::now().timestamp();
+
+    /* * * client controlled issued_at, overflowing age never verifies; verify_slice compares in constant time */
+    time_now
+        .checked_sub(issued_at)
+        .is_some_and(|age| (0..=CHRONO_DPOP_NONCE_EXPIRED.num_seconds()).contains(&age))
+        && dpop_nonce_mac(app_state, issued_at).verify_slice(&signature).is_ok()
+}
+/* * end sign nonce issued time, nonce is stateless and verified by signature */
+
+/* * RFC 7638 jwk thumbprint, members in lexicographic order */
+fn jwk_thumbprint(jwk: &DpopJwk) -> String {
+    let canonical_jwk = format!(
+        r#"{{"crv":"{}","kty":"{}","x":"{}","y":"{}"}}"#,
+        jwk.crv, jwk.kty, jwk.x, jwk.y
+    );
+
+    URL_SAFE_NO_PAD.encode(Sha256::digest(canonical_jwk.as_bytes()))
+}
+/* * end RFC 7638 jwk thumbprint, members in lexicographic order */
+
+/* * htu is compared against public url of requested path, without query and fragment */
+pub fn get_request_url(request: &HttpRequest, app_state: &AppState) -> String {
+    format!("{}{}", app_state.public_url.trim_end_matches('/'), request.path())
+}
+/* * end htu is compared against public url of requested path, without query and fragment */
+
+/* * get DPoP proof header from request */
+pub fn get_dpop_proof(request: &HttpRequest) -> Option<&str> {
+    request
+        .headers()
+        .get(DPOP_HEADER)
+        .and_then(|hv| hv.to_str().ok())
+}
+/* * end get DPoP proof header from request */
+
+/* * DPoP is optional at token endpoints, verify & consume proof only when client sends one */
+pub async fn get_dpop_jkt(request: &HttpRequest, app_state: &AppState) -> Result<Option<String>, AppError> {
+    let Some(proof) = get_dpop_proof(request) else {
+        return Ok(None);
+    };
+    let proof = verify_dpop_proof(request, app_state, proof, None)?;
+    consume_dpop_jti(&app_state.db_pool, &proof.jti).await?;
+
+    Ok(Some(proof.jkt))
+}
+/* * end DPoP is optional at token endpoints, verify & consume proof only when client sends one */
+
+/* * store proof jti until it expires, rejects replayed proofs across every instance */
+pub async fn consume_dpop_jti(db_pool: &DbPool, jti: &str) -> Result<(), AppError> {
+    let time_now = Utc::now().timestamp();
+
+    let sql_query = sqlx::query("DELETE FROM dpop_proofs WHERE expires_at <= ?");
+    let _ = sql_query
+        .bind(time_now)
+        .execute(db_pool)
+        .await?;
+
+    /* * * proof is accepted within max age on both sides of iat */
+    let expires_at = time_now + CHRONO_DPOP_PROOF_MAX_AGE.num_seconds() * 2;
+    let sql_query = sqlx::query("INSERT IGNORE INTO dpop_proofs (jti, expires_at) VALUES (?, ?)");
+    let query_result = sql_query
+        .bind(hash_token(jti))
+        .bind(expires_at)
+        .execute(db_pool)
+        .await?;
+    if query_result.rows_affected() != 1 {
+        return Err(dpop_error("proof jti has already been used."));
+    }
+
+    Ok(())
+}
+/* * end store proof jti until it expires, rejects replayed proofs across every instance */
+
+/* * verify DPoP proof (RFC 9449) and return jwk thumbprint of the proof key, caller consumes jti */
+pub fn verify_dpop_proof(
+    request: &HttpRequest,
+    app_state: &AppState,
+    proof: &str,
+    access_token: Option<&str>
+) -> Result<DpopProof, AppError>
+{
+    /* * split compact jws */
+    let mut segments = proof.split('.');
+    let (Some(encoded_header), Some(encoded_claims), Some(encoded_signature), None) =
+        (segments.next(), segments.next(), segments.next(), segments.next())
+    else {
+        return Err(dpop_error("proof must be a compact JWS."));
+    };
+    let header = URL_SAFE_NO_PAD
+        .decode(encoded_header)
+        .ok()
+        .and_then(|header| serde_json::from_slice::<DpopHeader>(&header).ok())
+        .ok_or_else(|| dpop_error("proof header is malformed."))?;
+    let claims = URL_SAFE_NO_PAD
+        .decode(encoded_claims)
+        .ok()
+        .and_then(|claims| serde_json::from_slice::<DpopClaims>(&claims).ok())
+        .ok_or_else(|| dpop_error("proof claims are malformed."))?;
+    let signature = URL_SAFE_NO_PAD
+        .decode(encoded_signature)
+        .map_err(|_| dpop_error("proof signature is malformed."))?;
+    /* * end split compact jws */
+
+    /* * only ES256 proofs with P-256 public jwk are supported */
+    if header.typ != "dpop+jwt" {
+        return Err(dpop_error("proof typ must be 'dpop+jwt'."));
+    }
+    if header.alg != "ES256" || header.jwk.kty != "EC" || header.jwk.crv != "P-256" {
+        return Err(dpop_error("only ES256 proofs with a P-256 jwk are supported."));
+    }
+    /* * end only ES256 proofs with P-256 public jwk are supported */
+
+    /* * verify proof signature with embedded jwk */
+    let x = URL_SAFE_NO_PAD.decode(&header.jwk.x).ok().filter(|x| x.len() == 32);
+    let y = URL_SAFE_NO_PAD.decode(&header.jwk.y).ok().filter(|y| y.len() == 32);
+    let (Some(x), Some(y)) = (x, y) else {
+        return Err(dpop_error("proof jwk coordinates are invalid."));
+    };
+    let encoded_point = EncodedPoint::from_affine_coordinates(x.as_slice().into(), y.as_slice().into(), false);
+    let verifying_key = VerifyingKey::from_encoded_point(&encoded_point)
+        .map_err(|_| dpop_error("proof jwk is not a valid P-256 public key."))?;
+    let signature = Signature::from_slice(&signature)
+        .map_err(|_| dpop_error("proof signature is not a valid ES256 signature."))?;
+    let signing_input = format!("{}.{}", encoded_header, encoded_claims);
+    verifying_key
+        .verify(signing_input.as_bytes(), &signature)
+        .map_err(|_| dpop_error("proof signature does not match proof jwk."))?;
+    /* * end verify proof signature with embedded jwk */
+
+    /* * proof must be made for this request */
+    if !claims.htm.eq_ignore_ascii_case(request.method().as_str()) {
+        return Err(dpop_error("proof htm does not match request method."));
+    }
+    let htu = claims.htu.split(['?', '#']).next().unwrap_or_default();
+    if htu != get_request_url(request, app_state) {
+        return Err(dpop_error("proof htu does not match request url."));
+    }
+    /* * end proof must be made for this request */
+
+    /* * proof must be fresh & carry a nonce issued by this server */
+    let time_now = Utc::now().timestamp();
+    let max_age = CHRONO_DPOP_PROOF_MAX_AGE.num_seconds().unsigned_abs();
+    if time_now.abs_diff(claims.iat) > max_age {
+        return Err(dpop_error("proof iat is outside the accepted time window."));
+    }
+    match claims.nonce.as_deref() {
+        Some(nonce) if verify_dpop_nonce(app_state, nonce) => (),
+        _ => return Err(use_dpop_nonce_error(app_state, access_token.is_some()))
+    }
+    /* * end proof must be fresh & carry a nonce issued by this server */
+
+    /* * proof presented with access token must hash that token */
+    if let Some(access_token) = access_token {
+        let ath = URL_SAFE_NO_PAD.encode(Sha256::digest(access_token.as_bytes()));
+        if claims.ath.as_deref() != Some(ath.as_str()) {
+            return Err(dpop_error("proof ath does not match access token."));
+        }
+    }
+    /* * end proof presented with access token must hash that token */
+
+    Ok(
+        DpopProof {
+            jkt: jwk_thumbprint(&header.jwk),
+            jti: claims.jti
+        }
+    )
+}
+/* * end verify DPoP proof (RFC 9449) and return jwk thumbprint of the proof key, caller consumes jti */
+
+#[cfg(test)]
+mod tests {
+    use actix_web::test::TestRequest;
+    use p256::ecdsa::{
+        SigningKey,
+        signature::Signer
+    };
+    use sqlx::mysql::MySqlPoolOptions;
+
+    use crate::{
+        mailer::build_mailer,
+        auth::{
+            build_credential_verifier,
+            build_token_format
+        },
+        types::{
+            TokenConfig,
+            SessionConfig,
+            RetentionConfig,
+            PreconditionConfig,
+            WebauthnConfig,
+            CsrfConfig,
+            ClientsConfig,
+            MtlsConfig
+        }
+    };
+
+    use super::*;
+
+    const PUBLIC_URL: &str = "http://localhost:3001";
+    const TOKEN_PATH: &str = "/api/auth/token";
+
+    /* * * lazy pool never connects, proof & nonce checks never touch the database */
+    fn app_state(secret_access_token: &str) -> AppState {
+        let db_pool = MySqlPoolOptions::new().connect_lazy("mysql://localhost/rst04_jwt").unwrap();
+        AppState {
+            secret_access_token: String::from(secret_access_token),
+            secret_refresh_token: String::from("test_secret_refresh_token"),
+            public_url: String::from(PUBLIC_URL),
+            token_format: build_token_format("jwt", secret_access_token, "test_secret_refresh_token", None, None).unwrap(),
+            tokens: TokenConfig::from_minutes(5, 10, 60).unwrap(),
+            sessions: SessionConfig::from_minutes(30, 60).unwrap(),
+            retention: RetentionConfig::from_days(30).unwrap(),
+            preconditions: PreconditionConfig { if_match_required: false },
+            webauthn: WebauthnConfig {
+                rp_id: String::from("localhost"),
+                rp_name: String::from("backend_https"),
+                rp_origin: String::from(PUBLIC_URL)
+            },
+            csrf: CsrfConfig::from_list(PUBLIC_URL).unwrap(),
+            clients: ClientsConfig::from_list("body", "").unwrap(),
+            mtls: MtlsConfig::from_list("").unwrap(),
+            mailer: build_mailer("log", None, "no-reply@localhost").unwrap(),
+            credential_verifier: build_credential_verifier("db", &db_pool, None, None, false, None, "admin").unwrap(),
+            db_pool
+        }
+    }
+
+    fn nonce_at(app_state: &AppState, issued_at: i64) -> String {
+        let signature = dpop_nonce_mac(app_state, issued_at).finalize().into_bytes();
+        format!("{}.{}", issued_at, URL_SAFE_NO_PAD.encode(signature))
+    }
+
+    fn proof(htm: &str, iat: i64, nonce: Option<&str>, ath: Option<&str>) -> String {
+        let signing_key = SigningKey::from_slice(&[7u8; 32]).unwrap();
+        let point = signing_key.verifying_key().to_encoded_point(false);
+        let header = json!({
+            "typ": "dpop+jwt",
+            "alg": "ES256",
+            "jwk": {
+                "kty": "EC",
+                "crv": "P-256",
+                "x": URL_SAFE_NO_PAD.encode(point.x().unwrap()),
+                "y": URL_SAFE_NO_PAD.encode(point.y().unwrap())
+            }
+        });
+        let claims = json!({ "jti": "jti-1", "htm": htm, "htu": format!("{}{}", PUBLIC_URL, TOKEN_PATH), "iat": iat, "nonce": nonce, "ath": ath });
+        let signing_input = format!("{}.{}", URL_SAFE_NO_PAD.encode(header.to_string()), URL_SAFE_NO_PAD.encode(claims.to_string()));
+        let signature: Signature = signing_key.sign(signing_input.as_bytes());
+
+        format!("{}.{}", signing_input, URL_SAFE_NO_PAD.encode(signature.to_bytes()))
+    }
+
+    fn verify(app_state: &AppState, proof: &str, access_token: Option<&str>) -> Result<DpopProof, AppError> {
+        let request = TestRequest::post().uri(TOKEN_PATH).to_http_request();
+        verify_dpop_proof(&request, app_state, proof, access_token)
+    }
+
+    #[actix_web::test]
+    async fn nonce_verifies_only_fresh_nonces_of_this_server() {
+        let app_state = app_state("test_secret_access_token");
+        let time_now = Utc::now().timestamp();
+
+        assert!(verify_dpop_nonce(&app_state, &generate_dpop_nonce(&app_state)));
+        assert!(!verify_dpop_nonce(&app_state, &generate_dpop_nonce(&self::app_state("other_secret"))));
+        assert!(!verify_dpop_nonce(&app_state, &nonce_at(&app_state, time_now - CHRONO_DPOP_NONCE_EXPIRED.num_seconds() - 1)));
+        assert!(!verify_dpop_nonce(&app_state, &nonce_at(&app_state, time_now + 60)));
+        assert!(!verify_dpop_nonce(&app_state, &nonce_at(&app_state, i64::MIN)));
+        assert!(!verify_dpop_nonce(&app_state, &format!("{}.AAAA", time_now)));
+        assert!(!verify_dpop_nonce(&app_state, "not-a-nonce"));
+    }
+
+    #[actix_web::test]
+    async fn nonce_key_is_not_the_token_secret() {
+        let app_state = app_state("test_secret_access_token");
+
+        assert_ne!(dpop_nonce_key(&app_state), app_state.secret_access_token.as_bytes());
+    }
+
+    #[actix_web::test]
+    async fn proof_verifies_and_binds_the_jwk_thumbprint() {
+        let app_state = app_state("test_secret_access_token");
+        let nonce = generate_dpop_nonce(&app_state);
+        let proof = proof("POST", Utc::now().timestamp(), Some(&nonce), None);
+
+        let verified = verify(&app_state, &proof, None).unwrap();
+        assert_eq!(verified.jti, "jti-1");
+        assert_eq!(verified.jkt.len(), 43);
+    }
+
+    #[actix_web::test]
+    async fn proof_rejects_stale_or_overflowing_iat_and_missing_nonce() {
+        let app_state = app_state("test_secret_access_token");
+        let nonce = generate_dpop_nonce(&app_state);
+        let time_now = Utc::now().timestamp();
+        let max_age = CHRONO_DPOP_PROOF_MAX_AGE.num_seconds();
+
+        for iat in [time_now - max_age - 1, time_now + max_age + 1, i64::MIN, i64::MAX] {
+            assert!(matches!(verify(&app_state, &proof("POST", iat, Some(&nonce), None), None), Err(AppError::Unauthorized(_))));
+        }
+        assert!(matches!(verify(&app_state, &proof("POST", time_now, None, None), None), Err(AppError::UseDpopNonce(_))));
+    }
+
+    #[actix_web::test]
+    async fn proof_rejects_other_request_token_or_signature() {
+        let app_state = app_state("test_secret_access_token");
+        let nonce = generate_dpop_nonce(&app_state);
+        let time_now = Utc::now().timestamp();
+        let ath = URL_SAFE_NO_PAD.encode(Sha256::digest(b"access-token"));
+
+        assert!(verify(&app_state, &proof("GET", time_now, Some(&nonce), None), None).is_err());
+        assert!(verify(&app_state, &proof("POST", time_now, Some(&nonce), Some(&ath)), Some("access-token")).is_ok());
+        assert!(verify(&app_state, &proof("POST", time_now, Some(&nonce), Some(&ath)), Some("other-token")).is_err());
+
+        let mut tampered = proof("POST", time_now, Some(&nonce), None);
+        tampered.replace_range(tampered.len() - 4.., "AAAA");
+        assert!(verify(&app_state, &tampered, None).is_err());
+    }
+}
@@ -1,142 +1,926 @@
 use actix_web::{
     web,
+    Either,
     Responder, 
     HttpResponse,
     http::StatusCode,
     cookie::{
         Cookie,
-        time::OffsetDateTime
+        time::{
+            Duration as ActixDuration,
+            OffsetDateTime
+        }
     },
     HttpRequest
 };
 use serde_json::json;
 
 use crate::{
-    types::AppState,
+    types::{
+        AppState,
+        ClientPolicy,
+        TokenTransport
+    },
+    tls::ClientCertificate,
+    errors::{
+        AppError,
+        AppErrorMessage
+    },
+    audit::{
+        record_audit_log,
+        AuditEntry
+    },
     auth::{
         services::{
             signin::signin_service,
             refresh::refresh_token_service,
-            logout::logout_service
+            logout::logout_service,
+            impersonate::impersonate_service,
+            mtls::mtls_token_service,
+            passkey_register::{
+                passkey_register_start_service,
+                passkey_register_finish_service
+            },
+            passkey_signin::{
+                passkey_signin_start_service,
+                passkey_signin_finish_service
+            },
+            passkeys::{
+                get_passkeys_service,
+                delete_passkey_service
+            },
+            sessions::{
+                get_sessions_service,
+                revoke_session_service,
+                revoke_all_sessions_service
+            },
+            security_events::{
+                get_security_events_service,
+                disavow_security_event_service
+            },
+            password_reset::password_reset_service
         },
         model::{
             In,
-            CredentialsPayload
+            CredentialsPayload,
+            PasskeyRegistrationPayload,
+            PasskeySigninStartPayload,
+            PasskeyAssertionPayload,
+            PasswordResetPayload,
+            RefreshTokenPayload,
+            TokenQuery
         },
         types::{
             ResponseSignin,
-            ResponseRefreshToken 
+            ResponseRefreshToken,
+            ResponseMtlsToken,
+            ResponseImpersonate,
+            ServiceOkSignin
+        },
+        constants::{
+            ACCESS_TOKEN_COOKIE,
+            REFRESH_TOKEN_COOKIE,
+            TOKEN_TYPE_BEARER,
+            TOKEN_TYPE_DPOP,
+            DPOP_NONCE_HEADER
+        },
+        helpers::{
+            get_client_info,
+            get_client_policy,
+            get_refresh_token,
+            generate_secret_token,
+            render_form_page
         },
-        constants::ACTIX_REFRESH_TOKEN_EXPIRED
+        dpop::{
+            get_dpop_jkt,
+            generate_dpop_nonce
+        },
+        csrf::{
+            verify_origin,
+            build_csrf_token_cookie,
+            delete_csrf_token_cookie
+        },
+        JwtAuth
     }
 };
 
+/* * build access_token cookie for browser_mode clients */
+fn build_access_token_cookie(access_token: String, max_age: ActixDuration) -> Cookie<'static> {
+    Cookie::build(ACCESS_TOKEN_COOKIE, access_token)
+        .secure(true)
+        .same_site(actix_web::cookie::SameSite::None)
+        .http_only(true)
+        .max_age(max_age)
+        .path("/")
+        .finish()
+}
+/* * end build access_token cookie for browser_mode clients */
+
+/* * build expired access_token cookie for removing it from client */
+fn delete_access_token_cookie() -> Cookie<'static> {
+    Cookie::build(ACCESS_TOKEN_COOKIE, "")
+        .http_only(true)
+        .secure(true)
+        .same_site(actix_web::cookie::SameSite::None)
+        .path("/")
+        .expires(OffsetDateTime::now_utc())
+        .finish()
+}
+/* * end build expired access_token cookie for removing it from client */
+
+/* * build refresh_token cookie */
+fn build_refresh_token_cookie(refresh_token: String, max_age: ActixDuration) -> Cookie<'static> {
+    Cookie::build(REFRESH_TOKEN_COOKIE, refresh_token)
+        .secure(true)
+        .same_site(actix_web::cookie::SameSite::None)
+        .http_only(true)
+        .max_age(max_age)
+        .path("/")
+        .finish()
+}
+/* * end build refresh_token cookie */
+
+/* * issued tokens split into cookies & response body fields following client transport policy */
+struct TokenDelivery {
+    access_token: Option<String>,
+    refresh_token: Option<String>,
+    csrf_token: Option<String>,
+    cookies: Vec<Cookie<'static>>
+}
+
+fn deliver_tokens(
+    policy: &ClientPolicy,
+    access_token: String,
+    access_token_max_age: ActixDuration,
+    refresh_token: String,
+    refresh_token_max_age: ActixDuration,
+    browser_mode: bool
+) -> TokenDelivery
+{
+    let mut cookies = Vec::new();
+
+    /* * cookie transport needs csrf_token for double-submit check */
+    let csrf_token = match policy.allows(TokenTransport::Cookie) {
+        true => {
+            let csrf_token = generate_secret_token();
+            cookies.push(build_refresh_token_cookie(refresh_token.clone(), refresh_token_max_age));
+            cookies.push(build_csrf_token_cookie(csrf_token.clone(), refresh_token_max_age));
+            Some(csrf_token)
+        },
+        false => None
+    };
+    /* * end cookie transport needs csrf_token for double-submit check */
+
+    /* * browser_mode keeps access_token out of javascript readable response body */
+    let access_token = match browser_mode && csrf_token.is_some() {
+        true => {
+            cookies.push(build_access_token_cookie(access_token, access_token_max_age));
+            None
+        },
+        false => Some(access_token)
+    };
+    /* * end browser_mode keeps access_token out of javascript readable response body */
+
+    /* * body & header transport clients must store refresh_token by themselves */
+    let refresh_token = (policy.allows(TokenTransport::Body) || policy.allows(TokenTransport::Header))
+        .then_some(refresh_token);
+    /* * end body & header transport clients must store refresh_token by themselves */
+
+    TokenDelivery { access_token, refresh_token, csrf_token, cookies }
+}
+/* * end issued tokens split into cookies & response body fields following client transport policy */
+
+/* * build signin response, tokens delivered following client transport policy */
+fn signin_response(user: ServiceOkSignin, policy: &ClientPolicy, app_state: &AppState) -> HttpResponse {
+    let status_code = StatusCode::OK;
+
+    let delivery = deliver_tokens(
+        policy,
+        user.encoded_access_token,
+        user.access_token_max_age,
+        user.encoded_refresh_token,
+        user.refresh_token_max_age,
+        user.browser_mode
+    );
+
+    let response_data = ResponseSignin {
+        token_type: user.token_type,
+        scope: user.scope,
+        access_token: delivery.access_token,
+        refresh_token: delivery.refresh_token,
+        csrf_token: delivery.csrf_token,
+        session_expiry: user.session_expiry
+    };
+    let success_message = json!({
+        "success": true,
+        "code": status_code.as_u16(),
+        "message": format!("user '{}' has successfully logged in.", user.username),
+        "data": response_data
+    });
+
+    let mut response = HttpResponse::build(status_code);
+    for cookie in delivery.cookies {
+        response.cookie(cookie);
+    }
+    if user.token_type == TOKEN_TYPE_DPOP {
+        response.insert_header((DPOP_NONCE_HEADER, generate_dpop_nonce(app_state)));
+    }
+    response.json(success_message)
+}
+/* * end build signin response, tokens delivered following client transport policy */
+
+/* * build expired refresh_token cookie for removing it from client */
+fn delete_refresh_token_cookie() -> Cookie<'static> {
+    Cookie::build(REFRESH_TOKEN_COOKIE, "")
+        .http_only(true)
+        .secure(true)
+        .same_site(actix_web::cookie::SameSite::None)
+        .path("/")
+        .expires(OffsetDateTime::now_utc())
+        .finish()
+}
+/* * end build expired refresh_token cookie for removing it from client */
+
 pub async fn signin(
+    request: HttpRequest,
     app_state: web::Data<AppState>, 
     payload: web::Json<In<CredentialsPayload>>
 ) -> impl Responder 
 {
     let app_state = app_state.get_ref();
     let payload = payload.into_inner().credentials;
+    let client = get_client_info(&request);
+    let username = payload.username.clone();
 
-    let signin_service = signin_service(app_state, payload).await;
+    let signin_options = match verify_origin(&request, &app_state.csrf).and_then(|_| get_client_policy(&request, app_state)) {
+        Ok(policy) => get_dpop_jkt(&request, app_state).await.map(|dpop_jkt| (policy, dpop_jkt)),
+        Err(e) => Err(e)
+    };
+    let signin_service = match signin_options {
+        Ok((policy, dpop_jkt)) => signin_service(app_state, payload, client.clone(), dpop_jkt).await.map(|user| (user, policy)),
+        Err(e) => Err(e)
+    };
+    let audit_entry = AuditEntry {
+        event: "auth.signin",
+        actor: Some(&username),
+        target: Some(&username),
+        success: signin_service.is_ok(),
+        reason: signin_service.as_ref().err().map(AppError::message),
+        client: &client
+    };
+    record_audit_log(&app_state.db_pool, audit_entry).await;
     match signin_service {
-        Ok(user) => {
+        Ok((user, policy)) => signin_response(user, &policy, app_state),
+        Err(e) => HttpResponse::from_error(e)
+    }
+}
 
+pub async fn refresh_token(
+    request: HttpRequest,
+    app_state: web::Data<AppState>,
+    payload: Option<web::Json<RefreshTokenPayload>>
+) -> impl Responder {
+    let app_state = &app_state.get_ref();
+    let client = get_client_info(&request);
+    let body_refresh_token = payload.and_then(|payload| payload.into_inner().refresh_token);
+
+    let refresh_token = get_client_policy(&request, app_state)
+        .and_then(|policy| {
+            get_refresh_token(&request, body_refresh_token, app_state, &policy)
+                .map(|refresh_token| (refresh_token, policy))
+        });
+    let refresh_token = match refresh_token {
+        Ok((refresh_token, policy)) => get_dpop_jkt(&request, app_state).await.map(|dpop_jkt| (refresh_token, policy, dpop_jkt)),
+        Err(e) => Err(e)
+    };
+    let refresh_token_service = match refresh_token {
+        Ok((refresh_token, policy, dpop_jkt)) => refresh_token_service(app_state, refresh_token, dpop_jkt, client.clone()).await.map(|token| (token, policy)),
+        Err(e) => Err(e)
+    };
+    let username = refresh_token_service.as_ref().ok().map(|(token, _)| token.username.as_str());
+    let audit_entry = AuditEntry {
+        event: "auth.refresh",
+        actor: username,
+        target: username,
+        success: refresh_token_service.is_ok(),
+        reason: refresh_token_service.as_ref().err().map(AppError::message),
+        client: &client
+    };
+    record_audit_log(&app_state.db_pool, audit_entry).await;
+    match refresh_token_service {
+        Ok((token, policy)) => {
             let status_code = StatusCode::OK;
 
-            let create_cookie = Cookie::build("refresh_token", user.encoded_refresh_token)
-                .secure(true)
-                .same_site(actix_web::cookie::SameSite::None)
-                .http_only(true)
-                .max_age(ACTIX_REFRESH_TOKEN_EXPIRED)
-                .path("/")
-                .finish();
+            let delivery = deliver_tokens(
+                &policy,
+                token.access_token,
+                token.access_token_max_age,
+                token.refresh_token,
+                token.refresh_token_previous_max_age,
+                token.browser_mode
+            );
 
-            let response_data = ResponseSignin {
-                access_token: user.encoded_access_token
+            let response_data = ResponseRefreshToken {
+                token_type: token.token_type,
+                scope: token.scope,
+                access_token: delivery.access_token,
+                refresh_token: delivery.refresh_token,
+                csrf_token: delivery.csrf_token,
+                session_expiry: token.session_expiry
             };
+
             let success_message = json!({
                 "success": true,
                 "code": status_code.as_u16(),
-                "message": format!("user '{}' has successfully logged in.", user.username),
-                "data": response_data
+                "data": response_data 
             });
+            let mut response = HttpResponse::build(status_code);
+            for cookie in delivery.cookies {
+                response.cookie(cookie);
+            }
+            if token.token_type == TOKEN_TYPE_DPOP {
+                response.insert_header((DPOP_NONCE_HEADER, generate_dpop_nonce(app_state)));
+            }
+            response.json(success_message)
+        },
+        Err(e) => HttpResponse::from_error(e)
+    }
+}
+
+pub async fn logout(
+    app_state: web::Data<AppState>,
+    request: HttpRequest,
+    payload: Option<web::Json<RefreshTokenPayload>>
+) -> impl Responder {
+    let app_state = app_state.get_ref();
+    let client = get_client_info(&request);
+    let body_refresh_token = payload.and_then(|payload| payload.into_inner().refresh_token);
+
+    let refresh_token = get_client_policy(&request, app_state)
+        .and_then(|policy| get_refresh_token(&request, body_refresh_token, app_state, &policy));
+    let logout_service = match refresh_token {
+        Ok(refresh_token) => logout_service(app_state, refresh_token).await,
+        Err(e) => Err(e)
+    };
+    let username = logout_service.as_deref().ok();
+    let audit_entry = AuditEntry {
+        event: "auth.logout",
+        actor: username,
+        target: username,
+        success: logout_service.is_ok(),
+        reason: logout_service.as_ref().err().map(AppError::message),
+        client: &client
+    };
+    record_audit_log(&app_state.db_pool, audit_entry).await;
+    match logout_service {
+        Ok(user) => {
+            let status_code = StatusCode::OK;
+
+            let delete_cookie = delete_refresh_token_cookie();
 
+            let success_message = json!({
+                "success": true,
+                "code": status_code.as_u16(),
+                "message": format!("user '{}' successfully logout.", user)
+            });
             HttpResponse::build(status_code)
-                .cookie(create_cookie)
+                .cookie(delete_cookie)
+                .cookie(delete_csrf_token_cookie())
+                .cookie(delete_access_token_cookie())
                 .json(success_message)
         },
-
         Err(e) => HttpResponse::from_error(e)
     }
 }
 
-pub async fn refresh_token(
+pub async fn impersonate(
+    auth: JwtAuth,
     request: HttpRequest,
-    app_state: web::Data<AppState>
+    app_state: web::Data<AppState>,
+    path: web::Path<String>
 ) -> impl Responder {
-    let refresh_token_cookie = request.cookie("refresh_token");
-    let app_state = &app_state.get_ref();
+    if let Err(e) = auth.require_admin().and_then(|_| auth.require_not_impersonating()) {
+        return HttpResponse::from_error(e);
+    }
+    let app_state = app_state.get_ref();
+    let target_username = path.into_inner();
+    let client = get_client_info(&request);
 
-    let refresh_token_service = refresh_token_service(app_state, refresh_token_cookie).await;
-    match refresh_token_service {
+    let impersonate_service = impersonate_service(app_state, &auth.claims.username, &target_username).await;
+    let audit_entry = AuditEntry {
+        event: "auth.impersonate",
+        actor: Some(&auth.claims.username),
+        target: Some(&target_username),
+        success: impersonate_service.is_ok(),
+        reason: impersonate_service.as_ref().err().map(AppError::message),
+        client: &client
+    };
+    record_audit_log(&app_state.db_pool, audit_entry).await;
+    match impersonate_service {
         Ok(token) => {
             let status_code = StatusCode::OK;
+            let response_data = ResponseImpersonate {
+                token_type: TOKEN_TYPE_BEARER,
+                scope: token.scope,
+                access_token: token.access_token,
+                expires_in: token.expires_in,
+                username: token.username,
+                impersonated_by: token.impersonated_by
+            };
+            let success_message = json!({
+                "success": true,
+                "code": status_code.as_u16(),
+                "data": response_data
+            });
+            HttpResponse::build(status_code).json(success_message)
+        },
+        Err(e) => HttpResponse::from_error(e)
+    }
+}
 
-            let update_cookie = Cookie::build("refresh_token", token.refresh_token.clone())
-                .http_only(true)
-                .max_age(token.refresh_token_previous_max_age)
-                .path("/")
-                .finish();
+pub async fn mtls_token(
+    request: HttpRequest,
+    app_state: web::Data<AppState>
+) -> impl Responder {
+    let app_state = app_state.get_ref();
+    let client = get_client_info(&request);
+    let certificate = request.conn_data::<ClientCertificate>();
 
-            let response_data = ResponseRefreshToken {
+    let mtls_token_service = mtls_token_service(app_state, certificate).await;
+    let principal = mtls_token_service.as_ref().ok().map(|token| token.principal.as_str());
+    let audit_entry = AuditEntry {
+        event: "auth.mtls_token",
+        actor: principal,
+        target: principal,
+        success: mtls_token_service.is_ok(),
+        reason: mtls_token_service.as_ref().err().map(AppError::message),
+        client: &client
+    };
+    record_audit_log(&app_state.db_pool, audit_entry).await;
+    match mtls_token_service {
+        Ok(token) => {
+            let status_code = StatusCode::OK;
+            let response_data = ResponseMtlsToken {
+                token_type: TOKEN_TYPE_BEARER,
+                scope: token.scope,
                 access_token: token.access_token,
-                refresh_token: token.refresh_token
+                expires_in: token.expires_in,
+                principal: token.principal
             };
+            let success_message = json!({
+                "success": true,
+                "code": status_code.as_u16(),
+                "data": response_data
+            });
+            HttpResponse::build(status_code).json(success_message)
+        },
+        Err(e) => HttpResponse::from_error(e)
+    }
+}
 
+/* * issue DPoP nonce, clients must include it in every proof */
+pub async fn dpop_nonce(
+    app_state: web::Data<AppState>
+) -> impl Responder {
+    let app_state = app_state.get_ref();
+    let status_code = StatusCode::OK;
+    let nonce = generate_dpop_nonce(app_state);
+
+    let success_message = json!({
+        "success": true,
+        "code": status_code.as_u16(),
+        "data": { "dpop_nonce": nonce }
+    });
+    HttpResponse::build(status_code)
+        .insert_header((DPOP_NONCE_HEADER, nonce))
+        .json(success_message)
+}
+/* * end issue DPoP nonce, clients must include it in every proof */
+
+/* * publish v4.public access token verifying key, resource servers verify tokens without any secret */
+pub async fn paseto_public_key(
+    app_state: web::Data<AppState>
+) -> impl Responder {
+    let status_code = StatusCode::OK;
+
+    match app_state.token_format.public_key() {
+        Some(public_key) => {
             let success_message = json!({
                 "success": true,
                 "code": status_code.as_u16(),
-                "data": response_data 
+                "data": {
+                    "version": "v4",
+                    "purpose": "public",
+                    "paserk": public_key
+                }
             });
             HttpResponse::build(status_code)
-                .cookie(update_cookie)
+                .insert_header(("Cache-Control", "public, max-age=300"))
                 .json(success_message)
         },
+        None => {
+            let app_err_message: AppErrorMessage<Option<bool>> = AppErrorMessage {
+                code: StatusCode::NOT_FOUND.as_u16(),
+                message: String::from("the configured token format has no public key."),
+                details: None
+            };
+            HttpResponse::from_error(AppError::NotFound(app_err_message.into()))
+        }
+    }
+}
+/* * end publish v4.public access token verifying key, resource servers verify tokens without any secret */
+
+pub async fn passkey_register_start(
+    auth: JwtAuth,
+    app_state: web::Data<AppState>
+) -> impl Responder {
+    if let Err(e) = auth.require_not_impersonating() {
+        return HttpResponse::from_error(e);
+    }
+    let app_state = app_state.get_ref();
+    let username = auth.claims.username;
+
+    let passkey_register_start_service = passkey_register_start_service(app_state, &username).await;
+    match passkey_register_start_service {
+        Ok(options) => {
+            let status_code = StatusCode::OK;
+            let success_message = json!({
+                "success": true,
+                "code": status_code.as_u16(),
+                "data": options
+            });
+            HttpResponse::build(status_code).json(success_message)
+        },
         Err(e) => HttpResponse::from_error(e)
     }
 }
 
-pub async fn logout(
+pub async fn passkey_register_finish(
+    auth: JwtAuth,
+    app_state: web::Data<AppState>,
+    payload: web::Json<In<PasskeyRegistrationPayload>>
+) -> impl Responder {
+    if let Err(e) = auth.require_not_impersonating() {
+        return HttpResponse::from_error(e);
+    }
+    let app_state = app_state.get_ref();
+    let username = auth.claims.username;
+    let payload = payload.into_inner().credentials;
+
+    let passkey_register_finish_service = passkey_register_finish_service(app_state, &username, payload).await;
+    match passkey_register_finish_service {
+        Ok(credential_id) => {
+            let status_code = StatusCode::CREATED;
+            let success_message = json!({
+                "success": true,
+                "code": status_code.as_u16(),
+                "message": format!("passkey '{}' has been successfully registered for user '{}'.", credential_id, username)
+            });
+            HttpResponse::build(status_code).json(success_message)
+        },
+        Err(e) => HttpResponse::from_error(e)
+    }
+}
+
+pub async fn passkey_signin_start(
     app_state: web::Data<AppState>,
+    payload: web::Json<In<PasskeySigninStartPayload>>
+) -> impl Responder {
+    let app_state = app_state.get_ref();
+    let payload = payload.into_inner().credentials;
+
+    let passkey_signin_start_service = passkey_signin_start_service(app_state, payload).await;
+    match passkey_signin_start_service {
+        Ok(options) => {
+            let status_code = StatusCode::OK;
+            let success_message = json!({
+                "success": true,
+                "code": status_code.as_u16(),
+                "data": options
+            });
+            HttpResponse::build(status_code).json(success_message)
+        },
+        Err(e) => HttpResponse::from_error(e)
+    }
+}
+
+pub async fn passkey_signin_finish(
     request: HttpRequest,
+    app_state: web::Data<AppState>,
+    payload: web::Json<In<PasskeyAssertionPayload>>
 ) -> impl Responder {
     let app_state = app_state.get_ref();
-    let refresh_token_cookie = request.cookie("refresh_token");
+    let payload = payload.into_inner().credentials;
+    let client = get_client_info(&request);
 
-    let logout_service = logout_service(app_state, refresh_token_cookie).await;
-    match logout_service {
-        Ok(user) => {
+    let signin_options = match verify_origin(&request, &app_state.csrf).and_then(|_| get_client_policy(&request, app_state)) {
+        Ok(policy) => get_dpop_jkt(&request, app_state).await.map(|dpop_jkt| (policy, dpop_jkt)),
+        Err(e) => Err(e)
+    };
+    let passkey_signin_finish_service = match signin_options {
+        Ok((policy, dpop_jkt)) => passkey_signin_finish_service(app_state, payload, client.clone(), dpop_jkt).await.map(|user| (user, policy)),
+        Err(e) => Err(e)
+    };
+    let username = passkey_signin_finish_service.as_ref().ok().map(|(user, _)| user.username.as_str());
+    let audit_entry = AuditEntry {
+        event: "auth.passkey_signin",
+        actor: username,
+        target: username,
+        success: passkey_signin_finish_service.is_ok(),
+        reason: passkey_signin_finish_service.as_ref().err().map(AppError::message),
+        client: &client
+    };
+    record_audit_log(&app_state.db_pool, audit_entry).await;
+    match passkey_signin_finish_service {
+        Ok((user, policy)) => signin_response(user, &policy, app_state),
+        Err(e) => HttpResponse::from_error(e)
+    }
+}
+
+pub async fn get_passkeys(
+    auth: JwtAuth,
+    app_state: web::Data<AppState>
+) -> impl Responder {
+    let app_state = app_state.get_ref();
+
+    let get_passkeys_service = get_passkeys_service(app_state, &auth.claims.username).await;
+    match get_passkeys_service {
+        Ok(passkeys) => {
             let status_code = StatusCode::OK;
+            let success_message = json!({
+                "success": true,
+                "code": status_code.as_u16(),
+                "message": "successfully retrieved all passkeys.",
+                "data": passkeys,
+                "length": passkeys.len()
+            });
+            HttpResponse::build(status_code).json(success_message)
+        },
+        Err(e) => HttpResponse::from_error(e)
+    }
+}
 
-            let delete_cookie = Cookie::build("refresh_token", "")
-                .http_only(true)
-                .secure(true)
-                .same_site(actix_web::cookie::SameSite::None)
-                .path("/")
-                .expires(OffsetDateTime::now_utc())
-                .finish();
+pub async fn delete_passkey(
+    auth: JwtAuth,
+    app_state: web::Data<AppState>,
+    path: web::Path<u32>
+) -> impl Responder {
+    if let Err(e) = auth.require_not_impersonating() {
+        return HttpResponse::from_error(e);
+    }
+    let app_state = app_state.get_ref();
+    let passkey_id_params = path.into_inner();
 
+    let delete_passkey_service = delete_passkey_service(app_state, &auth.claims.username, passkey_id_params).await;
+    match delete_passkey_service {
+        Ok(result) => {
+            let status_code = StatusCode::OK;
             let success_message = json!({
                 "success": true,
                 "code": status_code.as_u16(),
-                "message": format!("user '{}' successfully logout.", user)
+                "message": format!("passkey '{}' has been successfully deleted.", result)
+            });
+            HttpResponse::build(status_code).json(success_message)
+        },
+        Err(e) => HttpResponse::from_error(e)
+    }
+}
+
+pub async fn get_sessions(
+    auth: JwtAuth,
+    app_state: web::Data<AppState>
+) -> impl Responder {
+    let app_state = app_state.get_ref();
+
+    let get_sessions_service = get_sessions_service(app_state, &auth.claims.username).await;
+    match get_sessions_service {
+        Ok(sessions) => {
+            let status_code = StatusCode::OK;
+            let success_message = json!({
+                "success": true,
+                "code": status_code.as_u16(),
+                "message": "successfully retrieved all active sessions.",
+                "data": sessions,
+                "length": sessions.len()
+            });
+            HttpResponse::build(status_code).json(success_message)
+        },
+        Err(e) => HttpResponse::from_error(e)
+    }
+}
+
+pub async fn revoke_session(
+    auth: JwtAuth,
+    request: HttpRequest,
+    app_state: web::Data<AppState>,
+    path: web::Path<u32>
+) -> impl Responder {
+    if let Err(e) = auth.require_not_impersonating() {
+        return HttpResponse::from_error(e);
+    }
+    let app_state = app_state.get_ref();
+    let session_id_params = path.into_inner();
+    let client = get_client_info(&request);
+    let username = auth.claims.username;
+
+    let revoke_session_service = revoke_session_service(app_state, &username, session_id_params).await;
+    let audit_entry = AuditEntry {
+        event: "auth.session_revoke",
+        actor: Some(&username),
+        target: Some(&username),
+        success: revoke_session_service.is_ok(),
+        reason: revoke_session_service.as_ref().err().map(AppError::message),
+        client: &client
+    };
+    record_audit_log(&app_state.db_pool, audit_entry).await;
+    match revoke_session_service {
+        Ok(result) => {
+            let status_code = StatusCode::OK;
+            let success_message = json!({
+                "success": true,
+                "code": status_code.as_u16(),
+                "message": format!("session '{}' has been successfully revoked.", result)
+            });
+            HttpResponse::build(status_code).json(success_message)
+        },
+        Err(e) => HttpResponse::from_error(e)
+    }
+}
+
+pub async fn revoke_all_sessions(
+    auth: JwtAuth,
+    request: HttpRequest,
+    app_state: web::Data<AppState>
+) -> impl Responder {
+    if let Err(e) = auth.require_not_impersonating() {
+        return HttpResponse::from_error(e);
+    }
+    let app_state = app_state.get_ref();
+    let username = auth.claims.username;
+    let client = get_client_info(&request);
+
+    let revoke_all_sessions_service = revoke_all_sessions_service(app_state, &username).await;
+    let audit_entry = AuditEntry {
+        event: "auth.sessions_revoke_all",
+        actor: Some(&username),
+        target: Some(&username),
+        success: revoke_all_sessions_service.is_ok(),
+        reason: revoke_all_sessions_service.as_ref().err().map(AppError::message),
+        client: &client
+    };
+    record_audit_log(&app_state.db_pool, audit_entry).await;
+    match revoke_all_sessions_service {
+        Ok(result) => {
+            let status_code = StatusCode::OK;
+            let success_message = json!({
+                "success": true,
+                "code": status_code.as_u16(),
+                "message": format!("user '{}' has been signed out everywhere. {} session(s) revoked.", username, result)
             });
             HttpResponse::build(status_code)
-                .cookie(delete_cookie)
+                .cookie(delete_refresh_token_cookie())
+                .json(success_message)
+        },
+        Err(e) => HttpResponse::from_error(e)
+    }
+}
+
+pub async fn get_security_events(
+    auth: JwtAuth,
+    app_state: web::Data<AppState>
+) -> impl Responder {
+    let app_state = app_state.get_ref();
+
+    let get_security_events_service = get_security_events_service(app_state, &auth.claims.username).await;
+    match get_security_events_service {
+        Ok(security_events) => {
+            let status_code = StatusCode::OK;
+            let success_message = json!({
+                "success": true,
+                "code": status_code.as_u16(),
+                "message": "successfully retrieved all security events.",
+                "data": security_events,
+                "length": security_events.len()
+            });
+            HttpResponse::build(status_code).json(success_message)
+        },
+        Err(e) => HttpResponse::from_error(e)
+    }
+}
+
+/* * "this wasn't me" link from new device email, only renders confirmation form */
+pub async fn disavow_security_event_page(
+    app_state: web::Data<AppState>,
+    query: web::Query<TokenQuery>
+) -> impl Responder {
+    let token = query.into_inner().token;
+    let action = format!("{}/api/auth/security-events/disavow", app_state.public_url.trim_end_matches('/'));
+
+    render_form_page(
+        "secure your account",
+        "this will sign out every session of your account and email you a link to choose a new password.",
+        &action,
+        &[("token", &token)],
+        &[],
+        "sign out everywhere"
+    )
+}
+/* * end "this wasn't me" link from new device email, only renders confirmation form */
+
+pub async fn disavow_security_event(
+    request: HttpRequest,
+    app_state: web::Data<AppState>,
+    payload: web::Form<TokenQuery>
+) -> impl Responder {
+    let app_state = app_state.get_ref();
+    let token = payload.into_inner().token;
+    let client = get_client_info(&request);
+
+    let disavow_security_event_service = disavow_security_event_service(app_state, &token).await;
+    let username = disavow_security_event_service.as_ref().ok().map(|result| result.username.as_str());
+    let audit_entry = AuditEntry {
+        event: "auth.security_event_disavow",
+        actor: username,
+        target: username,
+        success: disavow_security_event_service.is_ok(),
+        reason: disavow_security_event_service.as_ref().err().map(AppError::message),
+        client: &client
+    };
+    record_audit_log(&app_state.db_pool, audit_entry).await;
+    match disavow_security_event_service {
+        Ok(result) => {
+            let status_code = StatusCode::OK;
+            let message = match result.reset_link_sent {
+                true => format!("every session of user '{}' has been signed out. a password reset link has been sent to your email.", result.username),
+                false => format!("every session of user '{}' has been signed out. please contact support to reset your password.", result.username)
+            };
+            let success_message = json!({
+                "success": true,
+                "code": status_code.as_u16(),
+                "message": message
+            });
+            HttpResponse::build(status_code)
+                .cookie(delete_refresh_token_cookie())
                 .json(success_message)
         },
         Err(e) => HttpResponse::from_error(e)
     }
+}
+
+/* * password reset link from email, only renders new password form */
+pub async fn password_reset_page(
+    app_state: web::Data<AppState>,
+    query: web::Query<TokenQuery>
+) -> impl Responder {
+    let token = query.into_inner().token;
+    let action = format!("{}/api/auth/password-reset", app_state.public_url.trim_end_matches('/'));
+
+    render_form_page(
+        "reset your password",
+        "choose a new password for your account.",
+        &action,
+        &[("token", &token)],
+        &["password", "confirm_password"],
+        "reset password"
+    )
+}
+/* * end password reset link from email, only renders new password form */
+
+pub async fn password_reset(
+    request: HttpRequest,
+    app_state: web::Data<AppState>,
+    payload: Either<web::Json<In<PasswordResetPayload>>, web::Form<PasswordResetPayload>>
+) -> impl Responder {
+    let app_state = app_state.get_ref();
+    /* * api clients send json, reset page submits html form */
+    let payload = match payload {
+        Either::Left(payload) => payload.into_inner().credentials,
+        Either::Right(payload) => payload.into_inner()
+    };
+    /* * end api clients send json, reset page submits html form */
+    let client = get_client_info(&request);
+
+    let password_reset_service = password_reset_service(app_state, payload).await;
+    let username = password_reset_service.as_deref().ok();
+    let audit_entry = AuditEntry {
+        event: "user.password_reset",
+        actor: username,
+        target: username,
+        success: password_reset_service.is_ok(),
+        reason: password_reset_service.as_ref().err().map(AppError::message),
+        client: &client
+    };
+    record_audit_log(&app_state.db_pool, audit_entry).await;
+    match password_reset_service {
+        Ok(user) => {
+            let status_code = StatusCode::OK;
+            let success_message = json!({
+                "success": true,
+                "code": status_code.as_u16(),
+                "message": format!("password of user '{}' has been successfully reset. please sign in again.", user)
+            });
+            HttpResponse::build(status_code).json(success_message)
+        },
+        Err(e) => HttpResponse::from_error(e)
+    }
 }
\ No newline at end of file
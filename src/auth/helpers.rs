@@ -1,5 +1,14 @@
 use actix_web::{
-    http::StatusCode,
+    HttpRequest,
+    HttpResponse,
+    http::{
+        StatusCode,
+        header::{
+            USER_AGENT,
+            CACHE_CONTROL,
+            REFERRER_POLICY
+        }
+    },
     cookie::time::Duration as ActixDuration
 };
 use argon2::{
@@ -7,31 +16,79 @@ use argon2::{
     PasswordHash, 
     PasswordVerifier,
 };
+use base64::{
+    Engine,
+    engine::general_purpose::URL_SAFE_NO_PAD
+};
 use chrono::Utc;
+use rand::{
+    RngCore,
+    rngs::OsRng
+};
+use sha2::{
+    Digest,
+    Sha256
+};
+use sqlx::Row;
 
 use crate::{
     db::DbPool, 
+    types::{
+        AppState,
+        SessionConfig,
+        ClientPolicy,
+        TokenTransport
+    },
     errors::{
         AppError, 
         AppErrorMessage
     },
-    auth::model::Credentials
+    auth::{
+        model::Credentials,
+        types::{
+            Claims,
+            ClientInfo,
+            ServiceOkSignin,
+            SessionExpiry,
+            SigninOptions,
+            Confirmation
+        },
+        token::TokenKind,
+        constants::{
+            CHRONO_WEBAUTHN_CHALLENGE_EXPIRED,
+            ROLE_ADMIN,
+            ROLE_SERVICE,
+            SCOPE_USERS_READ,
+            SCOPE_USERS_WRITE,
+            SCOPE_PROFILE_WRITE,
+            SCOPE_AUDIT_READ,
+            TOKEN_TYPE_BEARER,
+            TOKEN_TYPE_DPOP,
+            CLIENT_ID_HEADER,
+            REFRESH_TOKEN_COOKIE,
+            REFRESH_TOKEN_HEADER
+        },
+        csrf::verify_csrf
+    }
 };
 
-/* * get user credentials returns Result Query */
+/* * get user credentials returns Result Query, soft deleted users have no credentials */
 pub async fn get_user_credentials(
     db_pool: &DbPool,
     username: &str
 ) -> Result<Credentials, sqlx::Error>
 {
-    let sql_query = sqlx::query_as::<_, Credentials>("SELECT * FROM credentials WHERE username = ?");
+    let sql_query = sqlx::query_as::<_, Credentials>("SELECT credentials.password
+        FROM credentials INNER JOIN user ON user.username = credentials.username
+        WHERE credentials.username = ? AND user.deleted_at IS NULL
+    ");
     let query_result = sql_query
         .bind(username)
         .fetch_one(db_pool)
         .await;
     query_result
 }
-/* * get user credentials returns Result Query */
+/* * get user credentials returns Result Query, soft deleted users have no credentials */
 
 /* * verifying stored user password */
 pub fn verify_password(
@@ -66,4 +123,480 @@ pub fn convert_timestamp_to_actix_duration(timestamp: i64) -> ActixDuration {
 
     ActixDuration::seconds(duration)
 }
-/* * end convert timestamp to actix duration */
\ No newline at end of file
+/* * end convert timestamp to actix duration */
+
+/* * get client ip address & user agent from request */
+pub fn get_client_info(request: &HttpRequest) -> ClientInfo {
+    let ip_address = request
+        .connection_info()
+        .realip_remote_addr()
+        .map(|ip_address| ip_address.to_string());
+    let user_agent = request
+        .headers()
+        .get(USER_AGENT)
+        .and_then(|hv| hv.to_str().ok())
+        .map(|user_agent| user_agent.chars().take(255).collect::<String>());
+
+    ClientInfo { ip_address, user_agent }
+}
+/* * end get client ip address & user agent from request */
+
+/* * get token transport policy of client identified by X-Client-Id header */
+pub fn get_client_policy(request: &HttpRequest, app_state: &AppState) -> Result<ClientPolicy, AppError> {
+    let client_id = request
+        .headers()
+        .get(CLIENT_ID_HEADER)
+        .and_then(|hv| hv.to_str().ok());
+
+    app_state.clients
+        .get_policy(client_id)
+        .cloned()
+        .ok_or_else(|| {
+            let app_err_message: AppErrorMessage<Option<bool>> = AppErrorMessage {
+                code: StatusCode::UNAUTHORIZED.as_u16(),
+                message: format!("unknown client '{}'.", client_id.unwrap_or_default()),
+                details: None
+            };
+            AppError::Unauthorized(app_err_message.into())
+        })
+}
+/* * end get token transport policy of client identified by X-Client-Id header */
+
+/* * get refresh_token from header, body or cookie, only through transports allowed for client */
+pub fn get_refresh_token(
+    request: &HttpRequest,
+    body_refresh_token: Option<String>,
+    app_state: &AppState,
+    policy: &ClientPolicy
+) -> Result<Option<String>, AppError>
+{
+    let header_refresh_token = request
+        .headers()
+        .get(REFRESH_TOKEN_HEADER)
+        .and_then(|hv| hv.to_str().ok())
+        .map(str::to_string);
+    let cookie_refresh_token = request
+        .cookie(REFRESH_TOKEN_COOKIE)
+        .map(|cookie| cookie.value().to_string());
+
+    let candidates = [
+        (TokenTransport::Header, header_refresh_token),
+        (TokenTransport::Body, body_refresh_token),
+        (TokenTransport::Cookie, cookie_refresh_token)
+    ];
+    for (transport, refresh_token) in candidates {
+        let Some(refresh_token) = refresh_token.filter(|refresh_token| !refresh_token.is_empty()) else {
+            continue;
+        };
+        if !policy.allows(transport) {
+            let app_err_message: AppErrorMessage<Option<bool>> = AppErrorMessage {
+                code: StatusCode::FORBIDDEN.as_u16(),
+                message: format!("refresh_token transport '{}' is not allowed for this client.", transport.as_str()),
+                details: None
+            };
+            return Err(AppError::Forbidden(app_err_message.into()));
+        }
+        /* * * cookie is sent by browser automatically, must pass csrf check */
+        if transport == TokenTransport::Cookie {
+            verify_csrf(request, &app_state.csrf)?;
+        }
+        /* * * end cookie is sent by browser automatically, must pass csrf check */
+
+        return Ok(Some(refresh_token));
+    }
+
+    Ok(None)
+}
+/* * end get refresh_token from header, body or cookie, only through transports allowed for client */
+
+/* * generate random token id */
+pub fn generate_jti() -> String {
+    let mut jti = [0u8; 16];
+    OsRng.fill_bytes(&mut jti);
+
+    URL_SAFE_NO_PAD.encode(jti)
+}
+/* * end generate random token id */
+
+/* * generate random secret token for links sent by mail */
+pub fn generate_secret_token() -> String {
+    let mut token = [0u8; 32];
+    OsRng.fill_bytes(&mut token);
+
+    URL_SAFE_NO_PAD.encode(token)
+}
+/* * end generate random secret token for links sent by mail */
+
+/* * hash secret token before storing it in database */
+pub fn hash_token(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+/* * end hash secret token before storing it in database */
+
+/* * scopes a role is allowed to hold, users:write (any user) is admin only and regular users write their own account with profile:write */
+pub fn get_role_scopes(role: &str) -> &'static [&'static str] {
+    match role {
+        ROLE_ADMIN => &[SCOPE_USERS_READ, SCOPE_USERS_WRITE, SCOPE_PROFILE_WRITE, SCOPE_AUDIT_READ],
+        ROLE_SERVICE => &[SCOPE_USERS_READ],
+        _ => &[SCOPE_USERS_READ, SCOPE_PROFILE_WRITE]
+    }
+}
+/* * end scopes a role is allowed to hold, users:write (any user) is admin only and regular users write their own account with profile:write */
+
+/* * granted scope is requested scope limited to role scopes, all role scopes when nothing requested */
+pub fn resolve_scope(role: &str, requested_scope: Option<&str>) -> String {
+    let role_scopes = get_role_scopes(role);
+
+    match requested_scope {
+        Some(requested_scope) => requested_scope
+            .split_whitespace()
+            .filter(|scope| role_scopes.contains(scope))
+            .collect::<Vec<_>>()
+            .join(" "),
+        None => role_scopes.join(" ")
+    }
+}
+/* * end granted scope is requested scope limited to role scopes, all role scopes when nothing requested */
+
+/* * compute session idle & absolute expiry from session timestamps */
+pub fn get_session_expiry(
+    config: &SessionConfig,
+    created_at: i64,
+    last_activity_at: i64,
+    max_age: i64
+) -> SessionExpiry
+{
+    let session_expires_at = match config.absolute_lifetime {
+        Some(absolute_lifetime) => max_age.min(created_at + absolute_lifetime.num_seconds()),
+        None => max_age
+    };
+    let idle_expires_at = config.idle_timeout
+        .map(|idle_timeout| (last_activity_at + idle_timeout.num_seconds()).min(session_expires_at));
+
+    SessionExpiry {
+        idle_expires_at,
+        session_expires_at
+    }
+}
+/* * end compute session idle & absolute expiry from session timestamps */
+
+/* * generate access_token & refresh_token pair and store new session for authenticated user */
+pub async fn generate_token_pair(
+    app_state: &AppState,
+    username: &str,
+    client: &ClientInfo,
+    options: SigninOptions
+) -> Result<ServiceOkSignin, AppError>
+{
+    let db_pool = &app_state.db_pool;
+    let time_now = Utc::now().timestamp();
+
+    /* * get stored user role, password reset & soft delete state */
+    let sql_query = sqlx::query("SELECT 
+        user.role,
+        user.deleted_at,
+        credentials.password_reset_required
+        FROM user INNER JOIN credentials ON credentials.username = user.username
+        WHERE user.username = ?
+    ");
+    let query_result = sql_query
+        .bind(username)
+        .fetch_one(db_pool)
+        .await?;
+    let stored_role = query_result.get::<String, _>("role");
+    let stored_password_reset_required = query_result.get::<bool, _>("password_reset_required");
+    let stored_deleted_at = query_result.get::<Option<i64>, _>("deleted_at");
+    /* * end get stored user role, password reset & soft delete state */
+
+    /* * soft deleted users can not sign in, whichever verifier accepted them */
+    if stored_deleted_at.is_some() {
+        let app_err_message: AppErrorMessage<Option<bool>> = AppErrorMessage {
+            code: StatusCode::FORBIDDEN.as_u16(),
+            message: format!("user '{}' has been deleted. please contact an admin to restore the account.", username),
+            details: None
+        };
+        return Err(AppError::Forbidden(app_err_message.into()));
+    }
+    /* * end soft deleted users can not sign in, whichever verifier accepted them */
+
+    /* * refuse new sessions until password has been reset */
+    if stored_password_reset_required {
+        let app_err_message: AppErrorMessage<Option<bool>> = AppErrorMessage {
+            code: StatusCode::FORBIDDEN.as_u16(),
+            message: format!("a password reset is required for user '{}' before signing in again. please use the reset link sent to you.", username),
+            details: None
+        };
+        return Err(AppError::Forbidden(app_err_message.into()));
+    }
+    /* * end refuse new sessions until password has been reset */
+
+    /* * generate jwt_encoded_access_token */
+    let scope = resolve_scope(&stored_role, options.scope.as_deref());
+    let access_token_exp = (Utc::now() + app_state.tokens.access_token_expired).timestamp();
+    let claims_access_token = Claims {
+        username: username.to_string(),
+        iat: time_now,
+        exp: access_token_exp,
+        jti: None,
+        role: Some(stored_role),
+        scope: Some(scope.clone()),
+        cnf: Confirmation::from_jkt(options.dpop_jkt.clone()),
+        act: None
+    };
+    let encoded_access_token = app_state.token_format.encode(TokenKind::Access, &claims_access_token)
+    .map_err(|e| {
+        let app_err_message = AppErrorMessage {
+            code: StatusCode::INTERNAL_SERVER_ERROR.as_u16(),
+            message: format!("failed to encode access token: {:#?}", claims_access_token),
+            details: Some(e.to_string())
+        };
+        AppError::InternalServerError(app_err_message.into())
+    })?;
+    /* * end generate jwt_encoded_access_token */
+    /* * generate jwt_encoded_refresh_token */
+    let refresh_token_expired = match options.remember_me {
+        true => app_state.tokens.remember_me_refresh_token_expired,
+        false => app_state.tokens.refresh_token_expired
+    };
+    let mut refresh_token_exp = (Utc::now() + refresh_token_expired).timestamp();
+    /* * refresh_token never outlives session absolute lifetime */
+    if let Some(absolute_lifetime) = app_state.sessions.absolute_lifetime {
+        refresh_token_exp = refresh_token_exp.min(time_now + absolute_lifetime.num_seconds());
+    }
+    /* * end refresh_token never outlives session absolute lifetime */
+    let claims_refresh_token = Claims {
+        username: username.to_string(),
+        iat: time_now,
+        exp: refresh_token_exp,
+        jti: Some(generate_jti()),
+        role: None,
+        scope: None,
+        cnf: None,
+        act: None
+    };
+    let encoded_refresh_token = app_state.token_format.encode(TokenKind::Refresh, &claims_refresh_token)
+    .map_err(|e| {
+        let app_err_message = AppErrorMessage {
+            code: StatusCode::INTERNAL_SERVER_ERROR.as_u16(),
+            message: format!("failed to encode refresh token: {:#?}", claims_refresh_token),
+            details: Some(e.to_string())
+        };
+        AppError::InternalServerError(app_err_message.into())
+    })?;
+    /* * end generate jwt_encoded_refresh_token */
+
+    /* * clean up expired user sessions */
+    let sql_query = sqlx::query("DELETE FROM sessions WHERE username = ? AND max_age < ?");
+    let _ = sql_query
+        .bind(username)
+        .bind(time_now)
+        .execute(db_pool)
+        .await?;
+    /* * end clean up expired user sessions */
+
+    /* * stored jwt_encoded_refresh_token as new user session */
+    let sql_query = sqlx::query("INSERT INTO sessions (
+        username,
+        refresh_token,
+        max_age,
+        ip_address,
+        user_agent,
+        browser_mode,
+        dpop_jkt,
+        scope,
+        created_at
+    ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?);");
+    let _ = sql_query
+        .bind(username)
+        .bind(&encoded_refresh_token)
+        .bind(refresh_token_exp)
+        .bind(&client.ip_address)
+        .bind(&client.user_agent)
+        .bind(options.browser_mode)
+        .bind(&options.dpop_jkt)
+        .bind(&options.scope)
+        .bind(time_now)
+        .execute(db_pool)
+        .await?;
+    /* * end stored jwt_encoded_refresh_token as new user session */
+    /* * every sign in flow ends here, so it is the last login of user & credentials */
+    let sql_query = sqlx::query("UPDATE user SET last_login_at = ? WHERE username = ?");
+    let _ = sql_query
+        .bind(time_now)
+        .bind(username)
+        .execute(db_pool)
+        .await?;
+    let sql_query = sqlx::query("UPDATE credentials SET last_login_at = ? WHERE username = ?");
+    let _ = sql_query
+        .bind(time_now)
+        .bind(username)
+        .execute(db_pool)
+        .await?;
+    /* * end every sign in flow ends here, so it is the last login of user & credentials */
+    /* * check is jwt_encoded_refresh_token stored in sessions */
+    let sql_query = sqlx::query("SELECT username FROM sessions WHERE refresh_token = ?");
+    let query_result = sql_query
+        .bind(&encoded_refresh_token)
+        .fetch_optional(db_pool)
+        .await?;
+    if query_result.is_none() {
+        let app_err_message: AppErrorMessage<Option<bool>> = AppErrorMessage {
+            code: StatusCode::INTERNAL_SERVER_ERROR.as_u16(),
+            message: String::from("refresh token is mismatch or not found."),
+            details: None
+        };
+        return Err(AppError::InternalServerError(app_err_message.into()));
+    }
+    /* * end check is jwt_encoded_refresh_token stored in sessions */
+
+    Ok(
+        ServiceOkSignin {
+            username: username.to_string(),
+            encoded_access_token,
+            access_token_max_age: convert_timestamp_to_actix_duration(access_token_exp),
+            encoded_refresh_token,
+            refresh_token_max_age: convert_timestamp_to_actix_duration(refresh_token_exp),
+            browser_mode: options.browser_mode,
+            token_type: match options.dpop_jkt {
+                Some(_) => TOKEN_TYPE_DPOP,
+                None => TOKEN_TYPE_BEARER
+            },
+            scope,
+            session_expiry: get_session_expiry(&app_state.sessions, time_now, time_now, refresh_token_exp)
+        }
+    )
+}
+/* * end generate access_token & refresh_token pair and store new session for authenticated user */
+
+/* * store webauthn challenge for registration / authentication ceremony */
+pub async fn store_webauthn_challenge(
+    db_pool: &DbPool,
+    challenge: &str,
+    username: Option<&str>,
+    ceremony: &str
+) -> Result<(), AppError>
+{
+    let time_now = Utc::now().timestamp();
+    let expires_at = (Utc::now() + *CHRONO_WEBAUTHN_CHALLENGE_EXPIRED).timestamp();
+
+    /* * clean up expired challenges */
+    let sql_query = sqlx::query("DELETE FROM webauthn_challenges WHERE expires_at < ?");
+    let _ = sql_query
+        .bind(time_now)
+        .execute(db_pool)
+        .await?;
+    /* * end clean up expired challenges */
+
+    let sql_query = sqlx::query("INSERT INTO webauthn_challenges (challenge, username, ceremony, expires_at) VALUES (?, ?, ?, ?)");
+    let _ = sql_query
+        .bind(challenge)
+        .bind(username)
+        .bind(ceremony)
+        .bind(expires_at)
+        .execute(db_pool)
+        .await?;
+
+    Ok(())
+}
+/* * end store webauthn challenge for registration / authentication ceremony */
+
+/* * consume stored webauthn challenge, returns username bound to challenge */
+pub async fn take_webauthn_challenge(
+    db_pool: &DbPool,
+    challenge: &str,
+    ceremony: &str
+) -> Result<Option<String>, AppError>
+{
+    let challenge_not_found = || {
+        let app_err_message: AppErrorMessage<Option<bool>> = AppErrorMessage {
+            code: StatusCode::UNAUTHORIZED.as_u16(),
+            message: String::from("passkey challenge not found or already used. please start the ceremony again."),
+            details: None
+        };
+        AppError::Unauthorized(app_err_message.into())
+    };
+
+    /* * challenge is single use, row lock lets only one concurrent request consume it */
+    let mut transaction = db_pool.begin().await?;
+    let sql_query = sqlx::query("SELECT username, expires_at FROM webauthn_challenges WHERE challenge = ? AND ceremony = ? FOR UPDATE");
+    let query_result = sql_query
+        .bind(challenge)
+        .bind(ceremony)
+        .fetch_optional(&mut *transaction)
+        .await?
+        .ok_or_else(challenge_not_found)?;
+    let stored_username = query_result.get::<Option<String>, _>("username");
+    let stored_expires_at = query_result.get::<i64, _>("expires_at");
+
+    let sql_query = sqlx::query("DELETE FROM webauthn_challenges WHERE challenge = ? AND ceremony = ?");
+    let query_result = sql_query
+        .bind(challenge)
+        .bind(ceremony)
+        .execute(&mut *transaction)
+        .await?;
+    if query_result.rows_affected() != 1 {
+        return Err(challenge_not_found());
+    }
+    transaction.commit().await?;
+    /* * end challenge is single use, row lock lets only one concurrent request consume it */
+
+    let time_now = Utc::now().timestamp();
+    if time_now >= stored_expires_at {
+        let app_err_message: AppErrorMessage<Option<bool>> = AppErrorMessage {
+            code: StatusCode::UNAUTHORIZED.as_u16(),
+            message: String::from("passkey challenge has been expired. please start the ceremony again."),
+            details: None
+        };
+        return Err(AppError::Unauthorized(app_err_message.into()));
+    }
+
+    Ok(stored_username)
+}
+/* * end consume stored webauthn challenge, returns username bound to challenge */
+
+/* * html escape untrusted text before rendering it into a page */
+pub fn escape_html(text: &str) -> String {
+    text
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#x27;")
+}
+/* * end html escape untrusted text before rendering it into a page */
+
+/* * form page for links opened from emails, GET only renders it and the submitted POST does the work (mail scanners only prefetch GET) */
+pub fn render_form_page(
+    title: &str,
+    message: &str,
+    action: &str,
+    hidden_fields: &[(&str, &str)],
+    password_fields: &[&str],
+    submit: &str
+) -> HttpResponse
+{
+    let hidden_inputs = hidden_fields
+        .iter()
+        .map(|(name, value)| format!("<input type=\"hidden\" name=\"{}\" value=\"{}\">", escape_html(name), escape_html(value)))
+        .collect::<String>();
+    let password_inputs = password_fields
+        .iter()
+        .map(|name| format!("<p><label>{0}<br><input type=\"password\" name=\"{0}\" required></label></p>", escape_html(name)))
+        .collect::<String>();
+    let page = format!(
+        "<!DOCTYPE html><html><head><meta charset=\"utf-8\"><title>{0}</title></head><body><h1>{0}</h1><p>{1}</p><form method=\"post\" action=\"{2}\">{3}{4}<button type=\"submit\">{5}</button></form></body></html>",
+        escape_html(title),
+        escape_html(message),
+        escape_html(action),
+        hidden_inputs,
+        password_inputs,
+        escape_html(submit)
+    );
+
+    HttpResponse::Ok()
+        .insert_header((CACHE_CONTROL, "no-store"))
+        .insert_header((REFERRER_POLICY, "no-referrer"))
+        .content_type("text/html; charset=utf-8")
+        .body(page)
+}
+/* * end form page for links opened from emails, GET only renders it and the submitted POST does the work (mail scanners only prefetch GET) */
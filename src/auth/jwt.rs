@@ -4,21 +4,18 @@ use actix_web::{
     dev::Payload,
     http::{
         header::AUTHORIZATION, 
+        Method,
         StatusCode
     }, 
     web
 };
 use chrono::Utc;
-use std::future::{
-    Ready,
-    ready
-};
-use jsonwebtoken::{
-    decode,
-    DecodingKey,
-    Validation,
-    Algorithm,
-    errors::ErrorKind as JwtErrorKind
+use std::{
+    future::{
+        Future,
+        ready
+    },
+    pin::Pin
 };
 
 use crate::{
@@ -26,34 +23,148 @@ use crate::{
         AppError, 
         AppErrorMessage
     }, 
-    auth::types::Claims,
+    auth::{
+        types::Claims,
+        token::{
+            TokenError,
+            TokenKind
+        },
+        constants::{
+            ROLE_ADMIN,
+            SCOPE_USERS_WRITE,
+            SCOPE_PROFILE_WRITE,
+            ACCESS_TOKEN_COOKIE,
+            TOKEN_TYPE_DPOP
+        },
+        csrf::verify_csrf,
+        helpers::get_client_info,
+        dpop::{
+            get_dpop_proof,
+            verify_dpop_proof,
+            consume_dpop_jti
+        }
+    },
+    tls::ClientCertificate,
+    audit::{
+        record_audit_log,
+        AuditEntry
+    },
     AppState
 };
 
 #[derive(Debug)]
-pub struct JwtAuth;
+pub struct JwtAuth {
+    pub claims: Claims
+}
+impl JwtAuth {
+    /* * only allow token owned by admin */
+    pub fn require_admin(&self) -> Result<(), AppError> {
+        if self.claims.role.as_deref() != Some(ROLE_ADMIN) {
+            let app_err_message: AppErrorMessage<Option<bool>> = AppErrorMessage {
+                code: StatusCode::FORBIDDEN.as_u16(),
+                message: String::from("Forbidden: this action requires admin privileges."),
+                details: None
+            };
+            return Err(AppError::Forbidden(app_err_message.into()));
+        }
+
+        Ok(())
+    }
+    /* * end only allow token owned by admin */
+
+    /* * check scope claim holds the given scope */
+    pub fn has_scope(&self, scope: &str) -> bool {
+        self.claims.scope
+            .as_deref()
+            .is_some_and(|scopes| scopes.split_whitespace().any(|granted| granted == scope))
+    }
+
+    /* * only allow token granted with the given scope, e.g. "users:write" */
+    pub fn require_scope(&self, scope: &str) -> Result<(), AppError> {
+        if !self.has_scope(scope) {
+            let app_err_message: AppErrorMessage<Option<bool>> = AppErrorMessage {
+                code: StatusCode::FORBIDDEN.as_u16(),
+                message: format!("Forbidden: this action requires the '{}' scope.", scope),
+                details: None
+            };
+            return Err(AppError::Forbidden(app_err_message.into()));
+        }
+
+        Ok(())
+    }
+    /* * end only allow token granted with the given scope, e.g. "users:write" */
+
+    /* * writing own account needs profile:write, users:write already covers every account */
+    pub fn require_profile_write(&self) -> Result<(), AppError> {
+        match self.has_scope(SCOPE_USERS_WRITE) {
+            true => Ok(()),
+            false => self.require_scope(SCOPE_PROFILE_WRITE)
+        }
+    }
+    /* * end writing own account needs profile:write, users:write already covers every account */
+
+    /* * admin acting on behalf of claims.username, None when not impersonating */
+    pub fn impersonator(&self) -> Option<&str> {
+        self.claims.act.as_ref().map(|act| act.sub.as_str())
+    }
+    /* * end admin acting on behalf of claims.username, None when not impersonating */
+
+    /* * block destructive actions while impersonating */
+    pub fn require_not_impersonating(&self) -> Result<(), AppError> {
+        if let Some(impersonator) = self.impersonator() {
+            let app_err_message: AppErrorMessage<Option<bool>> = AppErrorMessage {
+                code: StatusCode::FORBIDDEN.as_u16(),
+                message: format!("Forbidden: this action is not allowed while '{}' is impersonating '{}'.", impersonator, self.claims.username),
+                details: None
+            };
+            return Err(AppError::Forbidden(app_err_message.into()));
+        }
+
+        Ok(())
+    }
+    /* * end block destructive actions while impersonating */
+}
 impl FromRequest for JwtAuth {
     type Error = AppError;
-    type Future = Ready<Result<Self, Self::Error>>;
+    type Future = Pin<Box<dyn Future<Output = Result<Self, Self::Error>>>>;
 
     fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
-        /* * checking Authorization header and get value */
+        /* * get app_state */
+        let app_state = req.app_data::<web::Data<AppState>>();
+        /* * end get app_state */
+
+        /* * checking Authorization header and get value, fallback to browser_mode access_token cookie */
         let auth_header = req.headers().get(AUTHORIZATION);
         let auth_value = auth_header.map_or("", |hv| hv.to_str().unwrap_or(""));
-        let token  = auth_value.split_whitespace().nth(1);
+        let auth_scheme = auth_value.split_whitespace().next().unwrap_or_default();
+        let (token, is_cookie_token) = match auth_value.split_whitespace().nth(1) {
+            Some(token) => (Some(token.to_string()), false),
+            None => (req.cookie(ACCESS_TOKEN_COOKIE).map(|cookie| cookie.value().to_string()), true)
+        };
         if token.is_none() {
             let app_err_message: AppErrorMessage<Option<bool>> = AppErrorMessage {
                 code: StatusCode::UNAUTHORIZED.as_u16(),
                 message: String::from("Unauthorized: missing or invalid authorization token."),
                 details: None
             };
-            return ready(Err(AppError::Unauthorized(app_err_message.into())));
+            return Box::pin(ready(Err(AppError::Unauthorized(app_err_message.into()))));
+        }
+        /* * end checking Authorization header and get value, fallback to browser_mode access_token cookie */
+
+        /* * cookie is sent by browser automatically, state-changing requests must pass csrf check */
+        let is_safe_method = matches!(*req.method(), Method::GET | Method::HEAD | Method::OPTIONS);
+        if is_cookie_token && !is_safe_method {
+            if let Some(app_state) = app_state {
+                if let Err(e) = verify_csrf(req, &app_state.csrf) {
+                    return Box::pin(ready(Err(e)));
+                }
+            }
         }
-        /* * end checking Authorization header and get value */
+        /* * end cookie is sent by browser automatically, state-changing requests must pass csrf check */
 
-        /* * get secret_access_token from app_state */
-        let secret_access_token = req.app_data::<web::Data<AppState>>()
-            .map(|app_state| &app_state.get_ref().secret_access_token)
+        /* * get token_format from app_state */
+        let token_format = app_state
+            .map(|app_state| &app_state.get_ref().token_format)
             /* * * convert option type to result type */
             .ok_or_else(|| {
                 let app_err_message: AppErrorMessage<Option<bool>> = AppErrorMessage {
@@ -64,18 +175,17 @@ impl FromRequest for JwtAuth {
                 AppError::InternalServerError(app_err_message.into())
             });
             /* * * end convert option type to result type */
-        /* * get secret_access_token from app_state */
+        /* * get token_format from app_state */
         
-        /* * streaming result secret_access_token for return Ok and Err */
-        let future_result = secret_access_token.and_then(|secret_access_token| {
+        /* * streaming result token_format for return Ok and Err */
+        let future_result = token_format.and_then(|token_format| {
             /* * * checking user token is valid */
-            let is_token_valid = decode::<Claims>(
-                &token.unwrap(),
-                &DecodingKey::from_secret(secret_access_token.as_ref()),
-                &Validation::new(Algorithm::HS256)
+            let claims = token_format.decode(
+                TokenKind::Access,
+                token.as_deref().unwrap_or_default()
             ).map_err(|e| {
-                match e.kind() {
-                    JwtErrorKind::ExpiredSignature => {
+                match e {
+                    TokenError::Expired => {
                         let app_err_message: AppErrorMessage<Option<bool>> = AppErrorMessage {
                             code: StatusCode::UNAUTHORIZED.as_u16(),
                             message: String::from("Access token signature has expired. Please regenerate a new signature for the access token."),
@@ -96,7 +206,7 @@ impl FromRequest for JwtAuth {
             /* * * end checking user token is valid */
 
             /* * * checking token expired  */
-            let token_exp = is_token_valid.claims.exp;
+            let token_exp = claims.exp;
             let time_now = Utc::now().naive_utc().timestamp();
             if time_now >= token_exp {
                 let app_err_message: AppErrorMessage<Option<bool>> = AppErrorMessage {
@@ -108,12 +218,89 @@ impl FromRequest for JwtAuth {
             }
             /* * * end checking token expired  */
 
-            Ok( Self )
+            /* * * DPoP bound token requires proof of possession of the bound key */
+            let mut dpop_jti = None;
+            let bound_jkt = claims.cnf.as_ref().and_then(|cnf| cnf.jkt.as_deref());
+            if let (Some(bound_jkt), Some(app_state)) = (bound_jkt, app_state) {
+                if !is_cookie_token && !auth_scheme.eq_ignore_ascii_case(TOKEN_TYPE_DPOP) {
+                    let app_err_message: AppErrorMessage<Option<bool>> = AppErrorMessage {
+                        code: StatusCode::UNAUTHORIZED.as_u16(),
+                        message: String::from("Unauthorized: DPoP bound access token must be sent with the DPoP authorization scheme."),
+                        details: None
+                    };
+                    return Err(AppError::Unauthorized(app_err_message.into()));
+                }
+                let proof = get_dpop_proof(req).ok_or_else(|| {
+                    let app_err_message: AppErrorMessage<Option<bool>> = AppErrorMessage {
+                        code: StatusCode::UNAUTHORIZED.as_u16(),
+                        message: String::from("Unauthorized: DPoP bound access token requires a DPoP proof."),
+                        details: None
+                    };
+                    AppError::Unauthorized(app_err_message.into())
+                })?;
+                let proof = verify_dpop_proof(req, app_state, proof, token.as_deref())?;
+                if proof.jkt != bound_jkt {
+                    let app_err_message: AppErrorMessage<Option<bool>> = AppErrorMessage {
+                        code: StatusCode::UNAUTHORIZED.as_u16(),
+                        message: String::from("Unauthorized: DPoP proof key does not match the key bound to the access token."),
+                        details: None
+                    };
+                    return Err(AppError::Unauthorized(app_err_message.into()));
+                }
+                dpop_jti = Some(proof.jti);
+            }
+            /* * * end DPoP bound token requires proof of possession of the bound key */
+
+            /* * * certificate-bound token is only accepted over the same client certificate */
+            let bound_x5t_s256 = claims.cnf.as_ref().and_then(|cnf| cnf.x5t_s256.as_deref());
+            if let Some(bound_x5t_s256) = bound_x5t_s256 {
+                let certificate = req.conn_data::<ClientCertificate>();
+                if certificate.map(|certificate| certificate.thumbprint.as_str()) != Some(bound_x5t_s256) {
+                    let app_err_message: AppErrorMessage<Option<bool>> = AppErrorMessage {
+                        code: StatusCode::UNAUTHORIZED.as_u16(),
+                        message: String::from("Unauthorized: certificate-bound access token must be presented over the same client certificate."),
+                        details: None
+                    };
+                    return Err(AppError::Unauthorized(app_err_message.into()));
+                }
+            }
+            /* * * end certificate-bound token is only accepted over the same client certificate */
+
+            /* * * every impersonated request is written to audit log */
+            if let (Some(act), Some(app_state)) = (claims.act.as_ref(), app_state) {
+                let db_pool = app_state.db_pool.clone();
+                let client = get_client_info(req);
+                let impersonator = act.sub.clone();
+                let username = claims.username.clone();
+                let request_line = format!("{} {}", req.method(), req.path());
+                actix_web::rt::spawn(async move {
+                    let audit_entry = AuditEntry {
+                        event: "auth.impersonated_request",
+                        actor: Some(&impersonator),
+                        target: Some(&username),
+                        success: true,
+                        reason: Some(request_line),
+                        client: &client
+                    };
+                    record_audit_log(&db_pool, audit_entry).await;
+                });
+            }
+            /* * * end every impersonated request is written to audit log */
+
+            Ok( (Self { claims }, dpop_jti) )
         });
-        /* * end streaming result secret_access_token for return Ok and Err */
+        /* * end streaming result token_format for return Ok and Err */
+
+        /* * return future, DPoP proof jti is consumed in database before request is accepted */
+        let db_pool = app_state.map(|app_state| app_state.db_pool.clone());
+        Box::pin(async move {
+            let (auth, dpop_jti) = future_result?;
+            if let (Some(dpop_jti), Some(db_pool)) = (dpop_jti, db_pool) {
+                consume_dpop_jti(&db_pool, &dpop_jti).await?;
+            }
 
-        /* * return future */
-        ready(future_result)
-        /* * end return future */
+            Ok(auth)
+        })
+        /* * end return future, DPoP proof jti is consumed in database before request is accepted */
     }
 }
\ No newline at end of file
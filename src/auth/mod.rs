@@ -1,11 +1,44 @@
 mod constants;
+mod csrf;
+mod dpop;
 mod handler;
 mod helpers;
 mod jwt;
 mod model;
 mod routes;
 mod services;
+mod token;
 mod types;
+mod verifier;
+mod webauthn;
 
 pub use routes::scoped_auth;
-pub use jwt::JwtAuth;
\ No newline at end of file
+pub use jwt::JwtAuth;
+pub use types::ClientInfo;
+pub(crate) use constants::DPOP_NONCE_HEADER;
+pub use constants::{
+    SCOPE_USERS_READ,
+    SCOPE_USERS_WRITE,
+    SCOPE_AUDIT_READ
+};
+pub use helpers::{
+    get_client_info,
+    generate_secret_token,
+    hash_token,
+    verify_password,
+    escape_html,
+    render_form_page
+};
+pub use services::sessions::{
+    get_sessions_service,
+    revoke_session_service,
+    revoke_all_sessions_service
+};
+pub use token::{
+    build_token_format,
+    TokenFormat
+};
+pub use verifier::{
+    build_credential_verifier,
+    CredentialVerifier
+};
\ No newline at end of file
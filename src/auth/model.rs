@@ -1,19 +1,34 @@
-use serde::Deserialize;
+use serde::{
+    Serialize,
+    Deserialize
+};
 use validator::Validate;
 use lazy_static::lazy_static;
 use regex::Regex;
 use sqlx::FromRow;
 
+use crate::user::{
+    validate_password,
+    RE_PASSWORD
+};
+
 lazy_static! {
     static ref RE_USERNAME: Regex = Regex::new(r"^[0-9a-zA-Z]{2,}$").unwrap(); 
 }
 
 #[derive(FromRow)]
 pub struct Credentials {
-    pub username: String,
-    pub password: String,
-    pub refresh_token: Option<String>,
-    pub max_age: Option<i64>
+    pub password: String
+}
+
+#[derive(Serialize, FromRow)]
+pub struct Session {
+    pub id_session: i32,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    pub created_at: i64,
+    pub last_refreshed_at: Option<i64>,
+    pub max_age: i64
 }
 
 #[derive(Deserialize)]
@@ -21,6 +36,11 @@ pub struct In<T> {
     pub credentials: T
 }
 
+#[derive(Deserialize)]
+pub struct RefreshTokenPayload {
+    pub refresh_token: Option<String>
+}
+
 #[derive(Deserialize, Validate)]
 pub struct CredentialsPayload{
     #[validate(
@@ -41,5 +61,114 @@ pub struct CredentialsPayload{
             message = "password length must be at least 8 characters."
         )
     )]
-    pub password: String
+    pub password: String,
+    #[serde(default)]
+    pub remember_me: Option<bool>,
+    #[serde(default)]
+    pub browser_mode: Option<bool>,
+    #[serde(default)]
+    pub scope: Option<String>
+}
+
+#[derive(Serialize, FromRow)]
+pub struct Passkey {
+    pub id_passkey: i32,
+    pub name: Option<String>,
+    pub credential_id: String,
+    pub sign_count: i64,
+    pub created_at: i64,
+    pub last_used_at: Option<i64>
+}
+
+#[derive(Deserialize, Validate)]
+pub struct PasskeyRegistrationPayload {
+    #[validate(
+        length(
+            min = 1,
+            max = 50,
+            message = "passkey name length must be between 1 to 50 characters."
+        )
+    )]
+    pub name: Option<String>,
+    pub id: String,
+    pub response: PasskeyAttestationResponse
+}
+
+#[derive(Deserialize)]
+pub struct PasskeyAttestationResponse {
+    #[serde(rename = "clientDataJSON")]
+    pub client_data_json: String,
+    #[serde(rename = "attestationObject")]
+    pub attestation_object: String
+}
+
+#[derive(Deserialize)]
+pub struct PasskeySigninStartPayload {
+    #[serde(default)]
+    pub username: Option<String>
+}
+
+#[derive(Deserialize)]
+pub struct PasskeyAssertionPayload {
+    pub id: String,
+    pub response: PasskeyAssertionResponse,
+    #[serde(default)]
+    pub remember_me: Option<bool>,
+    #[serde(default)]
+    pub browser_mode: Option<bool>,
+    #[serde(default)]
+    pub scope: Option<String>
+}
+
+#[derive(Deserialize)]
+pub struct PasskeyAssertionResponse {
+    #[serde(rename = "clientDataJSON")]
+    pub client_data_json: String,
+    #[serde(rename = "authenticatorData")]
+    pub authenticator_data: String,
+    pub signature: String
+}
+
+#[derive(Serialize, FromRow)]
+pub struct SecurityEvent {
+    pub id_security_event: i64,
+    pub event_type: String,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    pub created_at: i64,
+    pub disavowed_at: Option<i64>
+}
+
+#[derive(Deserialize)]
+pub struct TokenQuery {
+    pub token: String
+}
+
+#[derive(Deserialize, Validate)]
+pub struct PasswordResetPayload {
+    pub token: String,
+
+    #[validate(
+        length(
+            min = 8,
+            message = "password length must be at least 8 characters."
+        ),
+        custom(
+            function = "validate_password",
+            message = "password must contain at least one upper case, lower case, number and 8 characters long. don't use spaces."
+        ),
+        regex(
+            path = "RE_PASSWORD",
+            message = "pasword must be at least contain one special character."
+        )
+    )]
+    pub password: String,
+
+    #[validate(
+        must_match(
+            other = "password",
+            message = "password do not match. password and confirm_password must be the same."
+        )
+    )]
+    pub confirm_password: String
 }
\ No newline at end of file
@@ -4,7 +4,25 @@ use crate::{
     auth::handler::{
         signin,
         refresh_token,
-        logout
+        logout,
+        dpop_nonce,
+        paseto_public_key,
+        mtls_token,
+        impersonate,
+        passkey_register_start,
+        passkey_register_finish,
+        passkey_signin_start,
+        passkey_signin_finish,
+        get_passkeys,
+        delete_passkey,
+        get_sessions,
+        revoke_session,
+        revoke_all_sessions,
+        get_security_events,
+        disavow_security_event_page,
+        disavow_security_event,
+        password_reset_page,
+        password_reset
     },
     user::insert_user
 };
@@ -12,9 +30,36 @@ use crate::{
 pub fn scoped_auth(cfg: &mut web::ServiceConfig) {
     cfg.service(
         web::scope("/auth")
-            .route("/logout", web::get().to(logout))
+            .route("/logout", web::post().to(logout))
             .route("/signup", web::post().to(insert_user))
             .route("/signin", web::post().to(signin))
-            .route("/refresh", web::get().to(refresh_token))
+            .route("/refresh", web::post().to(refresh_token))
+            .route("/dpop-nonce", web::get().to(dpop_nonce))
+            .route("/paseto-public-key", web::get().to(paseto_public_key))
+            .route("/mtls/token", web::post().to(mtls_token))
+            .route("/impersonate/{username}", web::post().to(impersonate))
+            .service(
+                web::resource("/sessions")
+                    .get(get_sessions)
+                    .delete(revoke_all_sessions)
+            )
+            .route("/sessions/{id_session}", web::delete().to(revoke_session))
+            .route("/security-events", web::get().to(get_security_events))
+            .service(
+                web::resource("/security-events/disavow")
+                    .get(disavow_security_event_page)
+                    .post(disavow_security_event)
+            )
+            .service(
+                web::resource("/password-reset")
+                    .get(password_reset_page)
+                    .post(password_reset)
+            )
+            .route("/passkeys", web::get().to(get_passkeys))
+            .route("/passkeys/{id_passkey}", web::delete().to(delete_passkey))
+            .route("/passkeys/register/start", web::post().to(passkey_register_start))
+            .route("/passkeys/register/finish", web::post().to(passkey_register_finish))
+            .route("/passkeys/signin/start", web::post().to(passkey_signin_start))
+            .route("/passkeys/signin/finish", web::post().to(passkey_signin_finish))
     );
 }
\ No newline at end of file
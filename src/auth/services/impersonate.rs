@@ -0,0 +1,111 @@
+use actix_web::http::StatusCode;
+use chrono::Utc;
+use sqlx::Row;
+
+use crate::{
+    types::AppState,
+    auth::{
+        types::{
+            Actor,
+            Claims,
+            ServiceOkImpersonate
+        },
+        helpers::resolve_scope,
+        token::TokenKind,
+        constants::{
+            ROLE_ADMIN,
+            SCOPE_USERS_READ,
+            CHRONO_IMPERSONATION_EXPIRED
+        }
+    },
+    errors::{
+        AppError,
+        AppErrorMessage
+    }
+};
+
+/* * issue short-lived access token for target user with act claim naming the admin */
+pub async fn impersonate_service(
+    app_state: &AppState,
+    admin_username: &str,
+    target_username: &str
+) -> Result<ServiceOkImpersonate, AppError>
+{
+    let db_pool = &app_state.db_pool;
+    let target_username = target_username.to_lowercase();
+
+    /* * admin can not impersonate themselves */
+    if target_username == admin_username {
+        let app_err_message: AppErrorMessage<Option<bool>> = AppErrorMessage {
+            code: StatusCode::UNPROCESSABLE_ENTITY.as_u16(),
+            message: String::from("you can not impersonate yourself."),
+            details: None
+        };
+        return Err(AppError::UnprocessableEntity(app_err_message.into()));
+    }
+    /* * end admin can not impersonate themselves */
+
+    /* * get stored target user role */
+    let sql_query = sqlx::query("SELECT role FROM user WHERE username = ? AND deleted_at IS NULL");
+    let query_result = sql_query
+        .bind(&target_username)
+        .fetch_one(db_pool)
+        .await
+        .map_err(|e| {
+            let app_err_message = AppErrorMessage {
+                code: StatusCode::NOT_FOUND.as_u16(),
+                message: format!("user '{}' not found.", &target_username),
+                details: Some(e.to_string())
+            };
+            AppError::NotFound(app_err_message.into())
+        })?;
+    let stored_role = query_result.get::<String, _>("role");
+    /* * end get stored target user role */
+
+    /* * impersonating another admin would escalate support staff to admin */
+    if stored_role == ROLE_ADMIN {
+        let app_err_message: AppErrorMessage<Option<bool>> = AppErrorMessage {
+            code: StatusCode::FORBIDDEN.as_u16(),
+            message: format!("user '{}' is an admin and can not be impersonated.", &target_username),
+            details: None
+        };
+        return Err(AppError::Forbidden(app_err_message.into()));
+    }
+    /* * end impersonating another admin would escalate support staff to admin */
+
+    /* * generate impersonation access token, no refresh token is issued, read-only scope */
+    let scope = resolve_scope(&stored_role, Some(SCOPE_USERS_READ));
+    let time_now = Utc::now().timestamp();
+    let access_token_exp = (Utc::now() + *CHRONO_IMPERSONATION_EXPIRED).timestamp();
+    let claims = Claims {
+        username: target_username.clone(),
+        iat: time_now,
+        exp: access_token_exp,
+        jti: None,
+        role: Some(stored_role),
+        scope: Some(scope.clone()),
+        cnf: None,
+        act: Some(Actor { sub: admin_username.to_string() })
+    };
+    let access_token = app_state.token_format.encode(TokenKind::Access, &claims)
+    .map_err(|e| {
+        let app_err_message = AppErrorMessage {
+            code: StatusCode::INTERNAL_SERVER_ERROR.as_u16(),
+            message: format!("failed to encode access token: {:#?}", claims),
+            details: Some(e.to_string())
+        };
+        AppError::InternalServerError(app_err_message.into())
+    })?;
+    /* * end generate impersonation access token, no refresh token is issued, read-only scope */
+
+    Ok(
+        ServiceOkImpersonate {
+            username: target_username,
+            scope,
+            impersonated_by: admin_username.to_string(),
+            access_token,
+            expires_in: access_token_exp - time_now
+        }
+    )
+}
+/* * end issue short-lived access token for target user with act claim naming the admin */
@@ -1,7 +1,4 @@
-use actix_web::{
-    http::StatusCode, 
-    cookie::Cookie
-};
+use actix_web::http::StatusCode;
 use sqlx::Row;
 
 use crate::{
@@ -14,11 +11,10 @@ use crate::{
 
 pub async fn logout_service(
     app_state: &AppState,
-    refresh_token_cookie: Option<Cookie<'_>>
+    refresh_token: Option<String>
 ) -> Result<String, AppError> 
 {
-    let refresh_token_cookie = refresh_token_cookie
-        .map(|cookie| cookie.value().to_string() )
+    let refresh_token = refresh_token
         .ok_or_else(|| {
             let app_err_message: AppErrorMessage<Option<bool>> = AppErrorMessage {
                 code: StatusCode::UNAUTHORIZED.as_u16(),
@@ -29,10 +25,10 @@ pub async fn logout_service(
         })?;
 
     let db_pool = &app_state.db_pool;
-    /* * check is refresh_token exist in database */
-    let sql_query = sqlx::query("SELECT username, refresh_token FROM credentials WHERE refresh_token = ?"); 
+    /* * check is refresh_token session exist in database */
+    let sql_query = sqlx::query("SELECT id_session, username FROM sessions WHERE refresh_token = ?"); 
     let query_result = sql_query
-        .bind(&refresh_token_cookie)
+        .bind(&refresh_token)
         .fetch_one(db_pool)
         .await
         .map_err(|e| {
@@ -43,21 +39,21 @@ pub async fn logout_service(
             };
             AppError::NotFound(app_err_message.into()) 
         })?;
+    let stored_id_session = query_result.get::<i32, _>("id_session");
     let stored_username = query_result.get::<String, _>("username");
-    let stored_refresh_token = query_result.get::<&str, _>("refresh_token");
-    /* * check is refresh_token exist in database */
+    /* * check is refresh_token session exist in database */
     
-    /* * set stored_refresh_token to null */
-    let sql_query = sqlx::query("UPDATE credentials SET refresh_token = null, max_age = null WHERE refresh_token = ?");
+    /* * destroy stored session */
+    let sql_query = sqlx::query("DELETE FROM sessions WHERE id_session = ?");
     let _ = sql_query
-        .bind(stored_refresh_token)
+        .bind(stored_id_session)
         .execute(db_pool)
         .await?;
-    /* * end set stored_refresh_token to null */
-    /* * check is stored_refresh_token is null */
-    let sql_query = sqlx::query("SELECT 1 FROM credentials WHERE refresh_token = ?"); 
+    /* * end destroy stored session */
+    /* * check is stored session destroyed */
+    let sql_query = sqlx::query("SELECT 1 FROM sessions WHERE id_session = ?"); 
     let query_result = sql_query
-        .bind(&refresh_token_cookie)
+        .bind(stored_id_session)
         .fetch_one(db_pool)
         .await;
     if let Ok(_) = query_result {
@@ -71,7 +67,7 @@ pub async fn logout_service(
         };
         return Err(AppError::InternalServerError(app_err_message.into()))
     }
-    /* * end check is stored_refresh_token is null */
+    /* * end check is stored session destroyed */
 
     Ok(stored_username)
 }
\ No newline at end of file
@@ -1,3 +1,12 @@
+pub mod impersonate;
 pub mod logout;
+pub mod mtls;
+pub mod new_device;
+pub mod passkey_register;
+pub mod passkey_signin;
+pub mod passkeys;
+pub mod password_reset;
 pub mod refresh;
+pub mod security_events;
+pub mod sessions;
 pub mod signin;
\ No newline at end of file
@@ -0,0 +1,88 @@
+use actix_web::http::StatusCode;
+use chrono::Utc;
+
+use crate::{
+    types::AppState,
+    tls::ClientCertificate,
+    auth::{
+        types::{
+            Claims,
+            Confirmation,
+            ServiceOkMtlsToken
+        },
+        helpers::resolve_scope,
+        token::TokenKind,
+        constants::{
+            ROLE_SERVICE,
+            SERVICE_PRINCIPAL_PREFIX
+        }
+    },
+    errors::{
+        AppError,
+        AppErrorMessage
+    }
+};
+
+/* * issue certificate-bound access token (RFC 8705) for service principal of client certificate */
+pub async fn mtls_token_service(
+    app_state: &AppState,
+    certificate: Option<&ClientCertificate>
+) -> Result<ServiceOkMtlsToken, AppError>
+{
+    /* * client certificate must have been verified during tls handshake */
+    let certificate = certificate.ok_or_else(|| {
+        let app_err_message: AppErrorMessage<Option<bool>> = AppErrorMessage {
+            code: StatusCode::UNAUTHORIZED.as_u16(),
+            message: String::from("a client certificate is required. please connect over mTLS."),
+            details: None
+        };
+        AppError::Unauthorized(app_err_message.into())
+    })?;
+    /* * end client certificate must have been verified during tls handshake */
+
+    /* * map certificate subject to service principal */
+    let principal = app_state.mtls.get_principal(&certificate.subject).ok_or_else(|| {
+        let app_err_message: AppErrorMessage<Option<bool>> = AppErrorMessage {
+            code: StatusCode::FORBIDDEN.as_u16(),
+            message: format!("client certificate subject '{}' is not mapped to a service principal.", certificate.subject),
+            details: None
+        };
+        AppError::Forbidden(app_err_message.into())
+    })?;
+    /* * end map certificate subject to service principal */
+
+    /* * generate jwt_encoded_access_token bound to client certificate */
+    let scope = resolve_scope(ROLE_SERVICE, None);
+    let time_now = Utc::now().timestamp();
+    let access_token_exp = (Utc::now() + app_state.tokens.access_token_expired).timestamp();
+    let claims = Claims {
+        username: format!("{}{}", SERVICE_PRINCIPAL_PREFIX, principal),
+        iat: time_now,
+        exp: access_token_exp,
+        jti: None,
+        role: Some(ROLE_SERVICE.to_string()),
+        scope: Some(scope.clone()),
+        cnf: Some(Confirmation::from_x5t_s256(certificate.thumbprint.clone())),
+        act: None
+    };
+    let access_token = app_state.token_format.encode(TokenKind::Access, &claims)
+    .map_err(|e| {
+        let app_err_message = AppErrorMessage {
+            code: StatusCode::INTERNAL_SERVER_ERROR.as_u16(),
+            message: format!("failed to encode access token: {:#?}", claims),
+            details: Some(e.to_string())
+        };
+        AppError::InternalServerError(app_err_message.into())
+    })?;
+    /* * end generate jwt_encoded_access_token bound to client certificate */
+
+    Ok(
+        ServiceOkMtlsToken {
+            principal: claims.username,
+            scope,
+            access_token,
+            expires_in: access_token_exp - time_now
+        }
+    )
+}
+/* * end issue certificate-bound access token (RFC 8705) for service principal of client certificate */
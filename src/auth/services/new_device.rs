@@ -0,0 +1,137 @@
+use chrono::Utc;
+use sqlx::Row;
+
+use crate::{
+    types::AppState,
+    auth::{
+        types::ClientInfo,
+        helpers::{
+            generate_secret_token,
+            hash_token
+        }
+    },
+    mailer::MailMessage,
+    errors::AppError
+};
+
+/* * record sign-in device, alert user when ip address or device has not been seen before */
+pub async fn new_device_signin_service(
+    app_state: &AppState,
+    username: &str,
+    client: &ClientInfo
+) -> Result<bool, AppError>
+{
+    let db_pool = &app_state.db_pool;
+    let time_now = Utc::now().timestamp();
+
+    /* * checking known devices of user */
+    let sql_query = sqlx::query("SELECT
+        EXISTS (SELECT 1 FROM known_devices WHERE username = ?) AS has_known_devices,
+        EXISTS (SELECT 1 FROM known_devices WHERE username = ? AND ip_address <=> ?) AS ip_address_known,
+        EXISTS (SELECT 1 FROM known_devices WHERE username = ? AND user_agent <=> ?) AS user_agent_known,
+        (SELECT id_known_device FROM known_devices WHERE username = ? AND ip_address <=> ? AND user_agent <=> ? LIMIT 1) AS id_known_device
+    ");
+    let query_result = sql_query
+        .bind(username)
+        .bind(username)
+        .bind(&client.ip_address)
+        .bind(username)
+        .bind(&client.user_agent)
+        .bind(username)
+        .bind(&client.ip_address)
+        .bind(&client.user_agent)
+        .fetch_one(db_pool)
+        .await?;
+    let has_known_devices = query_result.get::<i64, _>("has_known_devices") != 0;
+    let ip_address_known = query_result.get::<i64, _>("ip_address_known") != 0;
+    let user_agent_known = query_result.get::<i64, _>("user_agent_known") != 0;
+    let id_known_device = query_result.get::<Option<i32>, _>("id_known_device");
+    /* * end checking known devices of user */
+
+    /* * remember this device */
+    match id_known_device {
+        Some(id_known_device) => {
+            let sql_query = sqlx::query("UPDATE known_devices SET last_seen_at = ? WHERE id_known_device = ?");
+            let _ = sql_query
+                .bind(time_now)
+                .bind(id_known_device)
+                .execute(db_pool)
+                .await?;
+        },
+        None => {
+            let sql_query = sqlx::query("INSERT INTO known_devices (
+                username,
+                ip_address,
+                user_agent,
+                first_seen_at,
+                last_seen_at
+            ) VALUES (?, ?, ?, ?, ?);");
+            let _ = sql_query
+                .bind(username)
+                .bind(&client.ip_address)
+                .bind(&client.user_agent)
+                .bind(time_now)
+                .bind(time_now)
+                .execute(db_pool)
+                .await?;
+        }
+    }
+    /* * end remember this device */
+
+    /* * first sign-in ever or already known device, nothing to alert */
+    if !has_known_devices || (ip_address_known && user_agent_known) {
+        return Ok(false);
+    }
+    /* * end first sign-in ever or already known device, nothing to alert */
+
+    /* * store security event with one-click disavow token */
+    let disavow_token = generate_secret_token();
+    let sql_query = sqlx::query("INSERT INTO security_events (
+        username,
+        event_type,
+        ip_address,
+        user_agent,
+        disavow_token,
+        created_at
+    ) VALUES (?, ?, ?, ?, ?, ?);");
+    let _ = sql_query
+        .bind(username)
+        .bind("new_device_signin")
+        .bind(&client.ip_address)
+        .bind(&client.user_agent)
+        .bind(hash_token(&disavow_token))
+        .bind(time_now)
+        .execute(db_pool)
+        .await?;
+    /* * end store security event with one-click disavow token */
+
+    /* * notify user by email when available */
+    let sql_query = sqlx::query("SELECT email FROM user WHERE username = ?");
+    let query_result = sql_query
+        .bind(username)
+        .fetch_one(db_pool)
+        .await?;
+    if let Some(email) = query_result.get::<Option<String>, _>("email") {
+        let disavow_link = format!(
+            "{}/api/auth/security-events/disavow?token={}",
+            app_state.public_url.trim_end_matches('/'),
+            disavow_token
+        );
+        let message = MailMessage {
+            to: email,
+            subject: String::from("new sign-in to your account"),
+            body: format!(
+                "hi {},\n\nyour account was just signed in from a new device or location.\n\nip address: {}\ndevice: {}\n\nif this was you, you can ignore this email.\nif this wasn't you, open the link below to sign out every session and reset your password:\n{}\n",
+                username,
+                client.ip_address.as_deref().unwrap_or("unknown"),
+                client.user_agent.as_deref().unwrap_or("unknown"),
+                disavow_link
+            )
+        };
+        app_state.mailer.send(message).await?;
+    }
+    /* * end notify user by email when available */
+
+    Ok(true)
+}
+/* * end record sign-in device, alert user when ip address or device has not been seen before */
@@ -0,0 +1,205 @@
+use actix_web::http::StatusCode;
+use base64::{
+    Engine,
+    engine::general_purpose::URL_SAFE_NO_PAD
+};
+use chrono::Utc;
+use sqlx::Row;
+use validator::Validate;
+
+use crate::{
+    types::AppState,
+    auth::{
+        model::PasskeyRegistrationPayload,
+        types::{
+            ResponsePasskeyCreationOptions,
+            PasskeyRelyingParty,
+            PasskeyUserEntity,
+            PasskeyCredentialParameter,
+            PasskeyCredentialDescriptor,
+            PasskeyAuthenticatorSelection
+        },
+        helpers::{
+            store_webauthn_challenge,
+            take_webauthn_challenge
+        },
+        webauthn::{
+            COSE_ALG_ES256,
+            generate_challenge,
+            decode_base64url,
+            verify_client_data,
+            parse_attestation_object,
+            parse_authenticator_data,
+            verify_authenticator_data
+        },
+        constants::CHRONO_WEBAUTHN_CHALLENGE_EXPIRED
+    },
+    errors::{
+        AppError,
+        AppErrorMessage
+    }
+};
+
+pub async fn passkey_register_start_service(
+    app_state: &AppState,
+    username: &str
+) -> Result<ResponsePasskeyCreationOptions, AppError>
+{
+    let db_pool = &app_state.db_pool;
+
+    /* * get stored user display name */
+    let sql_query = sqlx::query("SELECT full_name FROM user WHERE username = ?");
+    let query_result = sql_query
+        .bind(username)
+        .fetch_one(db_pool)
+        .await
+        .map_err(|e| {
+            let app_err_message = AppErrorMessage {
+                code: StatusCode::NOT_FOUND.as_u16(),
+                message: format!("the user '{}' was not found in the database.", username),
+                details: Some(e.to_string())
+            };
+            AppError::NotFound(app_err_message.into())
+        })?;
+    let stored_full_name = query_result.get::<Option<String>, _>("full_name");
+    /* * end get stored user display name */
+
+    /* * exclude passkeys already registered by user */
+    let sql_query = sqlx::query("SELECT credential_id FROM passkeys WHERE username = ?");
+    let exclude_credentials = sql_query
+        .bind(username)
+        .fetch_all(db_pool)
+        .await?
+        .iter()
+        .map(|row| PasskeyCredentialDescriptor {
+            credential_type: "public-key",
+            id: row.get::<String, _>("credential_id")
+        })
+        .collect::<Vec<_>>();
+    /* * end exclude passkeys already registered by user */
+
+    /* * generate and store registration challenge */
+    let challenge = generate_challenge();
+    store_webauthn_challenge(db_pool, &challenge, Some(username), "registration").await?;
+    /* * end generate and store registration challenge */
+
+    let webauthn = &app_state.webauthn;
+    Ok(
+        ResponsePasskeyCreationOptions {
+            challenge,
+            rp: PasskeyRelyingParty {
+                id: webauthn.rp_id.clone(),
+                name: webauthn.rp_name.clone()
+            },
+            user: PasskeyUserEntity {
+                id: URL_SAFE_NO_PAD.encode(username),
+                name: username.to_string(),
+                display_name: stored_full_name.unwrap_or(username.to_string())
+            },
+            pub_key_cred_params: vec![
+                PasskeyCredentialParameter {
+                    credential_type: "public-key",
+                    alg: COSE_ALG_ES256
+                }
+            ],
+            timeout: CHRONO_WEBAUTHN_CHALLENGE_EXPIRED.num_milliseconds(),
+            attestation: "none",
+            exclude_credentials,
+            authenticator_selection: PasskeyAuthenticatorSelection {
+                resident_key: "preferred",
+                user_verification: "required"
+            }
+        }
+    )
+}
+
+pub async fn passkey_register_finish_service(
+    app_state: &AppState,
+    username: &str,
+    payload: PasskeyRegistrationPayload
+) -> Result<String, AppError>
+{
+    /* * validating user input */
+    payload.validate()?;
+    /* * end validating user input */
+
+    let db_pool = &app_state.db_pool;
+    let webauthn = &app_state.webauthn;
+
+    /* * verify clientDataJSON & consume challenge */
+    let client_data_json = decode_base64url("clientDataJSON", &payload.response.client_data_json)?;
+    let client_data = verify_client_data(&client_data_json, "webauthn.create", &webauthn.rp_origin)?;
+    let challenge_username = take_webauthn_challenge(db_pool, &client_data.challenge, "registration").await?;
+    if challenge_username.as_deref() != Some(username) {
+        let app_err_message: AppErrorMessage<Option<bool>> = AppErrorMessage {
+            code: StatusCode::FORBIDDEN.as_u16(),
+            message: String::from("passkey challenge was not issued for this user."),
+            details: None
+        };
+        return Err(AppError::Forbidden(app_err_message.into()));
+    }
+    /* * end verify clientDataJSON & consume challenge */
+
+    /* * verify attestationObject & extract credential */
+    let attestation_object = decode_base64url("attestationObject", &payload.response.attestation_object)?;
+    let raw_auth_data = parse_attestation_object(&attestation_object)?;
+    let auth_data = parse_authenticator_data(&raw_auth_data)?;
+    verify_authenticator_data(&auth_data, &webauthn.rp_id)?;
+    let attested_credential = auth_data.attested_credential.ok_or_else(|| {
+        let app_err_message: AppErrorMessage<Option<bool>> = AppErrorMessage {
+            code: StatusCode::UNAUTHORIZED.as_u16(),
+            message: String::from("passkey verification failed. attested credential data is missing."),
+            details: None
+        };
+        AppError::Unauthorized(app_err_message.into())
+    })?;
+    let credential_id = URL_SAFE_NO_PAD.encode(&attested_credential.credential_id);
+    if credential_id != payload.id.trim_end_matches('=') {
+        let app_err_message: AppErrorMessage<Option<bool>> = AppErrorMessage {
+            code: StatusCode::UNAUTHORIZED.as_u16(),
+            message: String::from("passkey verification failed. credential id mismatch."),
+            details: None
+        };
+        return Err(AppError::Unauthorized(app_err_message.into()));
+    }
+    /* * end verify attestationObject & extract credential */
+
+    /* * checking credential id availability */
+    let sql_query = sqlx::query("SELECT 1 FROM passkeys WHERE credential_id = ?");
+    let query_result = sql_query
+        .bind(&credential_id)
+        .fetch_optional(db_pool)
+        .await?;
+    if query_result.is_some() {
+        let app_err_message: AppErrorMessage<Option<bool>> = AppErrorMessage {
+            code: StatusCode::CONFLICT.as_u16(),
+            message: String::from("this passkey is already registered."),
+            details: None
+        };
+        return Err(AppError::Conflict(app_err_message.into()));
+    }
+    /* * end checking credential id availability */
+
+    /* * storing passkey to passkeys table */
+    let time_now = Utc::now().timestamp();
+    let sql_query = sqlx::query("INSERT INTO passkeys (
+        username,
+        name,
+        credential_id,
+        public_key,
+        sign_count,
+        created_at
+    ) VALUES (?, ?, ?, ?, ?, ?);");
+    let _ = sql_query
+        .bind(username)
+        .bind(&payload.name)
+        .bind(&credential_id)
+        .bind(&attested_credential.public_key)
+        .bind(auth_data.sign_count as i64)
+        .bind(time_now)
+        .execute(db_pool)
+        .await?;
+    /* * end storing passkey to passkeys table */
+
+    Ok(credential_id)
+}
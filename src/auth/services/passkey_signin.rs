@@ -0,0 +1,195 @@
+use actix_web::http::StatusCode;
+use chrono::Utc;
+use sqlx::Row;
+
+use crate::{
+    types::AppState,
+    auth::{
+        model::{
+            PasskeySigninStartPayload,
+            PasskeyAssertionPayload
+        },
+        types::{
+            ResponsePasskeyRequestOptions,
+            PasskeyCredentialDescriptor,
+            ServiceOkSignin,
+            SigninOptions,
+            ClientInfo
+        },
+        helpers::{
+            store_webauthn_challenge,
+            take_webauthn_challenge,
+            generate_token_pair
+        },
+        webauthn::{
+            generate_challenge,
+            decode_base64url,
+            verify_client_data,
+            parse_authenticator_data,
+            verify_authenticator_data,
+            verify_assertion_signature
+        },
+        constants::CHRONO_WEBAUTHN_CHALLENGE_EXPIRED,
+        services::new_device::new_device_signin_service
+    },
+    errors::{
+        AppError,
+        AppErrorMessage
+    }
+};
+
+pub async fn passkey_signin_start_service(
+    app_state: &AppState,
+    payload: PasskeySigninStartPayload
+) -> Result<ResponsePasskeyRequestOptions, AppError>
+{
+    let db_pool = &app_state.db_pool;
+    let username = payload.username.map(|username| username.to_lowercase());
+
+    /* * allow only user passkeys when username provided, otherwise discoverable credentials */
+    let mut allow_credentials = Vec::new();
+    if let Some(username) = &username {
+        let sql_query = sqlx::query("SELECT credential_id FROM passkeys WHERE username = ?");
+        allow_credentials = sql_query
+            .bind(username)
+            .fetch_all(db_pool)
+            .await?
+            .iter()
+            .map(|row| PasskeyCredentialDescriptor {
+                credential_type: "public-key",
+                id: row.get::<String, _>("credential_id")
+            })
+            .collect::<Vec<_>>();
+
+        if allow_credentials.is_empty() {
+            let app_err_message: AppErrorMessage<Option<bool>> = AppErrorMessage {
+                code: StatusCode::NOT_FOUND.as_u16(),
+                message: format!("the user '{}' has no registered passkeys.", username),
+                details: None
+            };
+            return Err(AppError::NotFound(app_err_message.into()));
+        }
+    }
+    /* * end allow only user passkeys when username provided, otherwise discoverable credentials */
+
+    /* * generate and store authentication challenge */
+    let challenge = generate_challenge();
+    store_webauthn_challenge(db_pool, &challenge, username.as_deref(), "authentication").await?;
+    /* * end generate and store authentication challenge */
+
+    Ok(
+        ResponsePasskeyRequestOptions {
+            challenge,
+            rp_id: app_state.webauthn.rp_id.clone(),
+            timeout: CHRONO_WEBAUTHN_CHALLENGE_EXPIRED.num_milliseconds(),
+            user_verification: "required",
+            allow_credentials
+        }
+    )
+}
+
+pub async fn passkey_signin_finish_service(
+    app_state: &AppState,
+    payload: PasskeyAssertionPayload,
+    client: ClientInfo,
+    dpop_jkt: Option<String>
+) -> Result<ServiceOkSignin, AppError>
+{
+    let db_pool = &app_state.db_pool;
+    let webauthn = &app_state.webauthn;
+
+    /* * get stored passkey by credential id */
+    let credential_id = payload.id.trim_end_matches('=');
+    let sql_query = sqlx::query("SELECT id_passkey, username, public_key, sign_count FROM passkeys WHERE credential_id = ?");
+    let query_result = sql_query
+        .bind(credential_id)
+        .fetch_one(db_pool)
+        .await
+        .map_err(|e| {
+            let app_err_message = AppErrorMessage {
+                code: StatusCode::UNAUTHORIZED.as_u16(),
+                message: String::from("Oops! Authentication failed. this passkey is not registered."),
+                details: Some(e.to_string())
+            };
+            AppError::Unauthorized(app_err_message.into())
+        })?;
+    let stored_id_passkey = query_result.get::<i32, _>("id_passkey");
+    let stored_username = query_result.get::<String, _>("username");
+    let stored_public_key = query_result.get::<Vec<u8>, _>("public_key");
+    let stored_sign_count = query_result.get::<i64, _>("sign_count");
+    /* * end get stored passkey by credential id */
+
+    /* * verify clientDataJSON & consume challenge */
+    let client_data_json = decode_base64url("clientDataJSON", &payload.response.client_data_json)?;
+    let client_data = verify_client_data(&client_data_json, "webauthn.get", &webauthn.rp_origin)?;
+    let challenge_username = take_webauthn_challenge(db_pool, &client_data.challenge, "authentication").await?;
+    if let Some(challenge_username) = challenge_username {
+        if challenge_username != stored_username {
+            let app_err_message: AppErrorMessage<Option<bool>> = AppErrorMessage {
+                code: StatusCode::UNAUTHORIZED.as_u16(),
+                message: String::from("Oops! Authentication failed. passkey does not belong to the requested user."),
+                details: None
+            };
+            return Err(AppError::Unauthorized(app_err_message.into()));
+        }
+    }
+    /* * end verify clientDataJSON & consume challenge */
+
+    /* * verify authenticatorData & signature */
+    let raw_auth_data = decode_base64url("authenticatorData", &payload.response.authenticator_data)?;
+    let signature = decode_base64url("signature", &payload.response.signature)?;
+    let auth_data = parse_authenticator_data(&raw_auth_data)?;
+    verify_authenticator_data(&auth_data, &webauthn.rp_id)?;
+    verify_assertion_signature(&stored_public_key, &raw_auth_data, &client_data_json, &signature)?;
+    /* * end verify authenticatorData & signature */
+
+    /* * signature counter must increase, otherwise authenticator may be cloned */
+    let sign_count = auth_data.sign_count as i64;
+    if (sign_count != 0 || stored_sign_count != 0) && sign_count <= stored_sign_count {
+        let app_err_message: AppErrorMessage<Option<bool>> = AppErrorMessage {
+            code: StatusCode::UNAUTHORIZED.as_u16(),
+            message: String::from("Oops! Authentication failed. passkey signature counter did not increase."),
+            details: None
+        };
+        return Err(AppError::Unauthorized(app_err_message.into()));
+    }
+    /* * end signature counter must increase, otherwise authenticator may be cloned */
+
+    /* * update stored passkey usage, only when no concurrent signin consumed the same counter */
+    let time_now = Utc::now().timestamp();
+    let sql_query = sqlx::query("UPDATE passkeys SET sign_count = ?, last_used_at = ? WHERE id_passkey = ? AND sign_count = ?");
+    let query_result = sql_query
+        .bind(sign_count)
+        .bind(time_now)
+        .bind(stored_id_passkey)
+        .bind(stored_sign_count)
+        .execute(db_pool)
+        .await?;
+    if query_result.rows_affected() != 1 {
+        let app_err_message: AppErrorMessage<Option<bool>> = AppErrorMessage {
+            code: StatusCode::UNAUTHORIZED.as_u16(),
+            message: String::from("Oops! Authentication failed. passkey signature counter was already used."),
+            details: None
+        };
+        return Err(AppError::Unauthorized(app_err_message.into()));
+    }
+    /* * end update stored passkey usage, only when no concurrent signin consumed the same counter */
+
+    /* * generate and store access_token & refresh_token */
+    let options = SigninOptions {
+        remember_me: payload.remember_me.unwrap_or(false),
+        browser_mode: payload.browser_mode.unwrap_or(false),
+        dpop_jkt,
+        scope: payload.scope.clone()
+    };
+    let token_pair = generate_token_pair(app_state, &stored_username, &client, options).await?;
+    /* * end generate and store access_token & refresh_token */
+
+    /* * alert user when signing in from new device, never blocks signin */
+    if let Err(e) = new_device_signin_service(app_state, &stored_username, &client).await {
+        log::error!("failed to check new device signin for user '{}': {}", &stored_username, e);
+    }
+    /* * end alert user when signing in from new device, never blocks signin */
+
+    Ok( token_pair )
+}
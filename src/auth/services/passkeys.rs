@@ -0,0 +1,56 @@
+use actix_web::http::StatusCode;
+
+use crate::{
+    types::AppState,
+    auth::model::Passkey,
+    errors::{
+        AppError,
+        AppErrorMessage
+    }
+};
+
+pub async fn get_passkeys_service(
+    app_state: &AppState,
+    username: &str
+) -> Result<Vec<Passkey>, AppError>
+{
+    let db_pool = &app_state.db_pool;
+
+    /* * sql query retrieved all user passkeys */
+    let sql_query = sqlx::query_as::<_, Passkey>("SELECT id_passkey, name, credential_id, sign_count, created_at, last_used_at FROM passkeys WHERE username = ?");
+    let query_result = sql_query
+        .bind(username)
+        .fetch_all(db_pool)
+        .await?;
+    /* * end sql query retrieved all user passkeys */
+
+    Ok(query_result)
+}
+
+pub async fn delete_passkey_service(
+    app_state: &AppState,
+    username: &str,
+    passkey_id_params: u32
+) -> Result<u32, AppError>
+{
+    let db_pool = &app_state.db_pool;
+
+    /* * destroy user passkey from database */
+    let sql_query = sqlx::query("DELETE FROM passkeys WHERE id_passkey = ? AND username = ?");
+    let query_result = sql_query
+        .bind(passkey_id_params)
+        .bind(username)
+        .execute(db_pool)
+        .await?;
+    if query_result.rows_affected() == 0 {
+        let app_err_message: AppErrorMessage<Option<bool>> = AppErrorMessage {
+            code: StatusCode::NOT_FOUND.as_u16(),
+            message: format!("passkey with id '{}' not found.", passkey_id_params),
+            details: None
+        };
+        return Err(AppError::NotFound(app_err_message.into()));
+    }
+    /* * end destroy user passkey from database */
+
+    Ok(passkey_id_params)
+}
@@ -0,0 +1,123 @@
+use actix_web::http::StatusCode;
+use chrono::Utc;
+use sqlx::Row;
+use validator::Validate;
+
+use crate::{
+    types::AppState,
+    auth::{
+        model::PasswordResetPayload,
+        helpers::hash_token
+    },
+    user::{
+        hashing_password,
+        record_user_changes,
+        UserChange
+    },
+    errors::{
+        AppError,
+        AppErrorMessage
+    }
+};
+
+pub async fn password_reset_service(
+    app_state: &AppState,
+    payload: PasswordResetPayload
+) -> Result<String, AppError>
+{
+    /* * validating user input */
+    payload.validate()?;
+    /* * end validating user input */
+
+    let db_pool = &app_state.db_pool;
+    let time_now = Utc::now().timestamp();
+
+    /* * hashing password */
+    let hashed_password = hashing_password(&payload.password)?;
+    /* * end hashing password */
+
+    /* * reset token is single use, locked & consumed in the same transaction as the password change */
+    let mut transaction = db_pool.begin().await?;
+    let hashed_token = hash_token(&payload.token);
+    let sql_query = sqlx::query("SELECT 
+        password_resets.username,
+        password_resets.expires_at,
+        user.id_user
+        FROM password_resets INNER JOIN user ON user.username = password_resets.username
+        WHERE password_resets.token = ?
+        AND user.deleted_at IS NULL
+        FOR UPDATE
+    ");
+    let query_result = sql_query
+        .bind(&hashed_token)
+        .fetch_optional(&mut *transaction)
+        .await?
+        .ok_or_else(|| {
+            let app_err_message: AppErrorMessage<Option<bool>> = AppErrorMessage {
+                code: StatusCode::NOT_FOUND.as_u16(),
+                message: String::from("password reset token is invalid or has already been used."),
+                details: None
+            };
+            AppError::NotFound(app_err_message.into())
+        })?;
+    let stored_username = query_result.get::<String, _>("username");
+    let stored_expires_at = query_result.get::<i64, _>("expires_at");
+    let stored_id_user = query_result.get::<i32, _>("id_user");
+    if time_now >= stored_expires_at {
+        let app_err_message: AppErrorMessage<Option<bool>> = AppErrorMessage {
+            code: StatusCode::FORBIDDEN.as_u16(),
+            message: String::from("password reset token has been expired."),
+            details: None
+        };
+        return Err(AppError::Forbidden(app_err_message.into()));
+    }
+    let sql_query = sqlx::query("DELETE FROM password_resets WHERE token = ?");
+    let query_result = sql_query
+        .bind(&hashed_token)
+        .execute(&mut *transaction)
+        .await?;
+    if query_result.rows_affected() != 1 {
+        let app_err_message: AppErrorMessage<Option<bool>> = AppErrorMessage {
+            code: StatusCode::NOT_FOUND.as_u16(),
+            message: String::from("password reset token is invalid or has already been used."),
+            details: None
+        };
+        return Err(AppError::NotFound(app_err_message.into()));
+    }
+    /* * end reset token is single use, locked & consumed in the same transaction as the password change */
+
+    /* * update stored user & credentials password */
+    let sql_query = sqlx::query("UPDATE user SET password = ?, updated_at = ?, version = version + 1 WHERE username = ?");
+    let _ = sql_query
+        .bind(&hashed_password)
+        .bind(time_now)
+        .bind(&stored_username)
+        .execute(&mut *transaction)
+        .await?;
+    let sql_query = sqlx::query("UPDATE credentials SET password = ?, password_reset_required = FALSE, updated_at = ? WHERE username = ?");
+    let _ = sql_query
+        .bind(&hashed_password)
+        .bind(time_now)
+        .bind(&stored_username)
+        .execute(&mut *transaction)
+        .await?;
+    let changes = vec![UserChange { field: "password", old_value: Some(String::new()), new_value: Some(String::new()) }];
+    record_user_changes(&mut *transaction, stored_id_user as u32, Some(&stored_username), changes).await?;
+    /* * end update stored user & credentials password */
+
+    /* * other reset links of the user are void, and every existing session must sign in again */
+    let sql_query = sqlx::query("DELETE FROM password_resets WHERE username = ?");
+    let _ = sql_query
+        .bind(&stored_username)
+        .execute(&mut *transaction)
+        .await?;
+    let sql_query = sqlx::query("DELETE FROM sessions WHERE username = ?");
+    let _ = sql_query
+        .bind(&stored_username)
+        .execute(&mut *transaction)
+        .await?;
+    transaction.commit().await?;
+    /* * end other reset links of the user are void, and every existing session must sign in again */
+
+    Ok(stored_username)
+}
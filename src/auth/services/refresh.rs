@@ -1,18 +1,5 @@
-use actix_web::{
-    http::StatusCode, 
-    cookie::Cookie,
-};
+use actix_web::http::StatusCode;
 use sqlx::Row;
-use jsonwebtoken::{
-    decode,
-    DecodingKey,
-    Validation,
-    Algorithm::HS256,
-    errors::ErrorKind as JwtErrorKind,
-    encode,
-    EncodingKey,
-    Header
-};
 use chrono::Utc;
 
 use crate::{
@@ -23,61 +10,130 @@ use crate::{
     auth::{
         types::{
             Claims, 
+            Confirmation,
+            ClientInfo,
             ServiceOkRefreshToken
         }, 
-        constants::CHRONO_ACCESS_TOKEN_EXPIRED,
-        helpers::convert_timestamp_to_actix_duration
+        helpers::{
+            convert_timestamp_to_actix_duration,
+            generate_jti,
+            get_session_expiry,
+            resolve_scope
+        },
+        token::{
+            TokenError,
+            TokenKind
+        },
+        constants::{
+            TOKEN_TYPE_BEARER,
+            TOKEN_TYPE_DPOP
+        }
     },
     AppState
 };
 
 pub async fn refresh_token_service(
     app_state: &AppState,
-    refresh_token_cookie: Option<Cookie<'_>>
+    refresh_token: Option<String>,
+    dpop_jkt: Option<String>,
+    client: ClientInfo
 ) -> Result<ServiceOkRefreshToken, AppError>
 {
-    /* * check and get refresh_token cookie value */
-    let refresh_token_cookie = refresh_token_cookie.map(|cookie| cookie.value().to_string() )
+    /* * check and get refresh_token value */
+    let refresh_token = refresh_token
     .ok_or_else(|| {
         let app_err_message: AppErrorMessage<Option<bool>> = AppErrorMessage {
             code: StatusCode::UNAUTHORIZED.as_u16(),
-            message: format!("missing or invalid refresh_token cookie"),
+            message: String::from("missing or invalid refresh_token"),
             details: None
         };
         AppError::Unauthorized(app_err_message.into())
     })?;
-    /* * end check and get refresh_token cookie value */
+    /* * end check and get refresh_token value */
     
     let db_pool = &app_state.db_pool;
-    /* * check refresh_token_cookie is exists from db */
-    let sql_query = sqlx::query("SELECT username, refresh_token, max_age FROM credentials WHERE refresh_token = ?");
+    /* * check refresh_token session is exists from db */
+    let sql_query = sqlx::query("SELECT 
+        sessions.id_session,
+        sessions.username,
+        sessions.refresh_token,
+        sessions.max_age,
+        sessions.created_at,
+        sessions.last_refreshed_at,
+        sessions.browser_mode,
+        sessions.dpop_jkt,
+        sessions.scope,
+        user.role
+        FROM sessions INNER JOIN user ON user.username = sessions.username
+        WHERE sessions.refresh_token = ? AND user.deleted_at IS NULL
+    ");
     let query_result = sql_query
-        .bind(&refresh_token_cookie)
+        .bind(&refresh_token)
         .fetch_one(db_pool)
         .await
         .map_err(|e| {
             let app_err_message = AppErrorMessage {
                 code: StatusCode::NOT_FOUND.as_u16(),
-                message: String::from("refresh_token not found in the database."),
+                message: String::from("refresh_token session not found in the database. it may have been revoked."),
                 details: Some(e.to_string())
             };
             AppError::NotFound(app_err_message.into())
         })?;
+    let stored_id_session = query_result.get::<i32, _>("id_session");
     let stored_username = query_result.get::<String, _>("username");
     let stored_refresh_token = query_result.get::<&str, _>("refresh_token");
     let stored_max_age = query_result.get::<i64, _>("max_age");
-    /* * end check refresh_token_cookie is exists from db */
+    let stored_created_at = query_result.get::<i64, _>("created_at");
+    let stored_last_refreshed_at = query_result.get::<Option<i64>, _>("last_refreshed_at");
+    let stored_browser_mode = query_result.get::<bool, _>("browser_mode");
+    let stored_dpop_jkt = query_result.get::<Option<String>, _>("dpop_jkt");
+    let stored_scope = query_result.get::<Option<String>, _>("scope");
+    let stored_role = query_result.get::<String, _>("role");
+    /* * end check refresh_token session is exists from db */
+
+    /* * DPoP bound session can only be refreshed with proof of the same key */
+    if stored_dpop_jkt.is_some() && stored_dpop_jkt != dpop_jkt {
+        let app_err_message: AppErrorMessage<Option<bool>> = AppErrorMessage {
+            code: StatusCode::UNAUTHORIZED.as_u16(),
+            message: String::from("refresh_token is bound to a DPoP key. a DPoP proof signed with the same key is required."),
+            details: None
+        };
+        return Err(AppError::Unauthorized(app_err_message.into()));
+    }
+    /* * end DPoP bound session can only be refreshed with proof of the same key */
+
+    /* * enforce session idle timeout & absolute lifetime */
+    let time_now = Utc::now().naive_utc().timestamp();
+    let session_expiry = get_session_expiry(
+        &app_state.sessions,
+        stored_created_at,
+        stored_last_refreshed_at.unwrap_or(stored_created_at),
+        stored_max_age
+    );
+    if session_expiry.is_expired(time_now) {
+        let sql_query = sqlx::query("DELETE FROM sessions WHERE id_session = ?");
+        let _ = sql_query
+            .bind(stored_id_session)
+            .execute(db_pool)
+            .await?;
 
-    let secret_refresh_token = &app_state.secret_refresh_token;
+        let message = match time_now >= session_expiry.session_expires_at {
+            true => "session has reached its maximum lifetime. please log in again.",
+            false => "session has been idle for too long. please log in again."
+        };
+        let app_err_message: AppErrorMessage<Option<bool>> = AppErrorMessage {
+            code: StatusCode::FORBIDDEN.as_u16(),
+            message: String::from(message),
+            details: None
+        };
+        return Err(AppError::Forbidden(app_err_message.into()));
+    }
+    /* * end enforce session idle timeout & absolute lifetime */
     /* * decode refresh_token from db */
-    let decoded_refresh_token = decode::<Claims>(
-        &stored_refresh_token,
-        &DecodingKey::from_secret(secret_refresh_token.as_ref()),
-        &Validation::new(HS256)
-    )
+    let decoded_refresh_token = app_state.token_format.decode(TokenKind::Refresh, stored_refresh_token)
     .map_err(|e| {
-        match e.kind() {
-            JwtErrorKind::ExpiredSignature => {
+        match e {
+            TokenError::Expired => {
                 let app_err_message: AppErrorMessage<Option<bool>> = AppErrorMessage {
                     code: StatusCode::FORBIDDEN.as_u16(),
                     message: String::from("signature refresh_token is expired."),
@@ -98,8 +154,7 @@ pub async fn refresh_token_service(
     /* * end decode refresh_token from db */
     
     /* * check refresh_token is expired */
-    let time_now = Utc::now().naive_utc().timestamp();
-    if time_now >= decoded_refresh_token.claims.exp {
+    if time_now >= decoded_refresh_token.exp {
         let app_err_message: AppErrorMessage<Option<bool>> = AppErrorMessage {
             code: StatusCode::FORBIDDEN.as_u16(),
             message: String::from("refresh token has been expired. please log in again."),
@@ -110,7 +165,7 @@ pub async fn refresh_token_service(
     /* * end check refresh_token is expired */
 
     /* * matching stored_username with decoded_refresh_token_username */
-    if !stored_username.eq(&decoded_refresh_token.claims.username) {
+    if !stored_username.eq(&decoded_refresh_token.username) {
         let app_err_message: AppErrorMessage<Option<bool>> = AppErrorMessage {
             code: StatusCode::FORBIDDEN.as_u16(),
             message: String::from("stored_username is mismatch with decoded_refresh_token_username"),
@@ -120,19 +175,20 @@ pub async fn refresh_token_service(
     }
     /* * end matching stored_username with decoded_refresh_token_username */
 
-    /* * generate new encoded_access_token */
-    let secret_access_token = &app_state.secret_access_token;
-    let access_token_exp = (Utc::now().naive_utc() + *CHRONO_ACCESS_TOKEN_EXPIRED).timestamp();
+    /* * generate new encoded_access_token, scope follows current user role */
+    let scope = resolve_scope(&stored_role, stored_scope.as_deref());
+    let access_token_exp = (Utc::now() + app_state.tokens.access_token_expired).timestamp();
     let claims = Claims {
         username: stored_username.to_string(),
         iat: time_now,
-        exp: access_token_exp 
+        exp: access_token_exp,
+        jti: None,
+        role: Some(stored_role),
+        scope: Some(scope.clone()),
+        cnf: Confirmation::from_jkt(stored_dpop_jkt.clone()),
+        act: None
     };
-    let new_encoded_access_token = encode(
-        &Header::default(),
-        &claims,
-        &EncodingKey::from_secret(secret_access_token.as_ref())
-    )
+    let new_encoded_access_token = app_state.token_format.encode(TokenKind::Access, &claims)
     .map_err(|e| {
         let app_err_message = AppErrorMessage {
             code: StatusCode::INTERNAL_SERVER_ERROR.as_u16(),
@@ -141,20 +197,20 @@ pub async fn refresh_token_service(
         };
         AppError::InternalServerError(app_err_message.into())
     })?;
-    /* * end generate new encoded_access_token */
+    /* * end generate new encoded_access_token, scope follows current user role */
 
     /* * generate new encoded_refresh_token with previous exp date */
-    let secret_refresh_token = &app_state.secret_refresh_token;
     let claims = Claims {
         username: stored_username.to_string(),
         iat: time_now,
-        exp: decoded_refresh_token.claims.exp
+        exp: decoded_refresh_token.exp,
+        jti: Some(generate_jti()),
+        role: None,
+        scope: None,
+        cnf: None,
+        act: None
     };
-    let new_encoded_refresh_token = encode(
-        &Header::default(),
-        &claims,
-        &EncodingKey::from_secret(secret_refresh_token.as_ref())
-    )
+    let new_encoded_refresh_token = app_state.token_format.encode(TokenKind::Refresh, &claims)
     .map_err(|e| {
         let app_err_message = AppErrorMessage {
             code: StatusCode::INTERNAL_SERVER_ERROR.as_u16(),
@@ -165,22 +221,26 @@ pub async fn refresh_token_service(
     })?;
     /* * end generate new encoded_refresh_token with previous exp date */
 
-    /* * stored new encoded refresh token to db.credentials */
-    let sql_query = sqlx::query("UPDATE credentials SET refresh_token = ? WHERE refresh_token = ?");
-    let _ = sql_query
+    /* * stored new encoded refresh token to session */
+    let sql_query = sqlx::query("UPDATE sessions SET 
+        refresh_token = ?,
+        last_refreshed_at = ?,
+        ip_address = ?,
+        user_agent = ?
+        WHERE id_session = ? AND refresh_token = ?
+    ");
+    let query_result = sql_query
         .bind(&new_encoded_refresh_token)
-        .bind(&refresh_token_cookie)
+        .bind(time_now)
+        .bind(&client.ip_address)
+        .bind(&client.user_agent)
+        .bind(stored_id_session)
+        .bind(&refresh_token)
         .execute(db_pool)
         .await?;
-    /* * end stored new encoded refresh token to db.credentials */
-    /* * check is new encoded refresh token stored in db.credentials */
-    let sql_query = sqlx::query("SELECT refresh_token FROM credentials WHERE username = ?");
-    let query_result = sql_query
-        .bind(&stored_username)
-        .fetch_one(db_pool)
-        .await?;
-    let stored_new_refresh_token = query_result.get::<&str, _>("refresh_token");
-    if stored_new_refresh_token != new_encoded_refresh_token {
+    /* * end stored new encoded refresh token to session */
+    /* * check is new encoded refresh token stored in session */
+    if query_result.rows_affected() == 0 {
         let app_err_message: AppErrorMessage<Option<bool>> = AppErrorMessage {
             code: StatusCode::INTERNAL_SERVER_ERROR.as_u16(),
             message: String::from("an unexpected error occurred while processing your request. refresh_token is mismatch. please try again."),
@@ -188,14 +248,23 @@ pub async fn refresh_token_service(
         };
         return Err(AppError::InternalServerError(app_err_message.into()));
     }
-    /* * end check is new encoded refresh token stored in db.credentials */
+    /* * end check is new encoded refresh token stored in session */
 
     let refresh_token_previous_max_age = convert_timestamp_to_actix_duration(stored_max_age);
     Ok( 
         ServiceOkRefreshToken {
+            username: stored_username,
             access_token: new_encoded_access_token,
+            access_token_max_age: convert_timestamp_to_actix_duration(access_token_exp),
             refresh_token: new_encoded_refresh_token,
-            refresh_token_previous_max_age
+            refresh_token_previous_max_age,
+            browser_mode: stored_browser_mode,
+            token_type: match stored_dpop_jkt {
+                Some(_) => TOKEN_TYPE_DPOP,
+                None => TOKEN_TYPE_BEARER
+            },
+            scope,
+            session_expiry: get_session_expiry(&app_state.sessions, stored_created_at, time_now, stored_max_age)
         }
     )
 }
\ No newline at end of file
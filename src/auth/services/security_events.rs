@@ -0,0 +1,171 @@
+use actix_web::http::StatusCode;
+use chrono::Utc;
+use sqlx::Row;
+
+use crate::{
+    types::AppState,
+    mailer::MailMessage,
+    auth::{
+        model::SecurityEvent,
+        types::ServiceOkDisavow,
+        helpers::{
+            generate_secret_token,
+            hash_token
+        },
+        constants::{
+            CHRONO_SECURITY_EVENT_DISAVOW_EXPIRED,
+            CHRONO_PASSWORD_RESET_EXPIRED
+        }
+    },
+    errors::{
+        AppError,
+        AppErrorMessage
+    }
+};
+
+pub async fn get_security_events_service(
+    app_state: &AppState,
+    username: &str
+) -> Result<Vec<SecurityEvent>, AppError>
+{
+    let db_pool = &app_state.db_pool;
+
+    /* * sql query retrieved all user security events */
+    let sql_query = sqlx::query_as::<_, SecurityEvent>("SELECT
+        id_security_event,
+        event_type,
+        ip_address,
+        user_agent,
+        created_at,
+        disavowed_at
+        FROM security_events WHERE username = ?
+        ORDER BY id_security_event DESC
+    ");
+    let query_result = sql_query
+        .bind(username)
+        .fetch_all(db_pool)
+        .await?;
+    /* * end sql query retrieved all user security events */
+
+    Ok(query_result)
+}
+
+/* * "this wasn't me": revoke every session, force password reset & mail reset link */
+pub async fn disavow_security_event_service(
+    app_state: &AppState,
+    token: &str
+) -> Result<ServiceOkDisavow, AppError>
+{
+    let db_pool = &app_state.db_pool;
+    let time_now = Utc::now().timestamp();
+    let invalid_link = || {
+        let app_err_message: AppErrorMessage<Option<bool>> = AppErrorMessage {
+            code: StatusCode::NOT_FOUND.as_u16(),
+            message: String::from("this link is invalid or has already been used."),
+            details: None
+        };
+        AppError::NotFound(app_err_message.into())
+    };
+
+    let mut transaction = db_pool.begin().await?;
+
+    /* * check disavow token is exists from db */
+    let sql_query = sqlx::query("SELECT security_events.id_security_event, security_events.username, security_events.created_at, user.email
+        FROM security_events
+        LEFT JOIN user ON user.username = security_events.username
+        WHERE security_events.disavow_token = ? AND security_events.disavowed_at IS NULL
+        FOR UPDATE");
+    let query_result = sql_query
+        .bind(hash_token(token))
+        .fetch_optional(&mut *transaction)
+        .await?
+        .ok_or_else(invalid_link)?;
+    let stored_id_security_event = query_result.get::<i64, _>("id_security_event");
+    let stored_username = query_result.get::<String, _>("username");
+    let stored_created_at = query_result.get::<i64, _>("created_at");
+    let stored_email = query_result.get::<Option<String>, _>("email");
+    if time_now >= stored_created_at + CHRONO_SECURITY_EVENT_DISAVOW_EXPIRED.num_seconds() {
+        let app_err_message: AppErrorMessage<Option<bool>> = AppErrorMessage {
+            code: StatusCode::FORBIDDEN.as_u16(),
+            message: String::from("this link has been expired. please contact support to secure your account."),
+            details: None
+        };
+        return Err(AppError::Forbidden(app_err_message.into()));
+    }
+    /* * end check disavow token is exists from db */
+
+    /* * mark security event as disavowed, link is single use */
+    let sql_query = sqlx::query("UPDATE security_events SET disavowed_at = ? WHERE id_security_event = ? AND disavowed_at IS NULL");
+    let query_result = sql_query
+        .bind(time_now)
+        .bind(stored_id_security_event)
+        .execute(&mut *transaction)
+        .await?;
+    if query_result.rows_affected() != 1 {
+        return Err(invalid_link());
+    }
+    /* * end mark security event as disavowed, link is single use */
+
+    /* * revoke every user session */
+    let sql_query = sqlx::query("DELETE FROM sessions WHERE username = ?");
+    let _ = sql_query
+        .bind(&stored_username)
+        .execute(&mut *transaction)
+        .await?;
+    /* * end revoke every user session */
+
+    /* * force password reset */
+    let sql_query = sqlx::query("UPDATE credentials SET password_reset_required = TRUE WHERE username = ?");
+    let _ = sql_query
+        .bind(&stored_username)
+        .execute(&mut *transaction)
+        .await?;
+    /* * end force password reset */
+
+    /* * issue password reset token */
+    let reset_token = generate_secret_token();
+    let reset_token_exp = (Utc::now() + *CHRONO_PASSWORD_RESET_EXPIRED).timestamp();
+    let sql_query = sqlx::query("INSERT INTO password_resets (token, username, expires_at) VALUES (?, ?, ?)");
+    let _ = sql_query
+        .bind(hash_token(&reset_token))
+        .bind(&stored_username)
+        .bind(reset_token_exp)
+        .execute(&mut *transaction)
+        .await?;
+    /* * end issue password reset token */
+
+    transaction.commit().await?;
+
+    /* * mail reset link to account owner, account is already secured so mail failure only gets logged */
+    let mut reset_link_sent = false;
+    if let Some(email) = stored_email {
+        let reset_link = format!(
+            "{}/api/auth/password-reset?token={}",
+            app_state.public_url.trim_end_matches('/'),
+            reset_token
+        );
+        let message = MailMessage {
+            to: email,
+            subject: String::from("reset your password"),
+            body: format!(
+                "hi {},\n\nevery session of your account has been signed out. open the link below within {} minutes to choose a new password:\n{}\n",
+                stored_username,
+                CHRONO_PASSWORD_RESET_EXPIRED.num_minutes(),
+                reset_link
+            )
+        };
+        match app_state.mailer.send(message).await {
+            Ok(_) => reset_link_sent = true,
+            Err(e) => log::error!("failed to send password reset link to user '{}': {}", &stored_username, e)
+        }
+    }
+    /* * end mail reset link to account owner, account is already secured so mail failure only gets logged */
+
+    Ok(
+        ServiceOkDisavow {
+            username: stored_username,
+            reset_link_sent
+        }
+    )
+}
+/* * end "this wasn't me": revoke every session, force password reset & mail reset link */
@@ -0,0 +1,99 @@
+use actix_web::http::StatusCode;
+use chrono::Utc;
+
+use crate::{
+    types::AppState,
+    auth::{
+        model::Session,
+        helpers::get_session_expiry
+    },
+    errors::{
+        AppError,
+        AppErrorMessage
+    }
+};
+
+pub async fn get_sessions_service(
+    app_state: &AppState,
+    username: &str
+) -> Result<Vec<Session>, AppError>
+{
+    let db_pool = &app_state.db_pool;
+    let time_now = Utc::now().timestamp();
+
+    /* * sql query retrieved all active user sessions */
+    let sql_query = sqlx::query_as::<_, Session>("SELECT 
+        id_session,
+        ip_address,
+        user_agent,
+        created_at,
+        last_refreshed_at,
+        max_age
+        FROM sessions WHERE username = ? AND max_age > ?
+        ORDER BY created_at DESC
+    ");
+    let query_result = sql_query
+        .bind(username)
+        .bind(time_now)
+        .fetch_all(db_pool)
+        .await?
+        .into_iter()
+        .filter(|session| {
+            !get_session_expiry(
+                &app_state.sessions,
+                session.created_at,
+                session.last_refreshed_at.unwrap_or(session.created_at),
+                session.max_age
+            ).is_expired(time_now)
+        })
+        .collect::<Vec<_>>();
+    /* * end sql query retrieved all active user sessions */
+
+    Ok(query_result)
+}
+
+pub async fn revoke_session_service(
+    app_state: &AppState,
+    username: &str,
+    session_id_params: u32
+) -> Result<u32, AppError>
+{
+    let db_pool = &app_state.db_pool;
+
+    /* * destroy user session from database */
+    let sql_query = sqlx::query("DELETE FROM sessions WHERE id_session = ? AND username = ?");
+    let query_result = sql_query
+        .bind(session_id_params)
+        .bind(username)
+        .execute(db_pool)
+        .await?;
+    if query_result.rows_affected() == 0 {
+        let app_err_message: AppErrorMessage<Option<bool>> = AppErrorMessage {
+            code: StatusCode::NOT_FOUND.as_u16(),
+            message: format!("session with id '{}' not found.", session_id_params),
+            details: None
+        };
+        return Err(AppError::NotFound(app_err_message.into()));
+    }
+    /* * end destroy user session from database */
+
+    Ok(session_id_params)
+}
+
+pub async fn revoke_all_sessions_service(
+    app_state: &AppState,
+    username: &str
+) -> Result<u64, AppError>
+{
+    let db_pool = &app_state.db_pool;
+
+    /* * destroy every user session (invalidates all refresh tokens) */
+    let sql_query = sqlx::query("DELETE FROM sessions WHERE username = ?");
+    let query_result = sql_query
+        .bind(username)
+        .execute(db_pool)
+        .await?;
+    /* * end destroy every user session (invalidates all refresh tokens) */
+
+    Ok(query_result.rows_affected())
+}
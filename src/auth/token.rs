@@ -0,0 +1,514 @@
+use std::{
+    fmt,
+    sync::Arc
+};
+use base64::{
+    Engine,
+    engine::general_purpose::URL_SAFE_NO_PAD
+};
+use blake2::{
+    Blake2bMac,
+    digest::{
+        KeyInit,
+        Mac,
+        consts::{
+            U32,
+            U56
+        }
+    }
+};
+use chacha20::{
+    XChaCha20,
+    cipher::{
+        KeyIvInit,
+        StreamCipher
+    }
+};
+use chrono::{
+    DateTime,
+    SecondsFormat
+};
+use ed25519_dalek::{
+    Signature,
+    Signer,
+    SigningKey,
+    VerifyingKey
+};
+use hmac::Hmac;
+use jsonwebtoken::{
+    decode,
+    encode,
+    Algorithm,
+    DecodingKey,
+    EncodingKey,
+    Header,
+    Validation,
+    errors::ErrorKind as JwtErrorKind
+};
+use rand::{
+    RngCore,
+    rngs::OsRng
+};
+use serde_json::Value;
+use sha2::Sha256;
+
+use crate::auth::types::Claims;
+
+/* * access & refresh tokens are minted with different keys */
+#[derive(Clone, Copy, Debug)]
+pub enum TokenKind {
+    Access,
+    Refresh
+}
+
+#[derive(Debug)]
+pub enum TokenError {
+    Expired,
+    Invalid(String)
+}
+
+impl fmt::Display for TokenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TokenError::Expired => write!(f, "token has expired."),
+            TokenError::Invalid(message) => write!(f, "{}", message)
+        }
+    }
+}
+
+/* * pluggable token format, every format carries the same claims */
+pub trait TokenFormat: Send + Sync + fmt::Debug {
+    fn encode(&self, kind: TokenKind, claims: &Claims) -> Result<String, TokenError>;
+    fn decode(&self, kind: TokenKind, token: &str) -> Result<Claims, TokenError>;
+    /* * PASERK of the access token verifying key, symmetric formats have none to publish */
+    fn public_key(&self) -> Option<String> {
+        None
+    }
+}
+/* * end pluggable token format, every format carries the same claims */
+
+/* * jwt HS256, signed with SECRET_ACCESS_TOKEN / SECRET_REFRESH_TOKEN */
+pub struct JwtFormat {
+    secret_access_token: String,
+    secret_refresh_token: String
+}
+
+impl fmt::Debug for JwtFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("JwtFormat").finish_non_exhaustive()
+    }
+}
+
+impl JwtFormat {
+    fn secret(&self, kind: TokenKind) -> &[u8] {
+        match kind {
+            TokenKind::Access => self.secret_access_token.as_ref(),
+            TokenKind::Refresh => self.secret_refresh_token.as_ref()
+        }
+    }
+}
+
+impl TokenFormat for JwtFormat {
+    fn encode(&self, kind: TokenKind, claims: &Claims) -> Result<String, TokenError> {
+        encode(&Header::default(), claims, &EncodingKey::from_secret(self.secret(kind)))
+            .map_err(|e| TokenError::Invalid(e.to_string()))
+    }
+
+    fn decode(&self, kind: TokenKind, token: &str) -> Result<Claims, TokenError> {
+        decode::<Claims>(token, &DecodingKey::from_secret(self.secret(kind)), &Validation::new(Algorithm::HS256))
+            .map(|token_data| token_data.claims)
+            .map_err(|e| match e.kind() {
+                JwtErrorKind::ExpiredSignature => TokenError::Expired,
+                _ => TokenError::Invalid(e.to_string())
+            })
+    }
+}
+/* * end jwt HS256, signed with SECRET_ACCESS_TOKEN / SECRET_REFRESH_TOKEN */
+
+/* * PASETO pre-authentication encoding, LE64(count) || LE64(len) || piece ... */
+fn pae(pieces: &[&[u8]]) -> Vec<u8> {
+    let mut output = (pieces.len() as u64).to_le_bytes().to_vec();
+    for piece in pieces {
+        output.extend_from_slice(&(piece.len() as u64).to_le_bytes());
+        output.extend_from_slice(piece);
+    }
+    output
+}
+/* * end PASETO pre-authentication encoding, LE64(count) || LE64(len) || piece ... */
+
+/* * PASETO registered claims iat & exp are ISO 8601 strings, jwt uses unix timestamps */
+fn claims_to_paseto_payload(claims: &Claims) -> Result<Vec<u8>, TokenError> {
+    let mut payload = serde_json::to_value(claims).map_err(|e| TokenError::Invalid(e.to_string()))?;
+    for (name, timestamp) in [("iat", claims.iat), ("exp", claims.exp)] {
+        let datetime = DateTime::from_timestamp(timestamp, 0)
+            .ok_or_else(|| TokenError::Invalid(format!("claim {} is out of range.", name)))?;
+        payload[name] = Value::String(datetime.to_rfc3339_opts(SecondsFormat::Secs, true));
+    }
+
+    serde_json::to_vec(&payload).map_err(|e| TokenError::Invalid(e.to_string()))
+}
+
+fn claims_from_paseto_payload(payload: &[u8]) -> Result<Claims, TokenError> {
+    let mut payload = serde_json::from_slice::<Value>(payload).map_err(|e| TokenError::Invalid(e.to_string()))?;
+    for name in ["iat", "exp"] {
+        let timestamp = payload[name]
+            .as_str()
+            .and_then(|datetime| DateTime::parse_from_rfc3339(datetime).ok())
+            .ok_or_else(|| TokenError::Invalid(format!("claim {} must be an ISO 8601 datetime.", name)))?
+            .timestamp();
+        payload[name] = Value::from(timestamp);
+    }
+    let claims = serde_json::from_value::<Claims>(payload).map_err(|e| TokenError::Invalid(e.to_string()))?;
+
+    if chrono::Utc::now().timestamp() >= claims.exp {
+        return Err(TokenError::Expired);
+    }
+    Ok(claims)
+}
+/* * end PASETO registered claims iat & exp are ISO 8601 strings, jwt uses unix timestamps */
+
+/* * split "v4.<purpose>.<payload>[.<footer>]", footer must equal the expected footer */
+fn constant_time_eq(left: &[u8], right: &[u8]) -> bool {
+    left.len() == right.len() && left.iter().zip(right).fold(0u8, |diff, (l, r)| diff | (l ^ r)) == 0
+}
+
+fn split_paseto(header: &str, token: &str, expected_footer: &[u8]) -> Result<Vec<u8>, TokenError> {
+    let body = token
+        .strip_prefix(header)
+        .ok_or_else(|| TokenError::Invalid(format!("token must start with '{}'.", header)))?;
+    let (payload, footer) = match body.split_once('.') {
+        Some((payload, footer)) => (payload, footer),
+        None => (body, "")
+    };
+    let payload = URL_SAFE_NO_PAD.decode(payload).map_err(|_| TokenError::Invalid(String::from("token payload is malformed.")))?;
+    let footer = URL_SAFE_NO_PAD.decode(footer).map_err(|_| TokenError::Invalid(String::from("token footer is malformed.")))?;
+    if !constant_time_eq(&footer, expected_footer) {
+        return Err(TokenError::Invalid(String::from("token footer does not match.")));
+    }
+
+    Ok(payload)
+}
+
+fn join_paseto(header: &str, payload: &[u8], footer: &[u8]) -> String {
+    match footer.is_empty() {
+        true => format!("{}{}", header, URL_SAFE_NO_PAD.encode(payload)),
+        false => format!("{}{}.{}", header, URL_SAFE_NO_PAD.encode(payload), URL_SAFE_NO_PAD.encode(footer))
+    }
+}
+/* * end split "v4.<purpose>.<payload>[.<footer>]", footer must equal the expected footer */
+
+/* * derive 32 byte key for a PASETO purpose from configured secret, HMAC-SHA256 keyed with the secret */
+fn derive_key(secret: &str, label: &str) -> [u8; 32] {
+    let mut mac = <Hmac<Sha256> as KeyInit>::new_from_slice(secret.as_bytes()).expect("hmac accepts keys of any length");
+    mac.update(label.as_bytes());
+    mac.finalize().into_bytes().into()
+}
+
+fn keyed_blake2b<M: Mac + KeyInit>(key: &[u8], pieces: &[&[u8]]) -> Vec<u8> {
+    let mut mac = <M as KeyInit>::new_from_slice(key).expect("blake2b accepts 32 byte keys");
+    pieces.iter().for_each(|piece| mac.update(piece));
+    mac.finalize().into_bytes().to_vec()
+}
+/* * end derive 32 byte key for a PASETO purpose from configured secret, HMAC-SHA256 keyed with the secret */
+
+/* * PASETO v4.local, XChaCha20 + BLAKE2b-MAC encrypted claims */
+const PASETO_V4_LOCAL: &str = "v4.local.";
+
+/* * * encryption key & counter nonce, authentication key */
+fn v4_local_split_keys(key: &[u8; 32], nonce: &[u8]) -> (Vec<u8>, Vec<u8>, Vec<u8>) {
+    let tmp = keyed_blake2b::<Blake2bMac<U56>>(key, &[b"paseto-encryption-key", nonce]);
+    let authentication_key = keyed_blake2b::<Blake2bMac<U32>>(key, &[b"paseto-auth-key-for-aead", nonce]);
+
+    (tmp[..32].to_vec(), tmp[32..].to_vec(), authentication_key)
+}
+
+fn v4_local_encrypt(key: &[u8; 32], message: &[u8], footer: &[u8], implicit_assertion: &[u8], nonce: &[u8; 32]) -> String {
+    let (encryption_key, counter_nonce, authentication_key) = v4_local_split_keys(key, nonce);
+
+    let mut ciphertext = message.to_vec();
+    XChaCha20::new(encryption_key.as_slice().into(), counter_nonce.as_slice().into()).apply_keystream(&mut ciphertext);
+    let pre_auth = pae(&[PASETO_V4_LOCAL.as_bytes(), nonce, &ciphertext, footer, implicit_assertion]);
+    let tag = keyed_blake2b::<Blake2bMac<U32>>(&authentication_key, &[&pre_auth]);
+
+    join_paseto(PASETO_V4_LOCAL, &[nonce.as_slice(), &ciphertext, &tag].concat(), footer)
+}
+
+fn v4_local_decrypt(key: &[u8; 32], token: &str, footer: &[u8], implicit_assertion: &[u8]) -> Result<Vec<u8>, TokenError> {
+    let payload = split_paseto(PASETO_V4_LOCAL, token, footer)?;
+    if payload.len() < 64 {
+        return Err(TokenError::Invalid(String::from("token payload is too short.")));
+    }
+    let (nonce, rest) = payload.split_at(32);
+    let (ciphertext, tag) = rest.split_at(rest.len() - 32);
+    let (encryption_key, counter_nonce, authentication_key) = v4_local_split_keys(key, nonce);
+
+    /* * * authenticate before decrypting, tag comparison is constant time */
+    let pre_auth = pae(&[PASETO_V4_LOCAL.as_bytes(), nonce, ciphertext, footer, implicit_assertion]);
+    let mut mac = <Blake2bMac<U32> as KeyInit>::new_from_slice(&authentication_key).expect("blake2b accepts 32 byte keys");
+    mac.update(&pre_auth);
+    mac.verify_slice(tag).map_err(|_| TokenError::Invalid(String::from("token authentication failed.")))?;
+    /* * * end authenticate before decrypting, tag comparison is constant time */
+
+    let mut plaintext = ciphertext.to_vec();
+    XChaCha20::new(encryption_key.as_slice().into(), counter_nonce.as_slice().into()).apply_keystream(&mut plaintext);
+
+    Ok(plaintext)
+}
+/* * * end encryption key & counter nonce, authentication key */
+
+pub struct PasetoLocalFormat {
+    access_key: [u8; 32],
+    refresh_key: [u8; 32]
+}
+
+impl fmt::Debug for PasetoLocalFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PasetoLocalFormat").finish_non_exhaustive()
+    }
+}
+
+impl PasetoLocalFormat {
+    fn key(&self, kind: TokenKind) -> &[u8; 32] {
+        match kind {
+            TokenKind::Access => &self.access_key,
+            TokenKind::Refresh => &self.refresh_key
+        }
+    }
+}
+
+/* * * tokens carry no footer & no implicit assertion, a token with a footer is rejected */
+impl TokenFormat for PasetoLocalFormat {
+    fn encode(&self, kind: TokenKind, claims: &Claims) -> Result<String, TokenError> {
+        let mut nonce = [0u8; 32];
+        OsRng.fill_bytes(&mut nonce);
+
+        Ok(v4_local_encrypt(self.key(kind), &claims_to_paseto_payload(claims)?, b"", b"", &nonce))
+    }
+
+    fn decode(&self, kind: TokenKind, token: &str) -> Result<Claims, TokenError> {
+        claims_from_paseto_payload(&v4_local_decrypt(self.key(kind), token, b"", b"")?)
+    }
+}
+/* * end PASETO v4.local, XChaCha20 + BLAKE2b-MAC encrypted claims */
+
+/* * PASETO v4.public, Ed25519 signed claims */
+const PASETO_V4_PUBLIC: &str = "v4.public.";
+const PASERK_K4_PUBLIC: &str = "k4.public.";
+const PASERK_K4_SECRET: &str = "k4.secret.";
+
+fn v4_public_sign(key: &SigningKey, message: &[u8], footer: &[u8], implicit_assertion: &[u8]) -> String {
+    let pre_auth = pae(&[PASETO_V4_PUBLIC.as_bytes(), message, footer, implicit_assertion]);
+    let signature = key.sign(&pre_auth);
+
+    join_paseto(PASETO_V4_PUBLIC, &[message, &signature.to_bytes()].concat(), footer)
+}
+
+fn v4_public_verify(key: &VerifyingKey, token: &str, footer: &[u8], implicit_assertion: &[u8]) -> Result<Vec<u8>, TokenError> {
+    let payload = split_paseto(PASETO_V4_PUBLIC, token, footer)?;
+    if payload.len() < 64 {
+        return Err(TokenError::Invalid(String::from("token payload is too short.")));
+    }
+    let (message, signature) = payload.split_at(payload.len() - 64);
+    let signature = Signature::from_slice(signature).map_err(|_| TokenError::Invalid(String::from("token signature is malformed.")))?;
+
+    let pre_auth = pae(&[PASETO_V4_PUBLIC.as_bytes(), message, footer, implicit_assertion]);
+    key
+        .verify_strict(&pre_auth, &signature)
+        .map_err(|_| TokenError::Invalid(String::from("token signature verification failed.")))?;
+
+    Ok(message.to_vec())
+}
+
+pub struct PasetoPublicFormat {
+    access_key: SigningKey,
+    refresh_key: SigningKey
+}
+
+impl fmt::Debug for PasetoPublicFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PasetoPublicFormat")
+            .field("access_public_key", &URL_SAFE_NO_PAD.encode(self.access_key.verifying_key().as_bytes()))
+            .finish_non_exhaustive()
+    }
+}
+
+impl PasetoPublicFormat {
+    fn key(&self, kind: TokenKind) -> &SigningKey {
+        match kind {
+            TokenKind::Access => &self.access_key,
+            TokenKind::Refresh => &self.refresh_key
+        }
+    }
+}
+
+/* * * tokens carry no footer & no implicit assertion, a token with a footer is rejected */
+impl TokenFormat for PasetoPublicFormat {
+    fn encode(&self, kind: TokenKind, claims: &Claims) -> Result<String, TokenError> {
+        Ok(v4_public_sign(self.key(kind), &claims_to_paseto_payload(claims)?, b"", b""))
+    }
+
+    fn decode(&self, kind: TokenKind, token: &str) -> Result<Claims, TokenError> {
+        claims_from_paseto_payload(&v4_public_verify(&self.key(kind).verifying_key(), token, b"", b"")?)
+    }
+
+    fn public_key(&self) -> Option<String> {
+        Some(format!("{}{}", PASERK_K4_PUBLIC, URL_SAFE_NO_PAD.encode(self.access_key.verifying_key().as_bytes())))
+    }
+}
+
+/* * Ed25519 secret key as base64url 32 byte seed or PASERK k4.secret (64 byte keypair) */
+fn parse_signing_key(name: &str, encoded: &str) -> Result<SigningKey, String> {
+    let (encoded, is_paserk) = match encoded.trim().strip_prefix(PASERK_K4_SECRET) {
+        Some(encoded) => (encoded, true),
+        None => (encoded.trim(), false)
+    };
+    let bytes = URL_SAFE_NO_PAD
+        .decode(encoded.trim_end_matches('='))
+        .map_err(|_| format!("{} must be base64url encoded.", name))?;
+
+    match (bytes.len(), is_paserk) {
+        (32, false) => Ok(SigningKey::from_bytes(bytes.as_slice().try_into().expect("length checked above"))),
+        (64, _) => SigningKey::from_keypair_bytes(bytes.as_slice().try_into().expect("length checked above"))
+            .map_err(|_| format!("{} public half does not match its secret seed.", name)),
+        _ => Err(format!("{} must be a 32 byte Ed25519 seed or a 64 byte k4.secret keypair.", name))
+    }
+}
+/* * end Ed25519 secret key as base64url 32 byte seed or PASERK k4.secret (64 byte keypair) */
+/* * end PASETO v4.public, Ed25519 signed claims */
+
+/* * select token format from config ("jwt", "v4.local" or "v4.public"), v4.public signs with configured Ed25519 keys */
+pub fn build_token_format(
+    token_format: &str,
+    secret_access_token: &str,
+    secret_refresh_token: &str,
+    paseto_access_secret_key: Option<&str>,
+    paseto_refresh_secret_key: Option<&str>
+) -> Result<Arc<dyn TokenFormat>, String>
+{
+    match token_format {
+        "jwt" => Ok(Arc::new(JwtFormat {
+            secret_access_token: secret_access_token.to_string(),
+            secret_refresh_token: secret_refresh_token.to_string()
+        })),
+        "v4.local" => Ok(Arc::new(PasetoLocalFormat {
+            access_key: derive_key(secret_access_token, "paseto-v4-local"),
+            refresh_key: derive_key(secret_refresh_token, "paseto-v4-local")
+        })),
+        "v4.public" => match (paseto_access_secret_key, paseto_refresh_secret_key) {
+            (Some(access_key), Some(refresh_key)) => {
+                let access_key = parse_signing_key("PASETO_ACCESS_SECRET_KEY", access_key)?;
+                let refresh_key = parse_signing_key("PASETO_REFRESH_SECRET_KEY", refresh_key)?;
+                if access_key.verifying_key() == refresh_key.verifying_key() {
+                    return Err(String::from("PASETO_ACCESS_SECRET_KEY and PASETO_REFRESH_SECRET_KEY must be different keys."));
+                }
+                Ok(Arc::new(PasetoPublicFormat { access_key, refresh_key }))
+            },
+            _ => Err(String::from("v4.public requires PASETO_ACCESS_SECRET_KEY and PASETO_REFRESH_SECRET_KEY."))
+        },
+        other => Err(format!("unknown token format '{}'. supported formats: jwt, v4.local, v4.public", other))
+    }
+}
+/* * end select token format from config ("jwt", "v4.local" or "v4.public"), v4.public signs with configured Ed25519 keys */
+
+/* * official PASETO v4 test vectors, https://github.com/paseto-standard/test-vectors/blob/master/v4.json */
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LOCAL_KEY: &str = "707172737475767778797a7b7c7d7e7f808182838485868788898a8b8c8d8e8f";
+    const SECRET_KEY: &str = "b4cbfb43df4ce210727d953e4a713307fa19bb7d9f85041438d9e11b942a37741eb9dbbbbc047c03fd70604e0071f0987e16b28b757225c11f00415d0e20b1a2";
+    const PUBLIC_KEY: &str = "1eb9dbbbbc047c03fd70604e0071f0987e16b28b757225c11f00415d0e20b1a2";
+    const FOOTER: &str = r#"{"kid":"zVhMiPBP9fRf2snEcT7gFTioeA9COcNy9DfgL1W60haN"}"#;
+
+    fn hex(value: &str) -> Vec<u8> {
+        (0..value.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&value[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    fn local_key() -> [u8; 32] {
+        hex(LOCAL_KEY).try_into().unwrap()
+    }
+
+    fn signing_key() -> SigningKey {
+        SigningKey::from_keypair_bytes(&hex(SECRET_KEY).try_into().unwrap()).unwrap()
+    }
+
+    const MESSAGE_SECRET: &str = r#"{"data":"this is a secret message","exp":"2022-01-01T00:00:00+00:00"}"#;
+    const MESSAGE_HIDDEN: &str = r#"{"data":"this is a hidden message","exp":"2022-01-01T00:00:00+00:00"}"#;
+    const MESSAGE_SIGNED: &str = r#"{"data":"this is a signed message","exp":"2022-01-01T00:00:00+00:00"}"#;
+    const ZERO_NONCE: [u8; 32] = [0u8; 32];
+
+    /* * 4-E-1 & 4-E-2 */
+    const LOCAL_VECTORS: [(&str, &str); 2] = [
+        (MESSAGE_SECRET, "v4.local.AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAQAr68PS4AXe7If_ZgesdkUMvSwscFlAl1pk5HC0e8kApeaqMfGo_7OpBnwJOAbY9V7WU6abu74MmcUE8YWAiaArVI8XJ5hOb_4v9RmDkneN0S92dx0OW4pgy7omxgf3S8c3LlQg"),
+        (MESSAGE_HIDDEN, "v4.local.AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAQAr68PS4AXe7If_ZgesdkUMvS2csCgglvpk5HC0e8kApeaqMfGo_7OpBnwJOAbY9V7WU6abu74MmcUE8YWAiaArVI8XIemu9chy3WVKvRBfg6t8wwYHK0ArLxxfZP73W_vfwt5A")
+    ];
+
+    /* * 4-S-1, 4-S-2 & 4-S-3 as (footer, implicit assertion, token) */
+    const PUBLIC_VECTORS: [(&str, &str, &str); 3] = [
+        ("", "", "v4.public.eyJkYXRhIjoidGhpcyBpcyBhIHNpZ25lZCBtZXNzYWdlIiwiZXhwIjoiMjAyMi0wMS0wMVQwMDowMDowMCswMDowMCJ9bg_XBBzds8lTZShVlwwKSgeKpLT3yukTw6JUz3W4h_ExsQV-P0V54zemZDcAxFaSeef1QlXEFtkqxT1ciiQEDA"),
+        (FOOTER, "", "v4.public.eyJkYXRhIjoidGhpcyBpcyBhIHNpZ25lZCBtZXNzYWdlIiwiZXhwIjoiMjAyMi0wMS0wMVQwMDowMDowMCswMDowMCJ9v3Jt8mx_TdM2ceTGoqwrh4yDFn0XsHvvV_D0DtwQxVrJEBMl0F2caAdgnpKlt4p7xBnx1HcO-SPo8FPp214HDw.eyJraWQiOiJ6VmhNaVBCUDlmUmYyc25FY1Q3Z0ZUaW9lQTlDT2NOeTlEZmdMMVc2MGhhTiJ9"),
+        (FOOTER, r#"{"test-vector":"4-S-3"}"#, "v4.public.eyJkYXRhIjoidGhpcyBpcyBhIHNpZ25lZCBtZXNzYWdlIiwiZXhwIjoiMjAyMi0wMS0wMVQwMDowMDowMCswMDowMCJ9NPWciuD3d0o5eXJXG5pJy-DiVEoyPYWs1YSTwWHNJq6DZD3je5gf-0M4JR9ipdUSJbIovzmBECeaWmaqcaP0DQ.eyJraWQiOiJ6VmhNaVBCUDlmUmYyc25FY1Q3Z0ZUaW9lQTlDT2NOeTlEZmdMMVc2MGhhTiJ9")
+    ];
+
+    #[test]
+    fn v4_local_matches_test_vectors() {
+        for (message, token) in LOCAL_VECTORS {
+            assert_eq!(v4_local_encrypt(&local_key(), message.as_bytes(), b"", b"", &ZERO_NONCE), token);
+            assert_eq!(v4_local_decrypt(&local_key(), token, b"", b"").unwrap(), message.as_bytes());
+        }
+    }
+
+    #[test]
+    fn v4_local_binds_footer_and_implicit_assertion() {
+        let token = v4_local_encrypt(&local_key(), MESSAGE_SECRET.as_bytes(), FOOTER.as_bytes(), b"assertion", &ZERO_NONCE);
+
+        assert_eq!(v4_local_decrypt(&local_key(), &token, FOOTER.as_bytes(), b"assertion").unwrap(), MESSAGE_SECRET.as_bytes());
+        assert!(v4_local_decrypt(&local_key(), &token, b"", b"assertion").is_err());
+        assert!(v4_local_decrypt(&local_key(), &token, b"{\"kid\":\"other\"}", b"assertion").is_err());
+        assert!(v4_local_decrypt(&local_key(), &token, FOOTER.as_bytes(), b"").is_err());
+        assert!(v4_local_decrypt(&[0u8; 32], &token, FOOTER.as_bytes(), b"assertion").is_err());
+    }
+
+    #[test]
+    fn v4_public_matches_test_vectors() {
+        assert_eq!(signing_key().verifying_key().as_bytes().to_vec(), hex(PUBLIC_KEY));
+        for (footer, implicit_assertion, token) in PUBLIC_VECTORS {
+            assert_eq!(v4_public_sign(&signing_key(), MESSAGE_SIGNED.as_bytes(), footer.as_bytes(), implicit_assertion.as_bytes()), token);
+            let message = v4_public_verify(&signing_key().verifying_key(), token, footer.as_bytes(), implicit_assertion.as_bytes()).unwrap();
+            assert_eq!(message, MESSAGE_SIGNED.as_bytes());
+        }
+    }
+
+    #[test]
+    fn v4_public_rejects_wrong_footer_and_implicit_assertion() {
+        let (footer, implicit_assertion, token) = PUBLIC_VECTORS[2];
+        let verifying_key = signing_key().verifying_key();
+
+        assert!(v4_public_verify(&verifying_key, token, b"", implicit_assertion.as_bytes()).is_err());
+        assert!(v4_public_verify(&verifying_key, token, footer.as_bytes(), b"").is_err());
+        assert!(v4_public_verify(&verifying_key, PUBLIC_VECTORS[0].2, footer.as_bytes(), b"").is_err());
+    }
+
+    #[test]
+    fn token_formats_reject_tokens_with_footer() {
+        let format = PasetoPublicFormat { access_key: signing_key(), refresh_key: SigningKey::from_bytes(&[7u8; 32]) };
+        let token = PUBLIC_VECTORS[1].2;
+
+        assert!(matches!(format.decode(TokenKind::Access, token), Err(TokenError::Invalid(_))));
+    }
+
+    #[test]
+    fn parse_signing_key_accepts_seed_and_paserk_secret() {
+        let keypair = hex(SECRET_KEY);
+        let seed = URL_SAFE_NO_PAD.encode(&keypair[..32]);
+        let paserk = format!("{}{}", PASERK_K4_SECRET, URL_SAFE_NO_PAD.encode(&keypair));
+
+        assert_eq!(parse_signing_key("KEY", &seed).unwrap().to_bytes(), signing_key().to_bytes());
+        assert_eq!(parse_signing_key("KEY", &paserk).unwrap().to_bytes(), signing_key().to_bytes());
+        assert!(parse_signing_key("KEY", &URL_SAFE_NO_PAD.encode([1u8; 16])).is_err());
+    }
+}
+/* * end official PASETO v4 test vectors, https://github.com/paseto-standard/test-vectors/blob/master/v4.json */
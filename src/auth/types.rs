@@ -8,28 +8,218 @@ use actix_web::cookie::time::Duration as ActixDuration;
 pub struct Claims {
     pub username: String,
     pub iat: i64,
-    pub exp: i64 
+    pub exp: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jti: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub role: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cnf: Option<Confirmation>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub act: Option<Actor>
+}
+
+/* * RFC 8693 actor claim, names the admin acting on behalf of the token subject */
+#[derive(Serialize, Debug, Deserialize, Clone)]
+pub struct Actor {
+    pub sub: String
+}
+
+/* * proof-of-possession confirmation claim, token is only usable with the bound key */
+#[derive(Serialize, Debug, Deserialize, Clone, Default)]
+pub struct Confirmation {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jkt: Option<String>,
+    #[serde(rename = "x5t#S256", default, skip_serializing_if = "Option::is_none")]
+    pub x5t_s256: Option<String>
+}
+
+impl Confirmation {
+    pub fn from_jkt(jkt: Option<String>) -> Option<Self> {
+        jkt.map(|jkt| Self { jkt: Some(jkt), ..Default::default() })
+    }
+
+    pub fn from_x5t_s256(x5t_s256: String) -> Self {
+        Self { x5t_s256: Some(x5t_s256), ..Default::default() }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ClientInfo {
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>
+}
+
+#[derive(Serialize, Clone, Copy, Debug)]
+pub struct SessionExpiry {
+    pub idle_expires_at: Option<i64>,
+    pub session_expires_at: i64
+}
+
+impl SessionExpiry {
+    pub fn is_expired(&self, time_now: i64) -> bool {
+        time_now >= self.session_expires_at
+            || self.idle_expires_at.is_some_and(|idle_expires_at| time_now >= idle_expires_at)
+    }
+}
+
+/* * browser_mode: access_token is delivered as HttpOnly cookie instead of response body */
+/* * dpop_jkt: issued tokens are bound to the DPoP proof key thumbprint */
+#[derive(Clone, Debug, Default)]
+pub struct SigninOptions {
+    pub remember_me: bool,
+    pub browser_mode: bool,
+    pub dpop_jkt: Option<String>,
+    pub scope: Option<String>
 }
 
 pub struct ServiceOkSignin {
     pub username: String,
     pub encoded_access_token: String,
-    pub encoded_refresh_token: String
+    pub access_token_max_age: ActixDuration,
+    pub encoded_refresh_token: String,
+    pub refresh_token_max_age: ActixDuration,
+    pub browser_mode: bool,
+    pub token_type: &'static str,
+    pub scope: String,
+    pub session_expiry: SessionExpiry
 }
 
 #[derive(Serialize)]
 pub struct ResponseSignin {
-    pub access_token: String
+    pub token_type: &'static str,
+    pub scope: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub access_token: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub refresh_token: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub csrf_token: Option<String>,
+    #[serde(flatten)]
+    pub session_expiry: SessionExpiry
 }
 
 pub struct ServiceOkRefreshToken {
+    pub username: String,
     pub access_token: String,
+    pub access_token_max_age: ActixDuration,
     pub refresh_token: String,
-    pub refresh_token_previous_max_age: ActixDuration
+    pub refresh_token_previous_max_age: ActixDuration,
+    pub browser_mode: bool,
+    pub token_type: &'static str,
+    pub scope: String,
+    pub session_expiry: SessionExpiry
 }
 
 #[derive(Serialize)]
 pub struct ResponseRefreshToken {
+    pub token_type: &'static str,
+    pub scope: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub access_token: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub refresh_token: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub csrf_token: Option<String>,
+    #[serde(flatten)]
+    pub session_expiry: SessionExpiry
+}
+
+#[derive(Serialize)]
+pub struct PasskeyRelyingParty {
+    pub id: String,
+    pub name: String
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PasskeyUserEntity {
+    pub id: String,
+    pub name: String,
+    pub display_name: String
+}
+
+#[derive(Serialize)]
+pub struct PasskeyCredentialParameter {
+    #[serde(rename = "type")]
+    pub credential_type: &'static str,
+    pub alg: i64
+}
+
+#[derive(Serialize)]
+pub struct PasskeyCredentialDescriptor {
+    #[serde(rename = "type")]
+    pub credential_type: &'static str,
+    pub id: String
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PasskeyAuthenticatorSelection {
+    pub resident_key: &'static str,
+    pub user_verification: &'static str
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResponsePasskeyCreationOptions {
+    pub challenge: String,
+    pub rp: PasskeyRelyingParty,
+    pub user: PasskeyUserEntity,
+    pub pub_key_cred_params: Vec<PasskeyCredentialParameter>,
+    pub timeout: i64,
+    pub attestation: &'static str,
+    pub exclude_credentials: Vec<PasskeyCredentialDescriptor>,
+    pub authenticator_selection: PasskeyAuthenticatorSelection
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResponsePasskeyRequestOptions {
+    pub challenge: String,
+    pub rp_id: String,
+    pub timeout: i64,
+    pub user_verification: &'static str,
+    pub allow_credentials: Vec<PasskeyCredentialDescriptor>
+}
+
+pub struct ServiceOkDisavow {
+    pub username: String,
+    pub reset_link_sent: bool
+}
+
+pub struct ServiceOkImpersonate {
+    pub username: String,
+    pub scope: String,
+    pub impersonated_by: String,
+    pub access_token: String,
+    pub expires_in: i64
+}
+
+#[derive(Serialize)]
+pub struct ResponseImpersonate {
+    pub token_type: &'static str,
+    pub scope: String,
+    pub access_token: String,
+    pub expires_in: i64,
+    pub username: String,
+    pub impersonated_by: String
+}
+
+pub struct ServiceOkMtlsToken {
+    pub principal: String,
+    pub scope: String,
+    pub access_token: String,
+    pub expires_in: i64
+}
+
+#[derive(Serialize)]
+pub struct ResponseMtlsToken {
+    pub token_type: &'static str,
+    pub scope: String,
     pub access_token: String,
-    pub refresh_token: String
+    pub expires_in: i64,
+    pub principal: String
 }
\ No newline at end of file
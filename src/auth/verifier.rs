@@ -0,0 +1,397 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    fs,
+    sync::Arc,
+    time::Duration
+};
+use actix_web::http::StatusCode;
+use async_trait::async_trait;
+use chrono::Utc;
+use ldap3::{
+    LdapConnAsync,
+    LdapConnSettings,
+    dn_escape
+};
+
+use crate::{
+    db::DbPool,
+    errors::{
+        AppError,
+        AppErrorMessage
+    },
+    auth::helpers::{
+        get_user_credentials,
+        verify_password,
+        generate_secret_token
+    },
+    user::{
+        hashing_password,
+        record_user_changes,
+        UserChange
+    }
+};
+
+/* * LDAP result code for wrong dn or password */
+const LDAP_INVALID_CREDENTIALS: u32 = 49;
+
+/* * user verified by a backend, role is synced to database when backend provides one */
+#[derive(Clone, Debug)]
+pub struct VerifiedUser {
+    pub username: String,
+    pub role: Option<String>,
+    pub auth_source: &'static str
+}
+
+#[derive(Debug)]
+pub enum VerifyOutcome {
+    Verified(VerifiedUser),
+    InvalidPassword,
+    UnknownUser
+}
+
+/* * pluggable credential verifier */
+#[async_trait]
+pub trait CredentialVerifier: Send + Sync + fmt::Debug {
+    fn name(&self) -> &'static str;
+    async fn verify(&self, username: &str, password: &str) -> Result<VerifyOutcome, AppError>;
+}
+/* * end pluggable credential verifier */
+
+/* * build verifier AppError */
+fn verifier_error(message: String) -> AppError {
+    log::error!("{}", message);
+    let app_err_message: AppErrorMessage<Option<bool>> = AppErrorMessage {
+        code: StatusCode::INTERNAL_SERVER_ERROR.as_u16(),
+        message,
+        details: None
+    };
+    AppError::InternalServerError(app_err_message.into())
+}
+/* * end build verifier AppError */
+
+/* * database verifier, argon2 hash stored in credentials table */
+#[derive(Debug)]
+pub struct DbVerifier {
+    db_pool: DbPool
+}
+
+#[async_trait]
+impl CredentialVerifier for DbVerifier {
+    fn name(&self) -> &'static str {
+        "db"
+    }
+
+    async fn verify(&self, username: &str, password: &str) -> Result<VerifyOutcome, AppError> {
+        let user_credentials = match get_user_credentials(&self.db_pool, username).await {
+            Ok(user_credentials) => user_credentials,
+            Err(sqlx::Error::RowNotFound) => return Ok(VerifyOutcome::UnknownUser),
+            Err(e) => return Err(verifier_error(format!("failed to get credentials of user '{}': {}", username, e)))
+        };
+
+        match verify_password(password, &user_credentials.password)? {
+            true => Ok(VerifyOutcome::Verified(VerifiedUser { username: username.to_string(), role: None, auth_source: self.name() })),
+            false => Ok(VerifyOutcome::InvalidPassword)
+        }
+    }
+}
+/* * end database verifier, argon2 hash stored in credentials table */
+
+/* * ldap verifier, binds as the user with a dn built from template, e.g. "uid={username},ou=people,dc=example,dc=org" */
+#[derive(Debug)]
+pub struct LdapVerifier {
+    url: String,
+    bind_dn_template: String,
+    starttls: bool
+}
+
+impl LdapVerifier {
+    pub fn new(url: &str, bind_dn_template: &str, starttls: bool) -> Result<Self, String> {
+        if !bind_dn_template.contains("{username}") {
+            return Err(String::from("LDAP_BIND_DN_TEMPLATE must contain '{username}'."));
+        }
+
+        Ok(Self { url: url.to_string(), bind_dn_template: bind_dn_template.to_string(), starttls })
+    }
+}
+
+#[async_trait]
+impl CredentialVerifier for LdapVerifier {
+    fn name(&self) -> &'static str {
+        "ldap"
+    }
+
+    async fn verify(&self, username: &str, password: &str) -> Result<VerifyOutcome, AppError> {
+        /* * * empty password is an unauthenticated bind, which ldap servers accept */
+        if password.is_empty() {
+            return Ok(VerifyOutcome::InvalidPassword);
+        }
+        /* * * end empty password is an unauthenticated bind, which ldap servers accept */
+
+        let settings = LdapConnSettings::new()
+            .set_conn_timeout(Duration::from_secs(5))
+            .set_starttls(self.starttls);
+        let (conn, mut ldap) = LdapConnAsync::with_settings(settings, &self.url)
+            .await
+            .map_err(|e| verifier_error(format!("failed to connect to ldap server '{}': {}", self.url, e)))?;
+        ldap3::drive!(conn);
+
+        let bind_dn = self.bind_dn_template.replace("{username}", &dn_escape(username));
+        let bind_result = ldap
+            .simple_bind(&bind_dn, password)
+            .await
+            .map_err(|e| verifier_error(format!("failed to bind '{}' to ldap server: {}", bind_dn, e)))?;
+        if let Err(e) = ldap.unbind().await {
+            log::error!("failed to unbind '{}' from ldap server: {}", bind_dn, e);
+        }
+
+        /* * * ldap does not tell unknown dn from wrong password */
+        match bind_result.rc {
+            0 => Ok(VerifyOutcome::Verified(VerifiedUser { username: username.to_string(), role: None, auth_source: self.name() })),
+            LDAP_INVALID_CREDENTIALS => Ok(VerifyOutcome::InvalidPassword),
+            _ => Err(verifier_error(format!("ldap bind of '{}' failed: {}", bind_dn, bind_result)))
+        }
+        /* * * end ldap does not tell unknown dn from wrong password */
+    }
+}
+/* * end ldap verifier, binds as the user with a dn built from template, e.g. "uid={username},ou=people,dc=example,dc=org" */
+
+/* * htpasswd file verifier for bootstrap users, "username:hash" per line, argon2 or bcrypt hashes */
+pub struct HtpasswdVerifier {
+    users: HashMap<String, String>,
+    role: String
+}
+
+impl fmt::Debug for HtpasswdVerifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HtpasswdVerifier")
+            .field("users", &self.users.keys().collect::<Vec<_>>())
+            .field("role", &self.role)
+            .finish()
+    }
+}
+
+impl HtpasswdVerifier {
+    pub fn new(path: &str, role: &str) -> Result<Self, String> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("failed to read htpasswd file '{}': {}", path, e))?;
+
+        let mut users = HashMap::new();
+        for (index, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (username, hash) = line
+                .split_once(':')
+                .ok_or_else(|| format!("invalid htpasswd line {} in '{}', expected 'username:hash'.", index + 1, path))?;
+            if !hash.starts_with("$argon2") && !hash.starts_with("$2") {
+                return Err(format!("unsupported hash for user '{}' in '{}'. supported hashes: argon2, bcrypt", username, path));
+            }
+            users.insert(username.to_string(), hash.to_string());
+        }
+
+        Ok(Self { users, role: role.to_string() })
+    }
+}
+
+#[async_trait]
+impl CredentialVerifier for HtpasswdVerifier {
+    fn name(&self) -> &'static str {
+        "htpasswd"
+    }
+
+    async fn verify(&self, username: &str, password: &str) -> Result<VerifyOutcome, AppError> {
+        let Some(hash) = self.users.get(username) else {
+            return Ok(VerifyOutcome::UnknownUser);
+        };
+
+        let is_verified_password = match hash.starts_with("$argon2") {
+            true => verify_password(password, hash)?,
+            false => bcrypt::verify(password, hash)
+                .map_err(|e| verifier_error(format!("failed to verify bcrypt hash of user '{}': {}", username, e)))?
+        };
+
+        match is_verified_password {
+            true => Ok(VerifyOutcome::Verified(VerifiedUser { username: username.to_string(), role: Some(self.role.clone()), auth_source: self.name() })),
+            false => Ok(VerifyOutcome::InvalidPassword)
+        }
+    }
+}
+/* * end htpasswd file verifier for bootstrap users, "username:hash" per line, argon2 or bcrypt hashes */
+
+/* * credential backend owning the account, None when user is not stored yet */
+async fn get_user_auth_source(
+    db_pool: &DbPool,
+    username: &str
+) -> Result<Option<String>, AppError>
+{
+    sqlx::query_scalar::<_, String>("SELECT auth_source FROM user WHERE username = ?")
+        .bind(username)
+        .fetch_optional(db_pool)
+        .await
+        .map_err(|e| verifier_error(format!("failed to get auth source of user '{}': {}", username, e)))
+}
+/* * end credential backend owning the account, None when user is not stored yet */
+
+/* * build AppError for signing into an account owned by another backend */
+fn auth_source_mismatch_error(username: &str) -> AppError {
+    let app_err_message: AppErrorMessage<Option<bool>> = AppErrorMessage {
+        code: StatusCode::UNAUTHORIZED.as_u16(),
+        message: format!("Oops! Authentication failed. the account '{}' is managed by another credential backend.", username),
+        details: None
+    };
+    AppError::Unauthorized(app_err_message.into())
+}
+/* * end build AppError for signing into an account owned by another backend */
+
+/* * chain of verifiers, existing accounts are verified only by their owning backend, new ones by the first backend that verifies them */
+#[derive(Debug)]
+pub struct ChainVerifier {
+    db_pool: DbPool,
+    verifiers: Vec<Box<dyn CredentialVerifier>>
+}
+
+#[async_trait]
+impl CredentialVerifier for ChainVerifier {
+    fn name(&self) -> &'static str {
+        "chain"
+    }
+
+    async fn verify(&self, username: &str, password: &str) -> Result<VerifyOutcome, AppError> {
+        let mut outcome = VerifyOutcome::UnknownUser;
+        let mut last_error = None;
+
+        /* * * a wrong password on the owning backend must not fall through to another backend */
+        let auth_source = get_user_auth_source(&self.db_pool, username).await?;
+        let verifiers = self.verifiers
+            .iter()
+            .filter(|verifier| auth_source.as_deref().is_none_or(|auth_source| verifier.name() == auth_source));
+        /* * * end a wrong password on the owning backend must not fall through to another backend */
+
+        for verifier in verifiers {
+            /* * * an unavailable backend must not lock users out of the others */
+            match verifier.verify(username, password).await {
+                Ok(VerifyOutcome::Verified(verified_user)) => return Ok(VerifyOutcome::Verified(verified_user)),
+                Ok(VerifyOutcome::InvalidPassword) => outcome = VerifyOutcome::InvalidPassword,
+                Ok(VerifyOutcome::UnknownUser) => (),
+                Err(e) => {
+                    log::error!("credential verifier '{}' failed for user '{}': {}", verifier.name(), username, e);
+                    last_error = Some(e);
+                }
+            }
+            /* * * end an unavailable backend must not lock users out of the others */
+        }
+
+        match (outcome, last_error) {
+            (VerifyOutcome::UnknownUser, Some(e)) => Err(e),
+            (outcome, _) => Ok(outcome)
+        }
+    }
+}
+/* * end chain of verifiers, existing accounts are verified only by their owning backend, new ones by the first backend that verifies them */
+
+/* * users verified outside database get a user row owned by that backend, password is random so db verifier never accepts it */
+pub async fn provision_verified_user(
+    db_pool: &DbPool,
+    verified_user: &VerifiedUser
+) -> Result<(), AppError>
+{
+    let database_error = |e: sqlx::Error| verifier_error(format!("failed to provision user '{}': {}", verified_user.username, e));
+
+    /* * * never provision into or sign into an account owned by another backend */
+    let auth_source = get_user_auth_source(db_pool, &verified_user.username).await?;
+    if auth_source.as_deref().is_some_and(|auth_source| auth_source != verified_user.auth_source) {
+        return Err(auth_source_mismatch_error(&verified_user.username));
+    }
+    /* * * end never provision into or sign into an account owned by another backend */
+
+    if auth_source.is_none() {
+        let hashed_password = hashing_password(&generate_secret_token())?;
+        let mut transaction = db_pool.begin().await.map_err(database_error)?;
+        let time_now = Utc::now().timestamp();
+        sqlx::query("INSERT INTO user (username, password, auth_source, created_at, updated_at) VALUES (?, ?, ?, ?, ?)")
+            .bind(&verified_user.username)
+            .bind(&hashed_password)
+            .bind(verified_user.auth_source)
+            .bind(time_now)
+            .bind(time_now)
+            .execute(&mut *transaction)
+            .await
+            .map_err(database_error)?;
+        sqlx::query("INSERT INTO credentials (username, password, created_at, updated_at) VALUES (?, ?, ?, ?)")
+            .bind(&verified_user.username)
+            .bind(&hashed_password)
+            .bind(time_now)
+            .bind(time_now)
+            .execute(&mut *transaction)
+            .await
+            .map_err(database_error)?;
+        transaction.commit().await.map_err(database_error)?;
+    }
+
+    /* * * role is only written & recorded in user history when backend changed it */
+    if let Some(role) = &verified_user.role {
+        let (id_user, stored_role) = sqlx::query_as::<_, (i32, String)>("SELECT id_user, role FROM user WHERE username = ?")
+            .bind(&verified_user.username)
+            .fetch_one(db_pool)
+            .await
+            .map_err(database_error)?;
+        if stored_role != *role {
+            let mut transaction = db_pool.begin().await.map_err(database_error)?;
+            sqlx::query("UPDATE user SET role = ?, updated_at = ?, version = version + 1 WHERE id_user = ?")
+                .bind(role)
+                .bind(Utc::now().timestamp())
+                .bind(id_user)
+                .execute(&mut *transaction)
+                .await
+                .map_err(database_error)?;
+            let changes = vec![UserChange { field: "role", old_value: Some(stored_role), new_value: Some(role.clone()) }];
+            record_user_changes(&mut *transaction, id_user as u32, None, changes)
+                .await
+                .map_err(database_error)?;
+            transaction.commit().await.map_err(database_error)?;
+        }
+    }
+    /* * * end role is only written & recorded in user history when backend changed it */
+
+    Ok(())
+}
+/* * end users verified outside database get a user row owned by that backend, password is random so db verifier never accepts it */
+
+/* * select credential verifiers from config, e.g. "db,ldap,htpasswd" */
+pub fn build_credential_verifier(
+    verifiers: &str,
+    db_pool: &DbPool,
+    ldap_url: Option<String>,
+    ldap_bind_dn_template: Option<String>,
+    ldap_starttls: bool,
+    htpasswd_path: Option<String>,
+    htpasswd_role: &str
+) -> Result<Arc<dyn CredentialVerifier>, String>
+{
+    let mut chain: Vec<Box<dyn CredentialVerifier>> = Vec::new();
+
+    for verifier in verifiers.split(',').map(str::trim).filter(|verifier| !verifier.is_empty()) {
+        match verifier {
+            "db" => chain.push(Box::new(DbVerifier { db_pool: db_pool.clone() })),
+            "ldap" => {
+                let ldap_url = ldap_url.as_deref().ok_or("LDAP_URL must be set when CREDENTIAL_VERIFIERS contains ldap.")?;
+                let ldap_bind_dn_template = ldap_bind_dn_template.as_deref().ok_or("LDAP_BIND_DN_TEMPLATE must be set when CREDENTIAL_VERIFIERS contains ldap.")?;
+                chain.push(Box::new(LdapVerifier::new(ldap_url, ldap_bind_dn_template, ldap_starttls)?));
+            },
+            "htpasswd" => {
+                let htpasswd_path = htpasswd_path.as_deref().ok_or("HTPASSWD_PATH must be set when CREDENTIAL_VERIFIERS contains htpasswd.")?;
+                chain.push(Box::new(HtpasswdVerifier::new(htpasswd_path, htpasswd_role)?));
+            },
+            other => return Err(format!("unknown credential verifier '{}'. supported verifiers: db, ldap, htpasswd", other))
+        }
+    }
+
+    match chain.len() {
+        0 => Err(String::from("CREDENTIAL_VERIFIERS must contain at least one verifier.")),
+        1 => Ok(Arc::from(chain.remove(0))),
+        _ => Ok(Arc::new(ChainVerifier { db_pool: db_pool.clone(), verifiers: chain }))
+    }
+}
+/* * end select credential verifiers from config, e.g. "db,ldap,htpasswd" */
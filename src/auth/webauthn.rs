@@ -0,0 +1,294 @@
+use actix_web::http::StatusCode;
+use base64::{
+    Engine,
+    engine::general_purpose::URL_SAFE_NO_PAD
+};
+use ciborium::Value as CborValue;
+use p256::{
+    PublicKey,
+    ecdsa::{
+        Signature,
+        VerifyingKey,
+        signature::Verifier
+    }
+};
+use rand::{
+    RngCore,
+    rngs::OsRng
+};
+use serde::Deserialize;
+use sha2::{
+    Digest,
+    Sha256
+};
+
+use crate::errors::{
+    AppError,
+    AppErrorMessage
+};
+
+/* * authenticator data flags */
+const FLAG_USER_PRESENT: u8 = 0x01;
+const FLAG_USER_VERIFIED: u8 = 0x04;
+const FLAG_ATTESTED_CREDENTIAL_DATA: u8 = 0x40;
+/* * end authenticator data flags */
+
+/* * COSE algorithm identifier for ES256 (ECDSA w/ SHA-256 on P-256) */
+pub const COSE_ALG_ES256: i64 = -7;
+
+#[derive(Deserialize)]
+pub struct CollectedClientData {
+    #[serde(rename = "type")]
+    pub ceremony_type: String,
+    pub challenge: String,
+    pub origin: String
+}
+
+pub struct AttestedCredential {
+    pub credential_id: Vec<u8>,
+    pub public_key: Vec<u8>
+}
+
+pub struct AuthenticatorData {
+    pub rp_id_hash: Vec<u8>,
+    pub flags: u8,
+    pub sign_count: u32,
+    pub attested_credential: Option<AttestedCredential>
+}
+
+/* * build webauthn AppError */
+fn webauthn_error(message: &str) -> AppError {
+    let app_err_message: AppErrorMessage<Option<bool>> = AppErrorMessage {
+        code: StatusCode::UNAUTHORIZED.as_u16(),
+        message: format!("passkey verification failed. {}", message),
+        details: None
+    };
+    AppError::Unauthorized(app_err_message.into())
+}
+/* * end build webauthn AppError */
+
+/* * generate random base64url challenge */
+pub fn generate_challenge() -> String {
+    let mut challenge = [0u8; 32];
+    OsRng.fill_bytes(&mut challenge);
+
+    URL_SAFE_NO_PAD.encode(challenge)
+}
+/* * end generate random base64url challenge */
+
+/* * decode base64url field sent by browser */
+pub fn decode_base64url(
+    field_name: &str,
+    value: &str
+) -> Result<Vec<u8>, AppError>
+{
+    URL_SAFE_NO_PAD
+        .decode(value.trim_end_matches('='))
+        .map_err(|_| webauthn_error(&format!("'{}' is not valid base64url.", field_name)))
+}
+/* * end decode base64url field sent by browser */
+
+/* * parse and check clientDataJSON */
+pub fn verify_client_data(
+    client_data_json: &[u8],
+    expected_type: &str,
+    expected_origin: &str
+) -> Result<CollectedClientData, AppError>
+{
+    let client_data = serde_json::from_slice::<CollectedClientData>(client_data_json)
+        .map_err(|_| webauthn_error("clientDataJSON is malformed."))?;
+
+    if client_data.ceremony_type != expected_type {
+        return Err(webauthn_error(&format!("expected ceremony type '{}'.", expected_type)));
+    }
+    if client_data.origin != expected_origin {
+        return Err(webauthn_error(&format!("origin '{}' is not allowed.", client_data.origin)));
+    }
+
+    Ok(client_data)
+}
+/* * end parse and check clientDataJSON */
+
+/* * parse authenticator data (rpIdHash | flags | signCount | attestedCredentialData) */
+pub fn parse_authenticator_data(
+    auth_data: &[u8]
+) -> Result<AuthenticatorData, AppError>
+{
+    if auth_data.len() < 37 {
+        return Err(webauthn_error("authenticator data is too short."));
+    }
+    let rp_id_hash = auth_data[0..32].to_vec();
+    let flags = auth_data[32];
+    let sign_count = u32::from_be_bytes([auth_data[33], auth_data[34], auth_data[35], auth_data[36]]);
+
+    /* * * attested credential data only present on registration */
+    let mut attested_credential = None;
+    if flags & FLAG_ATTESTED_CREDENTIAL_DATA != 0 {
+        let rest = &auth_data[37..];
+        if rest.len() < 18 {
+            return Err(webauthn_error("attested credential data is too short."));
+        }
+        let credential_id_length = u16::from_be_bytes([rest[16], rest[17]]) as usize;
+        if rest.len() < 18 + credential_id_length {
+            return Err(webauthn_error("credential id length is out of bounds."));
+        }
+        let credential_id = rest[18..18 + credential_id_length].to_vec();
+        let mut cose_key_bytes = &rest[18 + credential_id_length..];
+        let cose_key: CborValue = ciborium::de::from_reader(&mut cose_key_bytes)
+            .map_err(|_| webauthn_error("credential public key is not valid CBOR."))?;
+        let public_key = cose_key_to_sec1(&cose_key)?;
+
+        attested_credential = Some(AttestedCredential { credential_id, public_key });
+    }
+    /* * * end attested credential data only present on registration */
+
+    Ok(
+        AuthenticatorData {
+            rp_id_hash,
+            flags,
+            sign_count,
+            attested_credential
+        }
+    )
+}
+/* * end parse authenticator data (rpIdHash | flags | signCount | attestedCredentialData) */
+
+/* * check rpIdHash, user presence & user verification flags */
+pub fn verify_authenticator_data(
+    auth_data: &AuthenticatorData,
+    rp_id: &str
+) -> Result<(), AppError>
+{
+    let expected_rp_id_hash = Sha256::digest(rp_id.as_bytes());
+    if auth_data.rp_id_hash.as_slice() != expected_rp_id_hash.as_slice() {
+        return Err(webauthn_error("relying party id hash mismatch."));
+    }
+    if auth_data.flags & FLAG_USER_PRESENT == 0 {
+        return Err(webauthn_error("user presence flag is not set."));
+    }
+    if auth_data.flags & FLAG_USER_VERIFIED == 0 {
+        return Err(webauthn_error("user verification flag is not set."));
+    }
+
+    Ok(())
+}
+/* * end check rpIdHash, user presence & user verification flags */
+
+/* * extract authData from attestationObject (only "none" attestation is accepted) */
+pub fn parse_attestation_object(
+    attestation_object: &[u8]
+) -> Result<Vec<u8>, AppError>
+{
+    let attestation: CborValue = ciborium::de::from_reader(attestation_object)
+        .map_err(|_| webauthn_error("attestationObject is not valid CBOR."))?;
+    let entries = attestation
+        .as_map()
+        .ok_or_else(|| webauthn_error("attestationObject must be a CBOR map."))?;
+
+    let mut fmt = None;
+    let mut auth_data = None;
+    for (key, value) in entries {
+        match key.as_text() {
+            Some("fmt") => fmt = value.as_text().map(str::to_string),
+            Some("authData") => auth_data = value.as_bytes().cloned(),
+            _ => {}
+        }
+    }
+
+    if fmt.as_deref() != Some("none") {
+        return Err(webauthn_error("only 'none' attestation format is supported."));
+    }
+
+    auth_data.ok_or_else(|| webauthn_error("attestationObject is missing authData."))
+}
+/* * end extract authData from attestationObject (only "none" attestation is accepted) */
+
+/* * convert COSE EC2 P-256 key to SEC1 uncompressed point */
+fn cose_key_to_sec1(
+    cose_key: &CborValue
+) -> Result<Vec<u8>, AppError>
+{
+    let entries = cose_key
+        .as_map()
+        .ok_or_else(|| webauthn_error("credential public key must be a COSE map."))?;
+
+    let mut kty = None;
+    let mut alg = None;
+    let mut crv = None;
+    let mut x = None;
+    let mut y = None;
+    for (key, value) in entries {
+        let label = key.as_integer().map(i128::from);
+        match label {
+            Some(1) => kty = value.as_integer().map(i128::from),
+            Some(3) => alg = value.as_integer().map(i128::from),
+            Some(-1) => crv = value.as_integer().map(i128::from),
+            Some(-2) => x = value.as_bytes().cloned(),
+            Some(-3) => y = value.as_bytes().cloned(),
+            _ => {}
+        }
+    }
+
+    /* * * kty 2 = EC2, crv 1 = P-256 */
+    if kty != Some(2) || alg != Some(COSE_ALG_ES256 as i128) || crv != Some(1) {
+        return Err(webauthn_error("only ES256 (P-256) passkeys are supported."));
+    }
+    let (x, y) = match (x, y) {
+        (Some(x), Some(y)) if x.len() == 32 && y.len() == 32 => (x, y),
+        _ => return Err(webauthn_error("credential public key coordinates are invalid."))
+    };
+
+    let mut sec1 = Vec::with_capacity(65);
+    sec1.push(0x04);
+    sec1.extend_from_slice(&x);
+    sec1.extend_from_slice(&y);
+    PublicKey::from_sec1_bytes(&sec1)
+        .map_err(|_| webauthn_error("credential public key is not on the P-256 curve."))?;
+
+    Ok(sec1)
+}
+/* * end convert COSE EC2 P-256 key to SEC1 uncompressed point */
+
+/* * verify assertion signature over authenticatorData || sha256(clientDataJSON) */
+pub fn verify_assertion_signature(
+    public_key: &[u8],
+    authenticator_data: &[u8],
+    client_data_json: &[u8],
+    signature: &[u8]
+) -> Result<(), AppError>
+{
+    let verifying_key = VerifyingKey::from_sec1_bytes(public_key)
+        .map_err(|_| webauthn_error("stored passkey public key is invalid."))?;
+    let signature = Signature::from_der(signature)
+        .map_err(|_| webauthn_error("signature is not a valid DER ECDSA signature."))?;
+
+    let mut signed_data = authenticator_data.to_vec();
+    signed_data.extend_from_slice(&Sha256::digest(client_data_json));
+
+    verifying_key
+        .verify(&signed_data, &signature)
+        .map_err(|_| webauthn_error("signature does not match the registered passkey."))
+}
+/* * end verify assertion signature over authenticatorData || sha256(clientDataJSON) */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn auth_data(rp_id: &str, flags: u8) -> AuthenticatorData {
+        AuthenticatorData {
+            rp_id_hash: Sha256::digest(rp_id.as_bytes()).to_vec(),
+            flags,
+            sign_count: 1,
+            attested_credential: None
+        }
+    }
+
+    #[test]
+    fn verify_authenticator_data_requires_presence_and_verification() {
+        assert!(verify_authenticator_data(&auth_data("localhost", FLAG_USER_PRESENT | FLAG_USER_VERIFIED), "localhost").is_ok());
+        assert!(verify_authenticator_data(&auth_data("localhost", FLAG_USER_PRESENT), "localhost").is_err());
+        assert!(verify_authenticator_data(&auth_data("localhost", FLAG_USER_VERIFIED), "localhost").is_err());
+        assert!(verify_authenticator_data(&auth_data("example.com", FLAG_USER_PRESENT | FLAG_USER_VERIFIED), "localhost").is_err());
+    }
+}
@@ -7,12 +7,17 @@ use actix_web::{
     ResponseError,
     HttpResponse,
     body::BoxBody,
-    http::StatusCode
+    http::{
+        StatusCode,
+        header::WWW_AUTHENTICATE
+    }
 };
 use serde::Serialize;
 use validator::ValidationErrors;
 use sqlx::Error as SqlxError;
 
+use crate::auth::DPOP_NONCE_HEADER;
+
 #[derive(ThisError, Debug)]
 pub enum AppError {
     #[error("Unprocessable Entity: {0}")]
@@ -30,6 +35,12 @@ pub enum AppError {
     Forbidden(JsonValue),
     #[error("See Other: {0}")]
     SeeOther(JsonValue),
+    #[error("Precondition Failed: {0}")]
+    PreconditionFailed(JsonValue),
+    #[error("Precondition Required: {0}")]
+    PreconditionRequired(JsonValue),
+    #[error("Use DPoP Nonce: {0}")]
+    UseDpopNonce(JsonValue),
 }
 
 /* * convert AppError to HttpResponse */
@@ -42,12 +53,58 @@ impl ResponseError for AppError {
             Self::NotFound(ref message) => HttpResponse::NotFound().json(message),
             Self::Unauthorized(ref message) => HttpResponse::Unauthorized().json(message),
             Self::Forbidden(ref message) => HttpResponse::Forbidden().json(message),
-            Self::SeeOther(ref message) => HttpResponse::SeeOther().json(message)
+            Self::SeeOther(ref message) => HttpResponse::SeeOther().json(message),
+            Self::PreconditionFailed(ref message) => HttpResponse::PreconditionFailed().json(message),
+            Self::PreconditionRequired(ref message) => HttpResponse::PreconditionRequired().json(message),
+            Self::UseDpopNonce(ref message) => {
+                /* * * fresh nonce goes to DPoP-Nonce header, resource requests also get WWW-Authenticate challenge */
+                let status_code = message
+                    .pointer("/error/code")
+                    .and_then(JsonValue::as_u64)
+                    .and_then(|code| StatusCode::from_u16(code as u16).ok())
+                    .unwrap_or(StatusCode::UNAUTHORIZED);
+                let dpop_nonce = message
+                    .pointer("/error/details/dpop_nonce")
+                    .and_then(JsonValue::as_str)
+                    .unwrap_or_default();
+                let mut response = HttpResponse::build(status_code);
+                response.insert_header((DPOP_NONCE_HEADER, dpop_nonce));
+                if status_code == StatusCode::UNAUTHORIZED {
+                    response.insert_header((WWW_AUTHENTICATE, r#"DPoP error="use_dpop_nonce", error_description="Resource server requires nonce in DPoP proof""#));
+                }
+                response.json(message)
+                /* * * end fresh nonce goes to DPoP-Nonce header, resource requests also get WWW-Authenticate challenge */
+            }
         }
     }
 }
 /* * end convert AppError to HttpResponse */
 
+/* * get readable message from AppError */
+impl AppError {
+    pub fn message(&self) -> String {
+        let value = match *self {
+            Self::UnprocessableEntity(ref message) => message,
+            Self::InternalServerError(ref message) => message,
+            Self::Conflict(ref message) => message,
+            Self::NotFound(ref message) => message,
+            Self::Unauthorized(ref message) => message,
+            Self::Forbidden(ref message) => message,
+            Self::SeeOther(ref message) => message,
+            Self::PreconditionFailed(ref message) => message,
+            Self::PreconditionRequired(ref message) => message,
+            Self::UseDpopNonce(ref message) => message
+        };
+
+        value
+            .pointer("/error/message")
+            .and_then(|message| message.as_str())
+            .map(str::to_string)
+            .unwrap_or_else(|| value.to_string())
+    }
+}
+/* * end get readable message from AppError */
+
 /* * custom app json error message */
 pub struct AppErrorMessage<T> {
     pub code: u16,
@@ -1,15 +1,41 @@
 mod auth;
 mod user;
 mod ping;
+mod audit;
 
-pub use auth::scoped_auth;
-pub use user::scoped_user;
+pub use auth::{
+    scoped_auth,
+    build_credential_verifier,
+    build_token_format
+};
+pub use user::{
+    scoped_user,
+    schedule_deleted_user_purge
+};
 pub use ping::scoped_ping;
+pub use audit::scoped_audit;
 
 
 mod db;
 mod errors;
+mod mailer;
+mod tls;
 mod types;
 
 pub use db::establish_connection;
-pub use types::AppState;
\ No newline at end of file
+pub use mailer::build_mailer;
+pub use tls::{
+    build_tls_config,
+    extract_client_certificate
+};
+pub use types::{
+    AppState,
+    ClientsConfig,
+    CsrfConfig,
+    MtlsConfig,
+    PreconditionConfig,
+    RetentionConfig,
+    SessionConfig,
+    TokenConfig,
+    WebauthnConfig
+};
\ No newline at end of file
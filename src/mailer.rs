@@ -0,0 +1,154 @@
+use std::{
+    fmt,
+    sync::Arc
+};
+use actix_web::http::StatusCode;
+use async_trait::async_trait;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use lettre::{
+    AsyncSmtpTransport,
+    AsyncTransport,
+    Message,
+    Tokio1Executor,
+    message::Mailbox
+};
+
+use crate::errors::{
+    AppError,
+    AppErrorMessage
+};
+
+#[derive(Clone, Debug)]
+pub struct MailMessage {
+    pub to: String,
+    pub subject: String,
+    pub body: String
+}
+
+/* * pluggable mail transport */
+#[async_trait]
+pub trait Mailer: Send + Sync + fmt::Debug {
+    async fn send(&self, message: MailMessage) -> Result<(), AppError>;
+}
+/* * end pluggable mail transport */
+
+/* * build mailer AppError */
+fn mailer_error(message: String) -> AppError {
+    log::error!("{}", message);
+    let app_err_message: AppErrorMessage<Option<bool>> = AppErrorMessage {
+        code: StatusCode::INTERNAL_SERVER_ERROR.as_u16(),
+        message,
+        details: None
+    };
+    AppError::InternalServerError(app_err_message.into())
+}
+/* * end build mailer AppError */
+
+/* * development mailer, logs mail at debug level with every link token redacted */
+static RE_LINK_TOKEN: Lazy<Regex> = Lazy::new(|| Regex::new(r"token=[^\s&]+").unwrap());
+
+fn redact_link_tokens(body: &str) -> String {
+    RE_LINK_TOKEN.replace_all(body, "token=[redacted]").into_owned()
+}
+
+#[derive(Debug)]
+pub struct LogMailer;
+
+#[async_trait]
+impl Mailer for LogMailer {
+    async fn send(&self, message: MailMessage) -> Result<(), AppError> {
+        log::debug!(
+            "[mailer] to: {}\n[mailer] subject: {}\n{}",
+            message.to,
+            message.subject,
+            redact_link_tokens(&message.body)
+        );
+        Ok(())
+    }
+}
+/* * end development mailer, logs mail at debug level with every link token redacted */
+
+/* * smtp mailer */
+pub struct SmtpMailer {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox
+}
+
+impl fmt::Debug for SmtpMailer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SmtpMailer")
+            .field("from", &self.from.to_string())
+            .finish()
+    }
+}
+
+impl SmtpMailer {
+    pub fn new(smtp_url: &str, from: &str) -> Result<Self, String> {
+        let transport = AsyncSmtpTransport::<Tokio1Executor>::from_url(smtp_url)
+            .map_err(|e| e.to_string())?
+            .build();
+        let from = from
+            .parse::<Mailbox>()
+            .map_err(|e| e.to_string())?;
+
+        Ok(Self { transport, from })
+    }
+}
+
+#[async_trait]
+impl Mailer for SmtpMailer {
+    async fn send(&self, message: MailMessage) -> Result<(), AppError> {
+        let to = message.to
+            .parse::<Mailbox>()
+            .map_err(|e| mailer_error(format!("invalid mail recipient '{}': {}", message.to, e)))?;
+        let email = Message::builder()
+            .from(self.from.clone())
+            .to(to)
+            .subject(message.subject)
+            .body(message.body)
+            .map_err(|e| mailer_error(format!("failed to build mail: {}", e)))?;
+
+        self.transport
+            .send(email)
+            .await
+            .map_err(|e| mailer_error(format!("failed to send mail: {}", e)))?;
+
+        Ok(())
+    }
+}
+/* * end smtp mailer */
+
+/* * select mailer from config ("log" or "smtp") */
+pub fn build_mailer(
+    mailer: &str,
+    smtp_url: Option<String>,
+    mail_from: &str
+) -> Result<Arc<dyn Mailer>, String>
+{
+    match mailer {
+        "log" => Ok(Arc::new(LogMailer)),
+        "smtp" => {
+            let smtp_url = smtp_url.ok_or("SMTP_URL must be set when MAILER=smtp.")?;
+            Ok(Arc::new(SmtpMailer::new(&smtp_url, mail_from)?))
+        },
+        other => Err(format!("unknown mailer '{}'. supported mailers: log, smtp", other))
+    }
+}
+/* * end select mailer from config ("log" or "smtp") */
+
+#[cfg(test)]
+mod tests {
+    use super::redact_link_tokens;
+
+    #[test]
+    fn redacts_every_link_token() {
+        let body = "open\nhttp://localhost/api/auth/password-reset?token=abc_DEF-123\nor http://localhost/x?token=xyz&next=1\n";
+        let redacted = redact_link_tokens(body);
+
+        assert!(!redacted.contains("abc_DEF-123"));
+        assert!(!redacted.contains("xyz"));
+        assert!(redacted.contains("?token=[redacted]\n"));
+        assert!(redacted.contains("?token=[redacted]&next=1"));
+    }
+}
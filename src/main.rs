@@ -8,9 +8,24 @@ use actix_cors::Cors;
 
 use rst04_jwt::{
     AppState, 
+    ClientsConfig,
+    CsrfConfig,
+    MtlsConfig,
+    PreconditionConfig,
+    RetentionConfig,
+    SessionConfig,
+    TokenConfig,
+    WebauthnConfig,
     establish_connection,
+    build_tls_config,
+    extract_client_certificate,
+    build_mailer,
+    build_credential_verifier,
+    build_token_format,
     scoped_auth,
-    scoped_user, scoped_ping
+    scoped_user, scoped_ping,
+    schedule_deleted_user_purge,
+    scoped_audit
 };
 
 #[actix_web::main]
@@ -41,22 +56,173 @@ async fn main() -> std::io::Result<()> {
         eprintln!("{} [{}]", error_message, e);
         std::process::exit(1);
     });
+    let parse_minutes = |name: &str, default: &str| -> i64 {
+        std::env::var(name).unwrap_or(default.to_string()).parse::<i64>().unwrap_or_else(|e| {
+            let error_message = format!("{} must be a number of minutes.", name);
+            eprintln!("{} [{}]", error_message, e);
+            std::process::exit(1);
+        })
+    };
+    let access_token_expired = parse_minutes("ACCESS_TOKEN_EXPIRED_MINUTES", "1");
+    let refresh_token_expired = parse_minutes("REFRESH_TOKEN_EXPIRED_MINUTES", "2");
+    let remember_me_refresh_token_expired = parse_minutes("REMEMBER_ME_REFRESH_TOKEN_EXPIRED_MINUTES", "43200");
+    let session_idle_timeout = parse_minutes("SESSION_IDLE_TIMEOUT_MINUTES", "30");
+    let session_absolute_lifetime = parse_minutes("SESSION_ABSOLUTE_LIFETIME_MINUTES", "43200");
+    let deleted_user_retention = std::env::var("DELETED_USER_RETENTION_DAYS").unwrap_or("30".to_string()).parse::<i64>().unwrap_or_else(|e| {
+        let error_message = "DELETED_USER_RETENTION_DAYS must be a number of days.";
+        eprintln!("{} [{}]", error_message, e);
+        std::process::exit(1);
+    });
+    let token_format = std::env::var("TOKEN_FORMAT").unwrap_or("jwt".to_string());
+    let paseto_access_secret_key = std::env::var("PASETO_ACCESS_SECRET_KEY").ok();
+    let paseto_refresh_secret_key = std::env::var("PASETO_REFRESH_SECRET_KEY").ok();
+    let public_url = std::env::var("APP_PUBLIC_URL").unwrap_or("http://localhost:3001".to_string());
+    let mailer = std::env::var("MAILER").unwrap_or_else(|e| {
+        let error_message = "MAILER must be set (smtp, or log for development without real mail).";
+        eprintln!("{} [{}]", error_message, e);
+        std::process::exit(1);
+    });
+    let smtp_url = std::env::var("SMTP_URL").ok();
+    let mail_from = std::env::var("MAIL_FROM").unwrap_or("no-reply@localhost".to_string());
+    let webauthn_rp_id = std::env::var("WEBAUTHN_RP_ID").unwrap_or("localhost".to_string());
+    let webauthn_rp_name = std::env::var("WEBAUTHN_RP_NAME").unwrap_or("backend_https".to_string());
+    let webauthn_rp_origin = std::env::var("WEBAUTHN_RP_ORIGIN").unwrap_or("http://localhost:3001".to_string());
+    let allowed_origins = std::env::var("ALLOWED_ORIGINS").unwrap_or("http://localhost:3000".to_string());
+    let default_client_token_transports = std::env::var("DEFAULT_CLIENT_TOKEN_TRANSPORTS").unwrap_or("cookie".to_string());
+    let client_token_transports = std::env::var("CLIENT_TOKEN_TRANSPORTS").unwrap_or_default();
+    let tls_cert_path = std::env::var("TLS_CERT_PATH").ok();
+    let tls_key_path = std::env::var("TLS_KEY_PATH").ok();
+    let mtls_client_ca_path = std::env::var("MTLS_CLIENT_CA_PATH").ok();
+    let mtls_principals = std::env::var("MTLS_PRINCIPALS").unwrap_or_default();
+    let credential_verifiers = std::env::var("CREDENTIAL_VERIFIERS").unwrap_or("db".to_string());
+    let ldap_url = std::env::var("LDAP_URL").ok();
+    let ldap_bind_dn_template = std::env::var("LDAP_BIND_DN_TEMPLATE").ok();
+    let ldap_starttls = std::env::var("LDAP_STARTTLS").is_ok_and(|starttls| starttls == "true");
+    let if_match_required = std::env::var("REQUIRE_IF_MATCH").is_ok_and(|required| required == "true");
+    let htpasswd_path = std::env::var("HTPASSWD_PATH").ok();
+    let htpasswd_role = std::env::var("HTPASSWD_ROLE").unwrap_or("admin".to_string());
     /* * end env var */
 
+    /* * token format, jwt or PASETO v4 */
+    let token_format = build_token_format(
+        &token_format,
+        &secret_access_token,
+        &secret_refresh_token,
+        paseto_access_secret_key.as_deref(),
+        paseto_refresh_secret_key.as_deref()
+    ).unwrap_or_else(|e| {
+        let error_message = "INVALID TOKEN_FORMAT CONFIG.";
+        eprintln!("{} [{}]", error_message, e);
+        std::process::exit(1);
+    });
+    /* * end token format, jwt or PASETO v4 */
+
+    /* * token lifetimes */
+    let tokens = TokenConfig::from_minutes(
+        access_token_expired,
+        refresh_token_expired,
+        remember_me_refresh_token_expired
+    ).unwrap_or_else(|e| {
+        let error_message = "INVALID TOKEN LIFETIME CONFIG.";
+        eprintln!("{} [{}]", error_message, e);
+        std::process::exit(1);
+    });
+    let sessions = SessionConfig::from_minutes(
+        session_idle_timeout,
+        session_absolute_lifetime
+    ).unwrap_or_else(|e| {
+        let error_message = "INVALID SESSION LIFETIME CONFIG.";
+        eprintln!("{} [{}]", error_message, e);
+        std::process::exit(1);
+    });
+    let preconditions = PreconditionConfig { if_match_required };
+    let retention = RetentionConfig::from_days(deleted_user_retention).unwrap_or_else(|e| {
+        let error_message = "INVALID RETENTION CONFIG.";
+        eprintln!("{} [{}]", error_message, e);
+        std::process::exit(1);
+    });
+    /* * end token lifetimes */
+
+    /* * csrf & cors origin allowlist */
+    let csrf = CsrfConfig::from_list(&allowed_origins).unwrap_or_else(|e| {
+        let error_message = "INVALID ALLOWED_ORIGINS CONFIG.";
+        eprintln!("{} [{}]", error_message, e);
+        std::process::exit(1);
+    });
+    /* * end csrf & cors origin allowlist */
+
+    /* * per client refresh_token transport policy */
+    let clients = ClientsConfig::from_list(&default_client_token_transports, &client_token_transports).unwrap_or_else(|e| {
+        let error_message = "INVALID CLIENT TOKEN TRANSPORT CONFIG.";
+        eprintln!("{} [{}]", error_message, e);
+        std::process::exit(1);
+    });
+    /* * end per client refresh_token transport policy */
+
+    /* * tls & mtls service principals */
+    let tls_config = match (&tls_cert_path, &tls_key_path) {
+        (Some(cert_path), Some(key_path)) => Some(
+            build_tls_config(cert_path, key_path, mtls_client_ca_path.as_deref()).unwrap_or_else(|e| {
+                let error_message = "INVALID TLS CONFIG.";
+                eprintln!("{} [{}]", error_message, e);
+                std::process::exit(1);
+            })
+        ),
+        _ => None
+    };
+    let mtls = MtlsConfig::from_list(&mtls_principals).unwrap_or_else(|e| {
+        let error_message = "INVALID MTLS_PRINCIPALS CONFIG.";
+        eprintln!("{} [{}]", error_message, e);
+        std::process::exit(1);
+    });
+    /* * end tls & mtls service principals */
+
+    /* * mailer */
+    let mailer = build_mailer(&mailer, smtp_url, &mail_from).unwrap_or_else(|e| {
+        let error_message = "FAILED TO CREATE MAILER.";
+        eprintln!("{} [{}]", error_message, e);
+        std::process::exit(1);
+    });
+    /* * end mailer */
+
     /* database pool */
     let db_pool = establish_connection(&db_url).await;
     /* end database pool */
 
+    /* * credential verifiers, tried in configured order */
+    let credential_verifier = build_credential_verifier(
+        &credential_verifiers,
+        &db_pool,
+        ldap_url,
+        ldap_bind_dn_template,
+        ldap_starttls,
+        htpasswd_path,
+        &htpasswd_role
+    ).unwrap_or_else(|e| {
+        let error_message = "INVALID CREDENTIAL VERIFIER CONFIG.";
+        eprintln!("{} [{}]", error_message, e);
+        std::process::exit(1);
+    });
+    /* * end credential verifiers, tried in configured order */
+
+    let webauthn = WebauthnConfig {
+        rp_id: webauthn_rp_id,
+        rp_name: webauthn_rp_name,
+        rp_origin: webauthn_rp_origin
+    };
     let app_state = web::Data::new( 
-        AppState { db_pool, secret_access_token, secret_refresh_token } 
+        AppState { db_pool, secret_access_token, secret_refresh_token, public_url, token_format, tokens, sessions, retention, preconditions, webauthn, csrf, clients, mtls, mailer, credential_verifier } 
     );
+    schedule_deleted_user_purge(app_state.clone());
     /* * backbone server */
 
-    HttpServer::new(move || {
-        let cors = Cors::default()
+    let server = HttpServer::new(move || {
+        let cors = app_state.csrf.allowed_origins
+            .iter()
+            .fold(Cors::default(), |cors, origin| cors.allowed_origin(origin))
             .allow_any_header()
             .allow_any_method()
-            .allow_any_origin()
+            .expose_headers(vec!["DPoP-Nonce", "ETag"])
             .supports_credentials();
 
         App::new()
@@ -68,9 +234,15 @@ async fn main() -> std::io::Result<()> {
                     .configure(scoped_ping)
                     .configure(scoped_auth)
                     .configure(scoped_user)
+                    .configure(scoped_audit)
             )
     })
-    .bind("0.0.0.0:3001")?
+    .on_connect(extract_client_certificate);
+
+    match tls_config {
+        Some(tls_config) => server.bind_rustls_0_23("0.0.0.0:3001", tls_config)?,
+        None => server.bind("0.0.0.0:3001")?
+    }
     .run()
     .await
     /* * end backbone server */
@@ -0,0 +1,119 @@
+use std::{
+    any::Any,
+    fs::File,
+    io::BufReader,
+    sync::Arc
+};
+use actix_tls::accept::rustls_0_23::TlsStream;
+use actix_web::{
+    dev::Extensions,
+    rt::net::TcpStream
+};
+use base64::{
+    Engine,
+    engine::general_purpose::URL_SAFE_NO_PAD
+};
+use rustls::{
+    RootCertStore,
+    ServerConfig,
+    server::WebPkiClientVerifier
+};
+use sha2::{
+    Digest,
+    Sha256
+};
+use x509_parser::prelude::{
+    FromDer,
+    X509Certificate
+};
+
+/* * verified client certificate of the tls connection */
+#[derive(Clone, Debug)]
+pub struct ClientCertificate {
+    pub subject: String,
+    pub thumbprint: String
+}
+
+/* * normalize distinguished name, "CN=a, O=b" and "CN=a,O=b" are the same subject */
+pub fn normalize_subject(subject: &str) -> String {
+    subject
+        .split(',')
+        .map(str::trim)
+        .filter(|rdn| !rdn.is_empty())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+/* * end normalize distinguished name, "CN=a, O=b" and "CN=a,O=b" are the same subject */
+
+/* * RFC 8705 x5t#S256, base64url sha256 of DER encoded certificate */
+pub fn certificate_thumbprint(der: &[u8]) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(der))
+}
+/* * end RFC 8705 x5t#S256, base64url sha256 of DER encoded certificate */
+
+/* * build rustls server config, client certificate is verified against client ca when provided */
+pub fn build_tls_config(
+    cert_path: &str,
+    key_path: &str,
+    client_ca_path: Option<&str>
+) -> Result<ServerConfig, String>
+{
+    let open = |path: &str| {
+        File::open(path)
+            .map(BufReader::new)
+            .map_err(|e| format!("failed to open '{}': {}", path, e))
+    };
+
+    let cert_chain = rustls_pemfile::certs(&mut open(cert_path)?)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("failed to read certificate '{}': {}", cert_path, e))?;
+    let private_key = rustls_pemfile::private_key(&mut open(key_path)?)
+        .map_err(|e| format!("failed to read private key '{}': {}", key_path, e))?
+        .ok_or_else(|| format!("no private key found in '{}'.", key_path))?;
+
+    let builder = ServerConfig::builder();
+    let builder = match client_ca_path {
+        Some(client_ca_path) => {
+            let mut roots = RootCertStore::empty();
+            for ca_cert in rustls_pemfile::certs(&mut open(client_ca_path)?) {
+                let ca_cert = ca_cert.map_err(|e| format!("failed to read client ca '{}': {}", client_ca_path, e))?;
+                roots.add(ca_cert).map_err(|e| format!("invalid client ca '{}': {}", client_ca_path, e))?;
+            }
+            /* * * browsers & mobile apps do not present certificates, only services do */
+            let client_verifier = WebPkiClientVerifier::builder(Arc::new(roots))
+                .allow_unauthenticated()
+                .build()
+                .map_err(|e| format!("failed to build client certificate verifier: {}", e))?;
+            /* * * end browsers & mobile apps do not present certificates, only services do */
+            builder.with_client_cert_verifier(client_verifier)
+        },
+        None => builder.with_no_client_auth()
+    };
+
+    builder
+        .with_single_cert(cert_chain, private_key)
+        .map_err(|e| format!("invalid server certificate or private key: {}", e))
+}
+/* * end build rustls server config, client certificate is verified against client ca when provided */
+
+/* * store verified client certificate as connection data, read it with HttpRequest::conn_data */
+pub fn extract_client_certificate(connection: &dyn Any, data: &mut Extensions) {
+    let Some(tls_stream) = connection.downcast_ref::<TlsStream<TcpStream>>() else {
+        return;
+    };
+    let (_, session) = tls_stream.get_ref();
+    let Some(der) = session.peer_certificates().and_then(|certificates| certificates.first()) else {
+        return;
+    };
+
+    match X509Certificate::from_der(der.as_ref()) {
+        Ok((_, certificate)) => {
+            data.insert(ClientCertificate {
+                subject: normalize_subject(&certificate.subject().to_string()),
+                thumbprint: certificate_thumbprint(der.as_ref())
+            });
+        },
+        Err(e) => log::error!("failed to parse client certificate: {}", e)
+    }
+}
+/* * end store verified client certificate as connection data, read it with HttpRequest::conn_data */
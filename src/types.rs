@@ -1,9 +1,290 @@
-use crate::db::DbPool;
+use std::{
+    collections::HashMap,
+    sync::Arc
+};
+use chrono::Duration as ChronoDuration;
+
+use crate::{
+    db::DbPool,
+    mailer::Mailer,
+    auth::{
+        CredentialVerifier,
+        TokenFormat
+    }
+};
 
 #[derive(Clone, Debug)]
 pub struct AppState {
     pub db_pool: DbPool,
     pub secret_access_token: String,
-    pub secret_refresh_token: String
+    pub secret_refresh_token: String,
+    pub public_url: String,
+    pub token_format: Arc<dyn TokenFormat>,
+    pub tokens: TokenConfig,
+    pub sessions: SessionConfig,
+    pub retention: RetentionConfig,
+    pub preconditions: PreconditionConfig,
+    pub webauthn: WebauthnConfig,
+    pub csrf: CsrfConfig,
+    pub clients: ClientsConfig,
+    pub mtls: MtlsConfig,
+    pub mailer: Arc<dyn Mailer>,
+    pub credential_verifier: Arc<dyn CredentialVerifier>
+}
+
+#[derive(Clone, Debug)]
+pub struct TokenConfig {
+    pub access_token_expired: ChronoDuration,
+    pub refresh_token_expired: ChronoDuration,
+    pub remember_me_refresh_token_expired: ChronoDuration
+}
+
+impl TokenConfig {
+    /* * build token lifetimes from minutes, remember me must not be shorter than regular refresh token */
+    pub fn from_minutes(
+        access_token_expired: i64,
+        refresh_token_expired: i64,
+        remember_me_refresh_token_expired: i64
+    ) -> Result<Self, String>
+    {
+        if access_token_expired <= 0 || refresh_token_expired <= 0 || remember_me_refresh_token_expired <= 0 {
+            return Err(String::from("token lifetimes must be greater than 0 minutes."));
+        }
+        if refresh_token_expired < access_token_expired {
+            return Err(String::from("refresh token lifetime must not be shorter than access token lifetime."));
+        }
+        if remember_me_refresh_token_expired < refresh_token_expired {
+            return Err(String::from("remember me refresh token lifetime must not be shorter than refresh token lifetime."));
+        }
+
+        Ok(
+            Self {
+                access_token_expired: ChronoDuration::minutes(access_token_expired),
+                refresh_token_expired: ChronoDuration::minutes(refresh_token_expired),
+                remember_me_refresh_token_expired: ChronoDuration::minutes(remember_me_refresh_token_expired)
+            }
+        )
+    }
+    /* * end build token lifetimes from minutes, remember me must not be shorter than regular refresh token */
+}
+
+#[derive(Clone, Debug)]
+pub struct SessionConfig {
+    pub idle_timeout: Option<ChronoDuration>,
+    pub absolute_lifetime: Option<ChronoDuration>
+}
+
+impl SessionConfig {
+    /* * build session policies from minutes, 0 disables the policy */
+    pub fn from_minutes(
+        idle_timeout: i64,
+        absolute_lifetime: i64
+    ) -> Result<Self, String>
+    {
+        if idle_timeout < 0 || absolute_lifetime < 0 {
+            return Err(String::from("session idle timeout and absolute lifetime must not be negative."));
+        }
+        if idle_timeout > 0 && absolute_lifetime > 0 && idle_timeout > absolute_lifetime {
+            return Err(String::from("session idle timeout must not be longer than session absolute lifetime."));
+        }
+
+        Ok(
+            Self {
+                idle_timeout: (idle_timeout > 0).then(|| ChronoDuration::minutes(idle_timeout)),
+                absolute_lifetime: (absolute_lifetime > 0).then(|| ChronoDuration::minutes(absolute_lifetime))
+            }
+        )
+    }
+    /* * end build session policies from minutes, 0 disables the policy */
+}
+
+#[derive(Clone, Debug)]
+pub struct RetentionConfig {
+    pub deleted_user_retention: Option<ChronoDuration>
+}
+
+impl RetentionConfig {
+    /* * build retention policy from days, 0 keeps soft deleted users forever */
+    pub fn from_days(deleted_user_retention: i64) -> Result<Self, String> {
+        if deleted_user_retention < 0 {
+            return Err(String::from("deleted user retention must not be negative."));
+        }
+
+        Ok(
+            Self {
+                deleted_user_retention: (deleted_user_retention > 0).then(|| ChronoDuration::days(deleted_user_retention))
+            }
+        )
+    }
+    /* * end build retention policy from days, 0 keeps soft deleted users forever */
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct PreconditionConfig {
+    /* * modifying user requests without If-Match are rejected with 428 */
+    pub if_match_required: bool
+}
+
+#[derive(Clone, Debug)]
+pub struct CsrfConfig {
+    pub allowed_origins: Vec<String>
+}
+
+impl CsrfConfig {
+    /* * build origin allowlist from comma separated origins, wildcard is not allowed with credentials */
+    pub fn from_list(allowed_origins: &str) -> Result<Self, String> {
+        let allowed_origins = allowed_origins
+            .split(',')
+            .map(|origin| origin.trim().trim_end_matches('/').to_string())
+            .filter(|origin| !origin.is_empty())
+            .collect::<Vec<_>>();
+
+        if allowed_origins.is_empty() {
+            return Err(String::from("at least one allowed origin must be set."));
+        }
+        if let Some(origin) = allowed_origins.iter().find(|origin| !origin.starts_with("http://") && !origin.starts_with("https://")) {
+            return Err(format!("allowed origin '{}' must be an http(s) origin, wildcard is not supported.", origin));
+        }
+
+        Ok( Self { allowed_origins } )
+    }
+    /* * end build origin allowlist from comma separated origins, wildcard is not allowed with credentials */
+
+    pub fn is_allowed_origin(&self, origin: &str) -> bool {
+        self.allowed_origins.iter().any(|allowed_origin| allowed_origin.eq_ignore_ascii_case(origin))
+    }
+}
+
+/* * how a client is allowed to carry its refresh_token */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TokenTransport {
+    Cookie,
+    Body,
+    Header
+}
+
+impl TokenTransport {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Cookie => "cookie",
+            Self::Body => "body",
+            Self::Header => "header"
+        }
+    }
+}
+
+impl std::str::FromStr for TokenTransport {
+    type Err = String;
+
+    fn from_str(transport: &str) -> Result<Self, Self::Err> {
+        match transport.trim() {
+            "cookie" => Ok(Self::Cookie),
+            "body" => Ok(Self::Body),
+            "header" => Ok(Self::Header),
+            other => Err(format!("unknown token transport '{}'. supported transports: cookie, body, header", other))
+        }
+    }
+}
+/* * end how a client is allowed to carry its refresh_token */
+
+#[derive(Clone, Debug)]
+pub struct ClientPolicy {
+    pub transports: Vec<TokenTransport>
+}
+
+impl ClientPolicy {
+    /* * parse transports from comma separated list, e.g. "body,header" */
+    pub fn from_list(transports: &str) -> Result<Self, String> {
+        let transports = transports
+            .split(',')
+            .filter(|transport| !transport.trim().is_empty())
+            .map(str::parse::<TokenTransport>)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if transports.is_empty() {
+            return Err(String::from("client policy must allow at least one token transport."));
+        }
+
+        Ok( Self { transports } )
+    }
+    /* * end parse transports from comma separated list, e.g. "body,header" */
+
+    pub fn allows(&self, transport: TokenTransport) -> bool {
+        self.transports.contains(&transport)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ClientsConfig {
+    pub default_policy: ClientPolicy,
+    pub clients: HashMap<String, ClientPolicy>
+}
+
+impl ClientsConfig {
+    /* * build client policies, clients are separated by ';', e.g. "mobile=body,header;cli=header" */
+    pub fn from_list(default_transports: &str, clients: &str) -> Result<Self, String> {
+        let default_policy = ClientPolicy::from_list(default_transports)?;
+        let clients = clients
+            .split(';')
+            .filter(|client| !client.trim().is_empty())
+            .map(|client| {
+                let (client_id, transports) = client
+                    .split_once('=')
+                    .ok_or_else(|| format!("client policy '{}' must be formatted as client_id=transports.", client))?;
+                Ok( (client_id.trim().to_string(), ClientPolicy::from_list(transports)?) )
+            })
+            .collect::<Result<HashMap<_, _>, String>>()?;
+
+        Ok( Self { default_policy, clients } )
+    }
+    /* * end build client policies, clients are separated by ';', e.g. "mobile=body,header;cli=header" */
+
+    /* * get client policy, requests without client id use default policy */
+    pub fn get_policy(&self, client_id: Option<&str>) -> Option<&ClientPolicy> {
+        match client_id {
+            Some(client_id) => self.clients.get(client_id),
+            None => Some(&self.default_policy)
+        }
+    }
+    /* * end get client policy, requests without client id use default policy */
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct MtlsConfig {
+    pub principals: HashMap<String, String>
+}
+
+impl MtlsConfig {
+    /* * map certificate subjects to service principals, e.g. "billing=CN=billing.internal,O=Example;reports=CN=reports.internal" */
+    pub fn from_list(principals: &str) -> Result<Self, String> {
+        let principals = principals
+            .split(';')
+            .filter(|principal| !principal.trim().is_empty())
+            .map(|principal| {
+                let (principal, subject) = principal
+                    .split_once('=')
+                    .ok_or_else(|| format!("mtls principal '{}' must be formatted as principal=subject.", principal))?;
+                let principal = principal.trim();
+                if principal.is_empty() || !principal.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+                    return Err(format!("mtls principal '{}' must be alphanumeric.", principal));
+                }
+                Ok( (crate::tls::normalize_subject(subject), principal.to_string()) )
+            })
+            .collect::<Result<HashMap<_, _>, String>>()?;
+
+        Ok( Self { principals } )
+    }
+    /* * end map certificate subjects to service principals, e.g. "billing=CN=billing.internal,O=Example;reports=CN=reports.internal" */
+
+    pub fn get_principal(&self, subject: &str) -> Option<&str> {
+        self.principals.get(subject).map(String::as_str)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct WebauthnConfig {
+    pub rp_id: String,
+    pub rp_name: String,
+    pub rp_origin: String
 }
 
@@ -2,45 +2,159 @@ use actix_web::{
     web, 
     Responder, 
     HttpResponse,
-    http::StatusCode
+    HttpRequest,
+    http::{
+        StatusCode,
+        header
+    }
 };
+use std::collections::HashMap;
 use serde_json::json;
 
 use crate::{
     types::AppState,
+    errors::AppError,
+    audit::{
+        record_audit_log,
+        AuditEntry
+    },
     user::{
         services::{
             get_all_users::get_all_users_service,
             get_user::get_user_service,
+            get_user_history::get_user_history_service,
+            search_users::search_users_service,
             insert_user::insert_user_service,
             update_user::update_user_service,
-            delete_user::delete_user_service
+            patch_user::patch_user_service,
+            delete_user::delete_user_service,
+            restore_user::restore_user_service,
+            email_change::{
+                confirm_email_change_service,
+                revert_email_change_service
+            }
         },
         model::{
            In,
-            UserPayload 
+            UserPayload,
+            UserPatchPayload,
+            UserListQuery,
+            UserSearchQuery,
+            UserFieldsQuery,
+            UserHistoryQuery,
+            EmailChangeTokenQuery
+        },
+        helpers::{
+            get_user_id_by_username,
+            get_username_by_id,
+            get_user_view,
+            parse_fields,
+            render_user,
+            get_if_match,
+            require_owner_or_admin,
+            user_etag,
+            MERGE_PATCH_CONTENT_TYPE
         }
     },
-    auth::JwtAuth
+    auth::{
+        JwtAuth,
+        get_client_info,
+        render_form_page,
+        get_sessions_service,
+        revoke_session_service,
+        revoke_all_sessions_service,
+        SCOPE_USERS_READ,
+        SCOPE_USERS_WRITE
+    }
 };
 
 pub async fn get_all_users(
-    _: JwtAuth,
-    app_state: web::Data<AppState>
+    auth: JwtAuth,
+    app_state: web::Data<AppState>,
+    query: web::Query<UserListQuery>
 ) -> impl Responder 
 {
+    if let Err(e) = auth.require_scope(SCOPE_USERS_READ) {
+        return HttpResponse::from_error(e);
+    }
+    /* * only admins may list soft deleted users or sort & filter on private columns, cursors carry the sort value */
+    if query.deleted == Some(true) || query.private_parameter().is_some() {
+        if let Err(e) = auth.require_admin() {
+            return HttpResponse::from_error(e);
+        }
+    }
+    /* * end only admins may list soft deleted users or sort & filter on private columns, cursors carry the sort value */
     let app_state = app_state.get_ref();
+    let query = query.into_inner();
     
-    let get_all_users_service = get_all_users_service(app_state).await;
+    let get_all_users_service = match parse_fields(query.fields.as_deref()) {
+        Ok(fields) => get_all_users_service(app_state, query).await.map(|user_list| (user_list, fields)),
+        Err(e) => Err(e)
+    };
     match get_all_users_service {
-        Ok(users) => {
+        Ok((user_list, fields)) => {
+            let users = user_list.users
+                .iter()
+                .map(|user| render_user(user, get_user_view(&auth, user), fields.as_deref()))
+                .collect::<Vec<_>>();
             let status_code = StatusCode::OK;
             let success_message = json!({
                 "success": true,
                 "code": status_code.as_u16(),
                 "message": "successfully retrieved all users data.",
                 "data": users,
-                "length": users.len()
+                "length": users.len(),
+                "pagination": user_list.pagination
+            });
+            HttpResponse::build(status_code).json(success_message)
+        },
+        Err(e) => HttpResponse::from_error(e)
+    }
+}
+
+pub async fn search_users(
+    auth: JwtAuth,
+    app_state: web::Data<AppState>,
+    query: web::Query<UserSearchQuery>
+) -> impl Responder 
+{
+    if let Err(e) = auth.require_scope(SCOPE_USERS_READ) {
+        return HttpResponse::from_error(e);
+    }
+    let app_state = app_state.get_ref();
+    let query = query.into_inner();
+    
+    let search_users_service = match parse_fields(query.fields.as_deref()) {
+        Ok(fields) => search_users_service(app_state, query, auth.require_admin().is_ok()).await.map(|user_search| (user_search, fields)),
+        Err(e) => Err(e)
+    };
+    match search_users_service {
+        Ok((user_search, fields)) => {
+            /* * highlights only for fields the caller is allowed to see */
+            let results = user_search.results
+                .into_iter()
+                .map(|result| {
+                    let mut rendered = render_user(&result.user, get_user_view(&auth, &result.user), fields.as_deref());
+                    let highlights = result.highlights
+                        .into_iter()
+                        .filter(|(field, _)| rendered.get(field).is_some())
+                        .collect::<HashMap<_, _>>();
+                    if let Some(object) = rendered.as_object_mut() {
+                        object.insert(String::from("score"), json!(result.score));
+                        object.insert(String::from("highlights"), json!(highlights));
+                    }
+                    rendered
+                })
+                .collect::<Vec<_>>();
+            /* * end highlights only for fields the caller is allowed to see */
+            let status_code = StatusCode::OK;
+            let success_message = json!({
+                "success": true,
+                "code": status_code.as_u16(),
+                "message": "successfully searched users.",
+                "data": results,
+                "length": results.len(),
+                "pagination": user_search.pagination
             });
             HttpResponse::build(status_code).json(success_message)
         },
@@ -49,23 +163,75 @@ pub async fn get_all_users(
 }
 
 pub async fn get_user(
-    _: JwtAuth,
+    auth: JwtAuth,
     app_state: web::Data<AppState>,
-    path: web::Path<u32>
+    path: web::Path<u32>,
+    query: web::Query<UserFieldsQuery>
 ) -> impl Responder 
 {
+    if let Err(e) = auth.require_scope(SCOPE_USERS_READ) {
+        return HttpResponse::from_error(e);
+    }
     let app_state = app_state.get_ref();
     let user_id_params = path.into_inner();
     
-    let get_user_service = get_user_service(app_state, user_id_params).await;
+    let get_user_service = match parse_fields(query.fields.as_deref()) {
+        Ok(fields) => get_user_service(app_state, user_id_params, auth.require_admin().is_ok()).await.map(|user| (user, fields)),
+        Err(e) => Err(e)
+    };
     match get_user_service {
-        Ok(user) => {
+        Ok((user, fields)) => {
             let status_code = StatusCode::OK;
             let success_message = json!({
                 "success": true,
                 "code": status_code.as_u16(),
                 "message": "user data retrieved successfully.",
-                "data": user
+                "data": render_user(&user, get_user_view(&auth, &user), fields.as_deref())
+            });
+            HttpResponse::build(status_code)
+                .insert_header((header::ETAG, user_etag(user.version)))
+                .json(success_message)
+        },
+        Err(e) => HttpResponse::from_error(e)
+    }
+}
+
+pub async fn get_user_history(
+    auth: JwtAuth,
+    app_state: web::Data<AppState>,
+    path: web::Path<u32>,
+    query: web::Query<UserHistoryQuery>
+) -> impl Responder 
+{
+    if let Err(e) = auth.require_scope(SCOPE_USERS_READ) {
+        return HttpResponse::from_error(e);
+    }
+    let app_state = app_state.get_ref();
+    let user_id_params = path.into_inner();
+    let query = query.into_inner();
+
+    /* * admins see every history, everyone else only their own */
+    let is_allowed = match auth.require_admin() {
+        Ok(_) => Ok(()),
+        Err(e) => match get_user_id_by_username(&app_state.db_pool, &auth.claims.username).await {
+            Ok(id_user) if id_user == user_id_params => Ok(()),
+            _ => Err(e)
+        }
+    };
+    /* * end admins see every history, everyone else only their own */
+    let get_user_history_service = match is_allowed {
+        Ok(_) => get_user_history_service(app_state, user_id_params, query).await,
+        Err(e) => Err(e)
+    };
+    match get_user_history_service {
+        Ok(user_history) => {
+            let status_code = StatusCode::OK;
+            let success_message = json!({
+                "success": true,
+                "code": status_code.as_u16(),
+                "message": "successfully retrieved user history.",
+                "data": user_history,
+                "length": user_history.len()
             });
             HttpResponse::build(status_code).json(success_message)
         },
@@ -73,14 +239,179 @@ pub async fn get_user(
     }
 }
 
+pub async fn get_user_sessions(
+    auth: JwtAuth,
+    app_state: web::Data<AppState>,
+    path: web::Path<u32>
+) -> impl Responder 
+{
+    if let Err(e) = auth.require_admin().and_then(|_| auth.require_scope(SCOPE_USERS_READ)) {
+        return HttpResponse::from_error(e);
+    }
+    let app_state = app_state.get_ref();
+    let user_id_params = path.into_inner();
+
+    let get_sessions_service = match get_username_by_id(&app_state.db_pool, user_id_params).await {
+        Ok(username) => get_sessions_service(app_state, &username).await,
+        Err(e) => Err(e)
+    };
+    match get_sessions_service {
+        Ok(sessions) => {
+            let status_code = StatusCode::OK;
+            let success_message = json!({
+                "success": true,
+                "code": status_code.as_u16(),
+                "message": "successfully retrieved all active sessions.",
+                "data": sessions,
+                "length": sessions.len()
+            });
+            HttpResponse::build(status_code).json(success_message)
+        },
+        Err(e) => HttpResponse::from_error(e)
+    }
+}
+
+pub async fn revoke_user_session(
+    auth: JwtAuth,
+    request: HttpRequest,
+    app_state: web::Data<AppState>,
+    path: web::Path<(u32, u32)>
+) -> impl Responder {
+    if let Err(e) = auth.require_admin()
+        .and_then(|_| auth.require_scope(SCOPE_USERS_WRITE))
+        .and_then(|_| auth.require_not_impersonating())
+    {
+        return HttpResponse::from_error(e);
+    }
+    let app_state = app_state.get_ref();
+    let (user_id_params, session_id_params) = path.into_inner();
+    let client = get_client_info(&request);
+
+    let revoke_session_service = match get_username_by_id(&app_state.db_pool, user_id_params).await {
+        Ok(username) => revoke_session_service(app_state, &username, session_id_params).await.map(|result| (username, result)),
+        Err(e) => Err(e)
+    };
+    let audit_entry = AuditEntry {
+        event: "user.session_revoke",
+        actor: Some(&auth.claims.username),
+        target: revoke_session_service.as_ref().ok().map(|(username, _)| username.as_str()),
+        success: revoke_session_service.is_ok(),
+        reason: revoke_session_service.as_ref().err().map(AppError::message),
+        client: &client
+    };
+    record_audit_log(&app_state.db_pool, audit_entry).await;
+    match revoke_session_service {
+        Ok((username, result)) => {
+            let status_code = StatusCode::OK;
+            let success_message = json!({
+                "success": true,
+                "code": status_code.as_u16(),
+                "message": format!("session '{}' of user '{}' has been successfully revoked.", result, username)
+            });
+            HttpResponse::build(status_code).json(success_message)
+        },
+        Err(e) => HttpResponse::from_error(e)
+    }
+}
+
+pub async fn revoke_all_user_sessions(
+    auth: JwtAuth,
+    request: HttpRequest,
+    app_state: web::Data<AppState>,
+    path: web::Path<u32>
+) -> impl Responder {
+    if let Err(e) = auth.require_admin()
+        .and_then(|_| auth.require_scope(SCOPE_USERS_WRITE))
+        .and_then(|_| auth.require_not_impersonating())
+    {
+        return HttpResponse::from_error(e);
+    }
+    let app_state = app_state.get_ref();
+    let user_id_params = path.into_inner();
+    let client = get_client_info(&request);
+
+    let revoke_all_sessions_service = match get_username_by_id(&app_state.db_pool, user_id_params).await {
+        Ok(username) => revoke_all_sessions_service(app_state, &username).await.map(|result| (username, result)),
+        Err(e) => Err(e)
+    };
+    let audit_entry = AuditEntry {
+        event: "user.sessions_revoke_all",
+        actor: Some(&auth.claims.username),
+        target: revoke_all_sessions_service.as_ref().ok().map(|(username, _)| username.as_str()),
+        success: revoke_all_sessions_service.is_ok(),
+        reason: revoke_all_sessions_service.as_ref().err().map(AppError::message),
+        client: &client
+    };
+    record_audit_log(&app_state.db_pool, audit_entry).await;
+    match revoke_all_sessions_service {
+        Ok((username, result)) => {
+            let status_code = StatusCode::OK;
+            let success_message = json!({
+                "success": true,
+                "code": status_code.as_u16(),
+                "message": format!("user '{}' has been signed out everywhere. {} session(s) revoked.", username, result)
+            });
+            HttpResponse::build(status_code).json(success_message)
+        },
+        Err(e) => HttpResponse::from_error(e)
+    }
+}
+
+pub async fn get_me(
+    auth: JwtAuth,
+    app_state: web::Data<AppState>,
+    query: web::Query<UserFieldsQuery>
+) -> impl Responder 
+{
+    if let Err(e) = auth.require_scope(SCOPE_USERS_READ) {
+        return HttpResponse::from_error(e);
+    }
+    let app_state = app_state.get_ref();
+    
+    let get_user_service = match parse_fields(query.fields.as_deref()) {
+        Ok(fields) => match get_user_id_by_username(&app_state.db_pool, &auth.claims.username).await {
+            Ok(id_user) => get_user_service(app_state, id_user, false).await.map(|user| (user, fields)),
+            Err(e) => Err(e)
+        },
+        Err(e) => Err(e)
+    };
+    match get_user_service {
+        Ok((user, fields)) => {
+            let status_code = StatusCode::OK;
+            let success_message = json!({
+                "success": true,
+                "code": status_code.as_u16(),
+                "message": "user data retrieved successfully.",
+                "data": render_user(&user, get_user_view(&auth, &user), fields.as_deref())
+            });
+            HttpResponse::build(status_code)
+                .insert_header((header::ETAG, user_etag(user.version)))
+                .json(success_message)
+        },
+        Err(e) => HttpResponse::from_error(e)
+    }
+}
+
 pub async fn insert_user(
+    request: HttpRequest,
     app_state: web::Data<AppState>,
     payload: web::Json<In<UserPayload>>
 ) -> impl Responder {
     let app_state = app_state.get_ref();
     let payload = payload.into_inner().user;
+    let client = get_client_info(&request);
+    let username = payload.username.clone();
     
     let insert_user_service = insert_user_service(app_state, payload).await;
+    let audit_entry = AuditEntry {
+        event: "user.signup",
+        actor: Some(&username),
+        target: Some(&username),
+        success: insert_user_service.is_ok(),
+        reason: insert_user_service.as_ref().err().map(AppError::message),
+        client: &client
+    };
+    record_audit_log(&app_state.db_pool, audit_entry).await;
 
     match insert_user_service {
         Ok(user) => { 
@@ -97,23 +428,281 @@ pub async fn insert_user(
 }
 
 pub async fn update_user(
-    _: JwtAuth,
+    auth: JwtAuth,
+    request: HttpRequest,
     app_state: web::Data<AppState>,
     path: web::Path<u32>,
     payload: web::Json<In<UserPayload>>
 ) -> impl Responder {
+    if let Err(e) = auth.require_not_impersonating() {
+        return HttpResponse::from_error(e);
+    }
     let app_state = app_state.get_ref();
     let user_id_params = path.into_inner();
     let payload = payload.into_inner().user;
+    let client = get_client_info(&request);
     
-    let update_user_service = update_user_service(app_state, user_id_params, payload).await;
+    let owner_or_admin = require_owner_or_admin(&auth, &app_state.db_pool, user_id_params).await;
+    let update_user_service = match owner_or_admin.and_then(|_| get_if_match(&request, app_state.preconditions.if_match_required)) {
+        Ok(if_match) => update_user_service(app_state, user_id_params, payload, if_match, &auth.claims.username).await,
+        Err(e) => Err(e)
+    };
+    /* * password is only a password change when it differs from the stored one, email only a change request when it is pending */
+    let is_password_change = update_user_service.as_ref().is_ok_and(|updated_user| updated_user.is_password_change);
+    let is_email_change_request = update_user_service.as_ref().is_ok_and(|updated_user| updated_user.pending_email.is_some());
+    let events = [
+        Some("user.update"),
+        is_password_change.then_some("user.password_change"),
+        is_email_change_request.then_some("user.email_change_request")
+    ];
+    for event in events.into_iter().flatten() {
+        let audit_entry = AuditEntry {
+            event,
+            actor: Some(&auth.claims.username),
+            target: update_user_service.as_ref().ok().map(|updated_user| updated_user.username.as_str()),
+            success: update_user_service.is_ok(),
+            reason: update_user_service.as_ref().err().map(AppError::message),
+            client: &client
+        };
+        record_audit_log(&app_state.db_pool, audit_entry).await;
+    }
+    /* * end password is only a password change when it differs from the stored one, email only a change request when it is pending */
     match update_user_service {
-        Ok(user) => {
+        Ok(updated_user) => {
+            let status_code = StatusCode::OK;
+            let success_message = json!({
+                "status": true,
+                "code": status_code.as_u16(),
+                "message": format!("user '{}' has been successfully updated.", updated_user.username),
+                "pending_email": updated_user.pending_email
+            });
+            HttpResponse::build(status_code)
+                .insert_header((header::ETAG, user_etag(updated_user.version)))
+                .json(success_message)
+        },
+        Err(e) => HttpResponse::from_error(e)
+    }
+}
+
+pub async fn patch_user(
+    auth: JwtAuth,
+    request: HttpRequest,
+    app_state: web::Data<AppState>,
+    path: web::Path<u32>,
+    payload: web::Json<UserPatchPayload>
+) -> impl Responder {
+    if let Err(e) = auth.require_not_impersonating() {
+        return HttpResponse::from_error(e);
+    }
+    let app_state = app_state.get_ref();
+    let user_id_params = path.into_inner();
+    let payload = payload.into_inner();
+    let client = get_client_info(&request);
+    
+    let owner_or_admin = require_owner_or_admin(&auth, &app_state.db_pool, user_id_params).await;
+    let patch_user_service = match owner_or_admin.and_then(|_| get_if_match(&request, app_state.preconditions.if_match_required)) {
+        Ok(if_match) => patch_user_service(app_state, user_id_params, payload, if_match, &auth.claims.username).await,
+        Err(e) => Err(e)
+    };
+    /* * password is only a password change when it was stored, email only a change request when it is pending */
+    let is_password_change = patch_user_service.as_ref().is_ok_and(|patched_user| patched_user.is_password_change);
+    let is_email_change_request = patch_user_service.as_ref().is_ok_and(|patched_user| patched_user.pending_email.is_some());
+    let events = [
+        Some("user.update"),
+        is_password_change.then_some("user.password_change"),
+        is_email_change_request.then_some("user.email_change_request")
+    ];
+    for event in events.into_iter().flatten() {
+        let audit_entry = AuditEntry {
+            event,
+            actor: Some(&auth.claims.username),
+            target: patch_user_service.as_ref().ok().map(|patched_user| patched_user.username.as_str()),
+            success: patch_user_service.is_ok(),
+            reason: patch_user_service.as_ref().err().map(AppError::message),
+            client: &client
+        };
+        record_audit_log(&app_state.db_pool, audit_entry).await;
+    }
+    /* * end password is only a password change when it was stored, email only a change request when it is pending */
+    match patch_user_service {
+        Ok(patched_user) => {
+            let status_code = StatusCode::OK;
+            let success_message = json!({
+                "status": true,
+                "code": status_code.as_u16(),
+                "message": format!("user '{}' has been successfully updated.", patched_user.username),
+                "changed_fields": patched_user.changed_fields,
+                "pending_email": patched_user.pending_email
+            });
+            HttpResponse::build(status_code)
+                .insert_header((header::ETAG, user_etag(patched_user.version)))
+                .insert_header(("Accept-Patch", MERGE_PATCH_CONTENT_TYPE))
+                .json(success_message)
+        },
+        Err(e) => HttpResponse::from_error(e)
+    }
+}
+
+pub async fn patch_me(
+    auth: JwtAuth,
+    request: HttpRequest,
+    app_state: web::Data<AppState>,
+    payload: web::Json<UserPatchPayload>
+) -> impl Responder {
+    if let Err(e) = auth.require_profile_write().and_then(|_| auth.require_not_impersonating()) {
+        return HttpResponse::from_error(e);
+    }
+    let app_state = app_state.get_ref();
+    let payload = payload.into_inner();
+    let client = get_client_info(&request);
+    
+    let patch_user_service = match get_if_match(&request, app_state.preconditions.if_match_required) {
+        Ok(if_match) => match get_user_id_by_username(&app_state.db_pool, &auth.claims.username).await {
+            Ok(id_user) => patch_user_service(app_state, id_user, payload, if_match, &auth.claims.username).await,
+            Err(e) => Err(e)
+        },
+        Err(e) => Err(e)
+    };
+    /* * password is only a password change when it was stored, email only a change request when it is pending */
+    let is_password_change = patch_user_service.as_ref().is_ok_and(|patched_user| patched_user.is_password_change);
+    let is_email_change_request = patch_user_service.as_ref().is_ok_and(|patched_user| patched_user.pending_email.is_some());
+    let events = [
+        Some("user.update"),
+        is_password_change.then_some("user.password_change"),
+        is_email_change_request.then_some("user.email_change_request")
+    ];
+    for event in events.into_iter().flatten() {
+        let audit_entry = AuditEntry {
+            event,
+            actor: Some(&auth.claims.username),
+            target: Some(&auth.claims.username),
+            success: patch_user_service.is_ok(),
+            reason: patch_user_service.as_ref().err().map(AppError::message),
+            client: &client
+        };
+        record_audit_log(&app_state.db_pool, audit_entry).await;
+    }
+    /* * end password is only a password change when it was stored, email only a change request when it is pending */
+    match patch_user_service {
+        Ok(patched_user) => {
             let status_code = StatusCode::OK;
             let success_message = json!({
                 "status": true,
                 "code": status_code.as_u16(),
-                "message": format!("user '{}' has been successfully updated.", user)
+                "message": format!("user '{}' has been successfully updated.", patched_user.username),
+                "changed_fields": patched_user.changed_fields,
+                "pending_email": patched_user.pending_email
+            });
+            HttpResponse::build(status_code)
+                .insert_header((header::ETAG, user_etag(patched_user.version)))
+                .insert_header(("Accept-Patch", MERGE_PATCH_CONTENT_TYPE))
+                .json(success_message)
+        },
+        Err(e) => HttpResponse::from_error(e)
+    }
+}
+
+/* * confirm link mailed to new address, only renders confirmation form */
+pub async fn confirm_email_change_page(
+    app_state: web::Data<AppState>,
+    query: web::Query<EmailChangeTokenQuery>
+) -> impl Responder {
+    let token = query.into_inner().token;
+    let action = format!("{}/api/users/email-change/confirm", app_state.public_url.trim_end_matches('/'));
+
+    render_form_page(
+        "confirm your new email address",
+        "this will use this address for your account.",
+        &action,
+        &[("token", &token)],
+        &[],
+        "confirm email address"
+    )
+}
+/* * end confirm link mailed to new address, only renders confirmation form */
+
+pub async fn confirm_email_change(
+    request: HttpRequest,
+    app_state: web::Data<AppState>,
+    payload: web::Form<EmailChangeTokenQuery>
+) -> impl Responder {
+    let app_state = app_state.get_ref();
+    let token = payload.into_inner().token;
+    let client = get_client_info(&request);
+
+    let confirm_email_change_service = confirm_email_change_service(app_state, &token).await;
+    let username = confirm_email_change_service.as_ref().ok().map(|result| result.username.as_str());
+    let audit_entry = AuditEntry {
+        event: "user.email_change_confirm",
+        actor: username,
+        target: username,
+        success: confirm_email_change_service.is_ok(),
+        reason: confirm_email_change_service.as_ref().err().map(AppError::message),
+        client: &client
+    };
+    record_audit_log(&app_state.db_pool, audit_entry).await;
+    match confirm_email_change_service {
+        Ok(result) => {
+            let status_code = StatusCode::OK;
+            let success_message = json!({
+                "success": true,
+                "code": status_code.as_u16(),
+                "message": format!("email of user '{}' has been successfully changed.", result.username),
+                "email": result.email
+            });
+            HttpResponse::build(status_code).json(success_message)
+        },
+        Err(e) => HttpResponse::from_error(e)
+    }
+}
+
+/* * revert link mailed to old address, only renders confirmation form */
+pub async fn revert_email_change_page(
+    app_state: web::Data<AppState>,
+    query: web::Query<EmailChangeTokenQuery>
+) -> impl Responder {
+    let token = query.into_inner().token;
+    let action = format!("{}/api/users/email-change/revert", app_state.public_url.trim_end_matches('/'));
+
+    render_form_page(
+        "keep your email address",
+        "this will keep your current email address and sign out every session of your account.",
+        &action,
+        &[("token", &token)],
+        &[],
+        "keep email address and sign out everywhere"
+    )
+}
+/* * end revert link mailed to old address, only renders confirmation form */
+
+pub async fn revert_email_change(
+    request: HttpRequest,
+    app_state: web::Data<AppState>,
+    payload: web::Form<EmailChangeTokenQuery>
+) -> impl Responder {
+    let app_state = app_state.get_ref();
+    let token = payload.into_inner().token;
+    let client = get_client_info(&request);
+
+    let revert_email_change_service = revert_email_change_service(app_state, &token).await;
+    let username = revert_email_change_service.as_ref().ok().map(|result| result.username.as_str());
+    let audit_entry = AuditEntry {
+        event: "user.email_change_revert",
+        actor: username,
+        target: username,
+        success: revert_email_change_service.is_ok(),
+        reason: revert_email_change_service.as_ref().err().map(AppError::message),
+        client: &client
+    };
+    record_audit_log(&app_state.db_pool, audit_entry).await;
+    match revert_email_change_service {
+        Ok(result) => {
+            let status_code = StatusCode::OK;
+            let success_message = json!({
+                "success": true,
+                "code": status_code.as_u16(),
+                "message": format!("email change of user '{}' has been reverted.", result.username),
+                "email": result.email
             });
             HttpResponse::build(status_code).json(success_message)
         },
@@ -122,14 +711,116 @@ pub async fn update_user(
 }
 
 pub async fn delete_user(
-    _: JwtAuth,
+    auth: JwtAuth,
+    request: HttpRequest,
     app_state: web::Data<AppState>,
     path: web::Path<u32>
 ) -> impl Responder {
+    if let Err(e) = auth.require_not_impersonating() {
+        return HttpResponse::from_error(e);
+    }
     let app_state = app_state.get_ref();
     let user_id_params = path.into_inner();
+    let client = get_client_info(&request);
     
-    let delete_user_service = delete_user_service(app_state, user_id_params).await;
+    let owner_or_admin = require_owner_or_admin(&auth, &app_state.db_pool, user_id_params).await;
+    let delete_user_service = match owner_or_admin.and_then(|_| get_if_match(&request, app_state.preconditions.if_match_required)) {
+        Ok(if_match) => delete_user_service(app_state, user_id_params, if_match, &auth.claims.username).await,
+        Err(e) => Err(e)
+    };
+    let audit_entry = AuditEntry {
+        event: "user.delete",
+        actor: Some(&auth.claims.username),
+        target: delete_user_service.as_deref().ok(),
+        success: delete_user_service.is_ok(),
+        reason: delete_user_service.as_ref().err().map(AppError::message),
+        client: &client
+    };
+    record_audit_log(&app_state.db_pool, audit_entry).await;
+    match delete_user_service {
+        Ok(result) => {
+            let status_code = StatusCode::OK;
+            let success_message = json!({
+                "success": true,
+                "code": status_code.as_u16(),
+                "message": format!("user '{}' has been successfully deleted.", result)
+            });
+            HttpResponse::build(status_code).json(success_message)
+        },
+        Err(e) => HttpResponse::from_error(e)
+    }
+}
+
+pub async fn restore_user(
+    auth: JwtAuth,
+    request: HttpRequest,
+    app_state: web::Data<AppState>,
+    path: web::Path<u32>
+) -> impl Responder {
+    if let Err(e) = auth.require_admin()
+        .and_then(|_| auth.require_scope(SCOPE_USERS_WRITE))
+        .and_then(|_| auth.require_not_impersonating())
+    {
+        return HttpResponse::from_error(e);
+    }
+    let app_state = app_state.get_ref();
+    let user_id_params = path.into_inner();
+    let client = get_client_info(&request);
+    
+    let restore_user_service = match get_if_match(&request, app_state.preconditions.if_match_required) {
+        Ok(if_match) => restore_user_service(app_state, user_id_params, if_match, &auth.claims.username).await,
+        Err(e) => Err(e)
+    };
+    let audit_entry = AuditEntry {
+        event: "user.restore",
+        actor: Some(&auth.claims.username),
+        target: restore_user_service.as_deref().ok(),
+        success: restore_user_service.is_ok(),
+        reason: restore_user_service.as_ref().err().map(AppError::message),
+        client: &client
+    };
+    record_audit_log(&app_state.db_pool, audit_entry).await;
+    match restore_user_service {
+        Ok(result) => {
+            let status_code = StatusCode::OK;
+            let success_message = json!({
+                "success": true,
+                "code": status_code.as_u16(),
+                "message": format!("user '{}' has been successfully restored.", result)
+            });
+            HttpResponse::build(status_code).json(success_message)
+        },
+        Err(e) => HttpResponse::from_error(e)
+    }
+}
+
+pub async fn delete_me(
+    auth: JwtAuth,
+    request: HttpRequest,
+    app_state: web::Data<AppState>
+) -> impl Responder {
+    if let Err(e) = auth.require_profile_write().and_then(|_| auth.require_not_impersonating()) {
+        return HttpResponse::from_error(e);
+    }
+    let app_state = app_state.get_ref();
+    let client = get_client_info(&request);
+    
+    let delete_user_service = match get_if_match(&request, app_state.preconditions.if_match_required) {
+        Ok(if_match) => match get_user_id_by_username(&app_state.db_pool, &auth.claims.username).await {
+            Ok(id_user) => delete_user_service(app_state, id_user, if_match, &auth.claims.username).await,
+            Err(e) => Err(e)
+        },
+        Err(e) => Err(e)
+    };
+    let audit_entry = AuditEntry {
+        event: "user.delete",
+        actor: Some(&auth.claims.username),
+        target: Some(&auth.claims.username),
+        success: delete_user_service.is_ok(),
+        reason: delete_user_service.as_ref().err().map(AppError::message),
+        client: &client
+    };
+    record_audit_log(&app_state.db_pool, audit_entry).await;
     match delete_user_service {
         Ok(result) => {
             let status_code = StatusCode::OK;
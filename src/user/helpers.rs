@@ -1,4 +1,24 @@
-use actix_web::http::StatusCode;
+use actix_web::{
+    HttpRequest,
+    http::{
+        StatusCode,
+        header
+    }
+};
+use chrono::Utc;
+use regex::Regex;
+use sqlx::{
+    MySql,
+    QueryBuilder
+};
+use serde_json::{
+    Value as JsonValue,
+    json
+};
+use base64::{
+    Engine,
+    engine::general_purpose::URL_SAFE_NO_PAD
+};
 use argon2::{
     password_hash::{
         SaltString,  
@@ -13,6 +33,22 @@ use crate::{
     errors::{
         AppError, 
         AppErrorMessage
+    },
+    user::model::{
+        User,
+        UserCursor,
+        UserView,
+        PublicUserResponse,
+        SelfUserResponse,
+        AdminUserResponse,
+        IfMatch,
+        UserChange,
+        USER_FIELDS
+    },
+    auth::{
+        JwtAuth,
+        SCOPE_USERS_WRITE,
+        escape_html
     }
 };
 
@@ -103,4 +139,401 @@ pub async fn get_stored_user(
 
     Ok (query_result)
 }
-/* * end check user with custom query and return bool */
\ No newline at end of file
+/* * end check user with custom query and return bool */
+
+/* * escape LIKE wildcards of user input */
+pub fn escape_like(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+/* * end escape LIKE wildcards of user input */
+
+/* * encode / decode opaque pagination cursor */
+pub fn encode_cursor(cursor: &UserCursor) -> String {
+    URL_SAFE_NO_PAD.encode(serde_json::to_vec(cursor).unwrap_or_default())
+}
+
+pub fn decode_cursor(cursor: &str) -> Result<UserCursor, AppError> {
+    URL_SAFE_NO_PAD
+        .decode(cursor)
+        .ok()
+        .and_then(|cursor| serde_json::from_slice::<UserCursor>(&cursor).ok())
+        .ok_or_else(|| {
+            let app_err_message: AppErrorMessage<Option<bool>> = AppErrorMessage {
+                code: StatusCode::UNPROCESSABLE_ENTITY.as_u16(),
+                message: String::from("invalid pagination cursor."),
+                details: None
+            };
+            AppError::UnprocessableEntity(app_err_message.into())
+        })
+}
+/* * end encode / decode opaque pagination cursor */
+
+/* * build invalid query AppError */
+pub fn invalid_query_error(message: String) -> AppError {
+    let app_err_message: AppErrorMessage<Option<bool>> = AppErrorMessage {
+        code: StatusCode::UNPROCESSABLE_ENTITY.as_u16(),
+        message,
+        details: None
+    };
+    AppError::UnprocessableEntity(app_err_message.into())
+}
+/* * end build invalid query AppError */
+
+/* * html escape field & wrap matched terms in <mark>, None when nothing matched */
+pub fn highlight(text: &str, terms: &Regex) -> Option<String> {
+    let mut highlighted = String::new();
+    let mut last_end = 0;
+    for matched in terms.find_iter(text) {
+        highlighted.push_str(&escape_html(&text[last_end..matched.start()]));
+        highlighted.push_str(&format!("<mark>{}</mark>", escape_html(matched.as_str())));
+        last_end = matched.end();
+    }
+    if last_end == 0 {
+        return None;
+    }
+    highlighted.push_str(&escape_html(&text[last_end..]));
+
+    Some(highlighted)
+}
+/* * end html escape field & wrap matched terms in <mark>, None when nothing matched */
+
+/* * resolve id_user of token owner, tokens only carry username */
+pub async fn get_user_id_by_username(
+    db_pool: &DbPool,
+    username: &str
+) -> Result<u32, AppError>
+{
+    let sql_query = sqlx::query_scalar::<_, i32>("SELECT id_user FROM user WHERE username = ? AND deleted_at IS NULL");
+    let id_user = sql_query
+        .bind(username)
+        .fetch_optional(db_pool)
+        .await?
+        .ok_or_else(|| {
+            let app_err_message: AppErrorMessage<Option<bool>> = AppErrorMessage {
+                code: StatusCode::NOT_FOUND.as_u16(),
+                message: format!("the user '{}' was not found in the database.", username),
+                details: None
+            };
+            AppError::NotFound(app_err_message.into())
+        })?;
+
+    Ok(id_user as u32)
+}
+/* * end resolve id_user of token owner, tokens only carry username */
+
+/* * resolve username of id_user, sessions & passkeys are keyed by username */
+pub async fn get_username_by_id(
+    db_pool: &DbPool,
+    id_user: u32
+) -> Result<String, AppError>
+{
+    let sql_query = sqlx::query_scalar::<_, String>("SELECT username FROM user WHERE id_user = ? AND deleted_at IS NULL");
+    let username = sql_query
+        .bind(id_user)
+        .fetch_optional(db_pool)
+        .await?
+        .ok_or_else(|| {
+            let app_err_message: AppErrorMessage<Option<bool>> = AppErrorMessage {
+                code: StatusCode::NOT_FOUND.as_u16(),
+                message: format!("the user with id '{}' was not found in the database.", id_user),
+                details: None
+            };
+            AppError::NotFound(app_err_message.into())
+        })?;
+
+    Ok(username)
+}
+/* * end resolve username of id_user, sessions & passkeys are keyed by username */
+
+/* * PATCH bodies are JSON merge patches (RFC 7396), the default json extractor already accepts the +json suffix */
+pub const MERGE_PATCH_CONTENT_TYPE: &str = "application/merge-patch+json";
+
+/* * owners write their own account with profile:write, every other account needs admin & users:write */
+pub async fn require_owner_or_admin(
+    auth: &JwtAuth,
+    db_pool: &DbPool,
+    id_user: u32
+) -> Result<(), AppError>
+{
+    if auth.require_admin().is_ok() {
+        return auth.require_scope(SCOPE_USERS_WRITE);
+    }
+    match get_user_id_by_username(db_pool, &auth.claims.username).await? == id_user {
+        true => auth.require_profile_write(),
+        false => auth.require_admin()
+    }
+}
+/* * end owners write their own account with profile:write, every other account needs admin & users:write */
+
+/* * admins see every user in full, owners see themselves, everyone else sees public profile */
+pub fn get_user_view(auth: &JwtAuth, user: &User) -> UserView {
+    if auth.require_admin().is_ok() {
+        return UserView::Admin;
+    }
+    match auth.claims.username == user.username {
+        true => UserView::Owner,
+        false => UserView::Public
+    }
+}
+/* * end admins see every user in full, owners see themselves, everyone else sees public profile */
+
+/* * parse ?fields=id_user,username into known user fields */
+pub fn parse_fields(fields: Option<&str>) -> Result<Option<Vec<String>>, AppError> {
+    let Some(fields) = fields else {
+        return Ok(None);
+    };
+
+    let fields = fields
+        .split(',')
+        .map(str::trim)
+        .filter(|field| !field.is_empty())
+        .map(str::to_string)
+        .collect::<Vec<_>>();
+    if let Some(unknown_field) = fields.iter().find(|field| !USER_FIELDS.contains(&field.as_str())) {
+        return Err(invalid_query_error(format!("unknown field '{}'. supported fields: {}", unknown_field, USER_FIELDS.join(", "))));
+    }
+
+    Ok(Some(fields))
+}
+/* * end parse ?fields=id_user,username into known user fields */
+
+/* * serialize user through its view, then keep only requested fields the view carries */
+pub fn render_user(user: &User, view: UserView, fields: Option<&[String]>) -> JsonValue {
+    let mut rendered = match view {
+        UserView::Public => serde_json::to_value(PublicUserResponse::from(user)),
+        UserView::Owner => serde_json::to_value(SelfUserResponse::from(user)),
+        UserView::Admin => serde_json::to_value(AdminUserResponse::from(user))
+    }
+    .unwrap_or_default();
+
+    if let (Some(fields), Some(object)) = (fields, rendered.as_object_mut()) {
+        object.retain(|field, _| fields.iter().any(|requested| requested == field));
+    }
+
+    rendered
+}
+/* * end serialize user through its view, then keep only requested fields the view carries */
+
+/* * strong ETag of a stored user version, e.g. "3" */
+pub fn user_etag(version: u32) -> String {
+    format!("\"{}\"", version)
+}
+
+/* * parse If-Match header, weak or malformed tags never match a stored version */
+pub fn get_if_match(
+    request: &HttpRequest,
+    if_match_required: bool
+) -> Result<Option<IfMatch>, AppError>
+{
+    let Some(if_match) = request.headers().get(header::IF_MATCH) else {
+        if if_match_required {
+            let app_err_message: AppErrorMessage<Option<bool>> = AppErrorMessage {
+                code: StatusCode::PRECONDITION_REQUIRED.as_u16(),
+                message: String::from("this request requires an If-Match header with the ETag of the user. please get the user first."),
+                details: None
+            };
+            return Err(AppError::PreconditionRequired(app_err_message.into()));
+        }
+        return Ok(None);
+    };
+
+    let if_match = if_match.to_str().unwrap_or_default().trim();
+    if if_match == "*" {
+        return Ok(Some(IfMatch::Any));
+    }
+    let versions = if_match
+        .split(',')
+        .filter_map(|etag| etag.trim().strip_prefix('"')?.strip_suffix('"')?.parse::<u32>().ok())
+        .collect::<Vec<_>>();
+
+    Ok(Some(IfMatch::Versions(versions)))
+}
+/* * end parse If-Match header, weak or malformed tags never match a stored version */
+
+/* * reject modification when If-Match was sent and does not match stored version */
+pub fn check_if_match(
+    if_match: Option<&IfMatch>,
+    user_id_params: u32,
+    stored_version: u32
+) -> Result<(), AppError>
+{
+    match if_match {
+        Some(if_match) if !if_match.matches(stored_version) => Err(precondition_failed_error(user_id_params, Some(stored_version))),
+        _ => Ok(())
+    }
+}
+
+/* * build precondition failed AppError, details carry current ETag when known */
+pub fn precondition_failed_error(user_id_params: u32, stored_version: Option<u32>) -> AppError {
+    let app_err_message: AppErrorMessage<JsonValue> = AppErrorMessage {
+        code: StatusCode::PRECONDITION_FAILED.as_u16(),
+        message: format!("the user with id '{}' has been modified by another request. please get the user again and retry.", user_id_params),
+        details: stored_version.map(|stored_version| json!({ "etag": user_etag(stored_version) }))
+    };
+    AppError::PreconditionFailed(app_err_message.into())
+}
+/* * end reject modification when If-Match was sent and does not match stored version */
+
+/* * write field level changes of a user, secrets never reach user_history */
+const REDACTED_FIELDS: [&str; 1] = ["password"];
+const REDACTED_VALUE: &str = "[redacted]";
+
+pub async fn record_user_changes<'e, E>(
+    executor: E,
+    id_user: u32,
+    actor: Option<&str>,
+    changes: Vec<UserChange>
+) -> Result<(), sqlx::Error>
+where
+    E: sqlx::Executor<'e, Database = MySql>
+{
+    if changes.is_empty() {
+        return Ok(());
+    }
+
+    let time_now = Utc::now().timestamp();
+    let actor = actor.map(|actor| actor.chars().take(25).collect::<String>());
+    let mut query_builder: QueryBuilder<MySql> = QueryBuilder::new("INSERT INTO user_history (
+        id_user,
+        field,
+        old_value,
+        new_value,
+        actor,
+        created_at
+    ) ");
+    query_builder.push_values(changes, |mut row, change| {
+        let is_redacted = REDACTED_FIELDS.contains(&change.field);
+        let stored_value = |value: Option<String>| match is_redacted {
+            true => value.map(|_| String::from(REDACTED_VALUE)),
+            false => value.map(|value| value.chars().take(255).collect::<String>())
+        };
+        row.push_bind(id_user)
+            .push_bind(change.field)
+            .push_bind(stored_value(change.old_value))
+            .push_bind(stored_value(change.new_value))
+            .push_bind(actor.clone())
+            .push_bind(time_now);
+    });
+    let _ = query_builder
+        .build()
+        .execute(executor)
+        .await?;
+
+    Ok(())
+}
+/* * end write field level changes of a user, secrets never reach user_history */
+
+/* * check email is requested by a pending change of another user, expired changes release the address */
+pub async fn is_email_pending(
+    db_pool: &DbPool,
+    email: &str,
+    id_user: Option<u32>
+) -> Result<bool, sqlx::Error>
+{
+    let time_now = Utc::now().timestamp();
+    let sql_query = sqlx::query_scalar::<_, i64>("SELECT EXISTS (
+        SELECT 1 FROM email_changes
+        WHERE new_email = ?
+        AND (? IS NULL OR id_user <> ?)
+        AND confirmed_at IS NULL
+        AND reverted_at IS NULL
+        AND expires_at > ?
+    )");
+    sql_query
+        .bind(email)
+        .bind(id_user)
+        .bind(id_user)
+        .bind(time_now)
+        .fetch_one(db_pool)
+        .await
+        .map(|exists| exists != 0)
+}
+/* * end check email is requested by a pending change of another user, expired changes release the address */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn user() -> User {
+        User {
+            id_user: 7,
+            full_name: Some(String::from("Jane Doe")),
+            email: Some(String::from("jane@gmail.com")),
+            phone_number: Some(String::from("0812345678")),
+            username: String::from("janedoe"),
+            role: String::from("user"),
+            deleted_at: None,
+            version: 3,
+            created_at: Some(1700000000),
+            updated_at: None,
+            last_login_at: None
+        }
+    }
+
+    fn keys(rendered: &JsonValue) -> Vec<&str> {
+        let mut keys = rendered.as_object().unwrap().keys().map(String::as_str).collect::<Vec<_>>();
+        keys.sort();
+        keys
+    }
+
+    #[test]
+    fn parse_fields_trims_and_rejects_unknown_fields() {
+        assert!(parse_fields(None).unwrap().is_none());
+        assert_eq!(parse_fields(Some(" id_user, ,email ")).unwrap(), Some(vec![String::from("id_user"), String::from("email")]));
+        assert!(parse_fields(Some("id_user,password")).is_err());
+        assert!(parse_fields(Some("version")).is_err());
+    }
+
+    #[test]
+    fn render_user_shows_only_fields_of_the_view() {
+        assert_eq!(keys(&render_user(&user(), UserView::Public, None)), vec!["full_name", "id_user", "username"]);
+        assert!(!keys(&render_user(&user(), UserView::Owner, None)).contains(&"deleted_at"));
+        assert!(keys(&render_user(&user(), UserView::Admin, None)).contains(&"deleted_at"));
+        assert!(render_user(&user(), UserView::Admin, None).get("version").is_none());
+    }
+
+    #[test]
+    fn render_user_projects_requested_fields_within_the_view() {
+        let fields = parse_fields(Some("username,email")).unwrap().unwrap();
+
+        assert_eq!(render_user(&user(), UserView::Owner, Some(&fields)), json!({ "username": "janedoe", "email": "jane@gmail.com" }));
+        assert_eq!(render_user(&user(), UserView::Public, Some(&fields)), json!({ "username": "janedoe" }));
+    }
+
+    fn if_match(value: Option<&str>, if_match_required: bool) -> Result<Option<IfMatch>, AppError> {
+        let mut request = actix_web::test::TestRequest::patch();
+        if let Some(value) = value {
+            request = request.insert_header((header::IF_MATCH, value));
+        }
+        get_if_match(&request.to_http_request(), if_match_required)
+    }
+
+    #[test]
+    fn get_if_match_requires_header_only_when_configured() {
+        assert!(if_match(None, false).unwrap().is_none());
+        assert!(matches!(if_match(None, true), Err(AppError::PreconditionRequired(_))));
+    }
+
+    #[test]
+    fn get_if_match_parses_strong_star_weak_and_malformed_tags() {
+        assert!(matches!(if_match(Some("*"), false).unwrap(), Some(IfMatch::Any)));
+        assert!(matches!(if_match(Some(r#""3", "5""#), false).unwrap(), Some(IfMatch::Versions(versions)) if versions == vec![3, 5]));
+        assert!(matches!(if_match(Some(r#"W/"3""#), false).unwrap(), Some(IfMatch::Versions(versions)) if versions.is_empty()));
+        assert!(matches!(if_match(Some(r#"3, "abc", "4"#), false).unwrap(), Some(IfMatch::Versions(versions)) if versions.is_empty()));
+    }
+
+    #[test]
+    fn check_if_match_rejects_other_versions_with_current_etag() {
+        let strong = if_match(Some(&user_etag(3)), false).unwrap();
+        let weak = if_match(Some(r#"W/"3""#), false).unwrap();
+
+        assert!(check_if_match(None, 7, 3).is_ok());
+        assert!(check_if_match(Some(&IfMatch::Any), 7, 3).is_ok());
+        assert!(check_if_match(strong.as_ref(), 7, 3).is_ok());
+        assert!(matches!(check_if_match(strong.as_ref(), 7, 4), Err(AppError::PreconditionFailed(_))));
+        assert!(matches!(check_if_match(weak.as_ref(), 7, 3), Err(AppError::PreconditionFailed(_))));
+    }
+}
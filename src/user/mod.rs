@@ -5,5 +5,15 @@ mod routes;
 mod services;
 
 pub use routes::scoped_user;
+pub use services::purge_users::schedule_deleted_user_purge;
 
-pub use handler::insert_user;
\ No newline at end of file
+pub use handler::insert_user;
+pub use helpers::{
+    hashing_password,
+    record_user_changes
+};
+pub use model::{
+    validate_password,
+    UserChange,
+    RE_PASSWORD
+};
\ No newline at end of file
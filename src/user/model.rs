@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use serde::{
     Serialize,
     Deserialize
@@ -12,17 +13,225 @@ use regex::Regex;
 
 lazy_static! {
     static ref RE_USERNAME: Regex = Regex::new(r"^[0-9a-zA-Z]{2,}$").unwrap();
-    static ref RE_PASSWORD: Regex = Regex::new(r"^.*?[!@#$%^&*()].*$").unwrap();
+    pub static ref RE_PASSWORD: Regex = Regex::new(r"^.*?[!@#$%^&*()].*$").unwrap();
 }
 
-#[derive(Serialize, FromRow)]
+/* * database row without password hash, never serialized, responses use one of the user views below */
+#[derive(FromRow)]
 pub struct User {
     pub id_user: i32,
     pub full_name: Option<String>,
     pub email: Option<String>,
     pub phone_number: Option<String>,
     pub username: String,
-    pub password: String
+    pub role: String,
+    pub deleted_at: Option<i64>,
+    pub version: u32,
+    pub created_at: Option<i64>,
+    pub updated_at: Option<i64>,
+    pub last_login_at: Option<i64>
+}
+
+/* * fields a client may request with ?fields= */
+pub const USER_FIELDS: [&str; 10] = [
+    "id_user", "username", "full_name", "email", "phone_number", "role",
+    "created_at", "updated_at", "last_login_at", "deleted_at"
+];
+
+/* * which view of a user the caller is allowed to see */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum UserView {
+    Public,
+    Owner,
+    Admin
+}
+
+/* * public profile, visible to every authenticated caller */
+#[derive(Serialize)]
+pub struct PublicUserResponse {
+    pub id_user: i32,
+    pub username: String,
+    pub full_name: Option<String>
+}
+
+/* * own profile, contact details & role of token owner */
+#[derive(Serialize)]
+pub struct SelfUserResponse {
+    pub id_user: i32,
+    pub username: String,
+    pub full_name: Option<String>,
+    pub email: Option<String>,
+    pub phone_number: Option<String>,
+    pub role: String,
+    pub created_at: Option<i64>,
+    pub updated_at: Option<i64>,
+    pub last_login_at: Option<i64>
+}
+
+/* * admin view, every non-secret field */
+#[derive(Serialize)]
+pub struct AdminUserResponse {
+    pub id_user: i32,
+    pub username: String,
+    pub full_name: Option<String>,
+    pub email: Option<String>,
+    pub phone_number: Option<String>,
+    pub role: String,
+    pub created_at: Option<i64>,
+    pub updated_at: Option<i64>,
+    pub last_login_at: Option<i64>,
+    pub deleted_at: Option<i64>
+}
+
+impl From<&User> for PublicUserResponse {
+    fn from(user: &User) -> Self {
+        Self {
+            id_user: user.id_user,
+            username: user.username.clone(),
+            full_name: user.full_name.clone()
+        }
+    }
+}
+
+impl From<&User> for SelfUserResponse {
+    fn from(user: &User) -> Self {
+        Self {
+            id_user: user.id_user,
+            username: user.username.clone(),
+            full_name: user.full_name.clone(),
+            email: user.email.clone(),
+            phone_number: user.phone_number.clone(),
+            role: user.role.clone(),
+            created_at: user.created_at,
+            updated_at: user.updated_at,
+            last_login_at: user.last_login_at
+        }
+    }
+}
+
+impl From<&User> for AdminUserResponse {
+    fn from(user: &User) -> Self {
+        Self {
+            id_user: user.id_user,
+            username: user.username.clone(),
+            full_name: user.full_name.clone(),
+            email: user.email.clone(),
+            phone_number: user.phone_number.clone(),
+            role: user.role.clone(),
+            created_at: user.created_at,
+            updated_at: user.updated_at,
+            last_login_at: user.last_login_at,
+            deleted_at: user.deleted_at
+        }
+    }
+}
+/* * end which view of a user the caller is allowed to see */
+
+/* * ?fields= projection of single user responses */
+#[derive(Deserialize)]
+pub struct UserFieldsQuery {
+    pub fields: Option<String>
+}
+
+/* * GET /users query, cursor and offset are mutually exclusive */
+#[derive(Deserialize)]
+pub struct UserListQuery {
+    pub limit: Option<u32>,
+    pub offset: Option<u32>,
+    pub cursor: Option<String>,
+    pub sort: Option<String>,
+    pub order: Option<String>,
+    pub username_prefix: Option<String>,
+    pub email_domain: Option<String>,
+    pub has_phone_number: Option<bool>,
+    pub created_after: Option<i64>,
+    pub created_before: Option<i64>,
+    pub last_login_after: Option<i64>,
+    pub last_login_before: Option<i64>,
+    /* * * admin only, true lists soft deleted users instead of active ones */
+    pub deleted: Option<bool>,
+    pub fields: Option<String>
+}
+
+/* * sort columns of the public profile, every other sort & filter reads private columns */
+pub const PUBLIC_SORT_COLUMNS: [&str; 3] = ["id_user", "username", "full_name"];
+
+impl UserListQuery {
+    /* * * first sort or filter on a column outside the public profile, admin only */
+    pub fn private_parameter(&self) -> Option<&'static str> {
+        let is_private_sort = self.sort
+            .as_deref()
+            .is_some_and(|sort| !PUBLIC_SORT_COLUMNS.contains(&sort));
+        let parameters = [
+            ("sort", is_private_sort),
+            ("email_domain", self.email_domain.is_some()),
+            ("has_phone_number", self.has_phone_number.is_some()),
+            ("created_after", self.created_after.is_some()),
+            ("created_before", self.created_before.is_some()),
+            ("last_login_after", self.last_login_after.is_some()),
+            ("last_login_before", self.last_login_before.is_some())
+        ];
+        parameters
+            .into_iter()
+            .find(|(_, is_used)| *is_used)
+            .map(|(parameter, _)| parameter)
+    }
+}
+/* * end sort columns of the public profile, every other sort & filter reads private columns */
+
+/* * opaque cursor, position of the last returned user in the requested sort order */
+#[derive(Serialize, Deserialize)]
+pub struct UserCursor {
+    pub sort: String,
+    pub order: String,
+    pub value: Option<String>,
+    pub id_user: i32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub offset: Option<u32>
+}
+
+#[derive(Serialize)]
+pub struct Pagination {
+    pub total: i64,
+    pub limit: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub offset: Option<u32>,
+    pub next_cursor: Option<String>,
+    pub has_more: bool
+}
+
+pub struct ServiceOkUserList {
+    pub users: Vec<User>,
+    pub pagination: Pagination
+}
+
+/* * GET /users/search query, same pagination as GET /users */
+#[derive(Deserialize)]
+pub struct UserSearchQuery {
+    pub q: String,
+    pub limit: Option<u32>,
+    pub offset: Option<u32>,
+    pub cursor: Option<String>,
+    pub fields: Option<String>
+}
+
+#[derive(FromRow)]
+pub struct UserSearchRow {
+    #[sqlx(flatten)]
+    pub user: User,
+    pub score: f64
+}
+
+/* * matched user with relevance score, matched terms of each field are wrapped in <mark> */
+pub struct UserSearchResult {
+    pub user: User,
+    pub score: f64,
+    pub highlights: HashMap<String, String>
+}
+
+pub struct ServiceOkUserSearch {
+    pub results: Vec<UserSearchResult>,
+    pub pagination: Pagination
 }
 
 #[derive(Deserialize)]
@@ -105,6 +314,158 @@ pub struct UserPayload {
     pub confirm_password: String
 }
 
+/* * PATCH /users/{id_user} payload (RFC 7396), absent field is unchanged & null clears nullable field */
+#[derive(Deserialize, Validate)]
+pub struct UserPatchPayload {
+    #[serde(
+        default,
+        deserialize_with = "patch_capitalize_option_words"
+    )]
+    pub full_name: Option<Option<String>>,
+
+    #[serde(
+        default,
+        deserialize_with = "patch_to_option_lowercase"
+    )]
+    #[validate(
+        email(
+            message = "invalid email format."
+        ),
+        custom(
+            function = "validate_email_domain",
+            message = "invalid email format or domain. supported domains: gmail.com, icloud.com, yahoo.com, outlook.com"
+        )
+    )]
+    pub email: Option<Option<String>>,
+
+    #[serde(
+        default,
+        deserialize_with = "patch_option"
+    )]
+    #[validate(
+        length(
+            min = 9,
+            max = 14,
+            message = "phone number must be between 9 to 14."
+        ),
+        custom(
+            function = "validate_phone_number",
+            message = "invalid phone number format. must contain only digits."
+        )
+    )]
+    pub phone_number: Option<Option<String>>,
+
+    #[serde(
+        default,
+        deserialize_with = "to_option_lowercase"
+    )]
+    #[validate(
+        length(
+            min = 2,
+            max = 25,
+            message = "username length must be between 2 to 25 characters."
+        ),
+        regex(
+            path = "RE_USERNAME",
+            message = "username must consist of alphanumeric characters and be at least 2 characters long."
+        )
+    )]
+    pub username: Option<String>,
+
+    /* * password is only changed when sent together with confirm_password */
+    #[validate(
+        length(
+            min = 8,
+            message = "password length must be at least 8 characters."
+        ),
+        custom(
+            function = "validate_password",
+            message = "password must contain at least one upper case, lower case, number and 8 characters long. don't use spaces."
+        ),
+        regex(
+            path = "RE_PASSWORD",
+            message = "pasword must be at least contain one special character."
+        )
+    )]
+    pub password: Option<String>,
+
+    #[validate(
+        must_match(
+            other = "password",
+            message = "password do not match. password and confirm_password must be the same."
+        )
+    )]
+    pub confirm_password: Option<String>
+}
+
+pub struct ServiceOkPatchUser {
+    pub username: String,
+    pub is_password_change: bool,
+    pub changed_fields: Vec<&'static str>,
+    pub pending_email: Option<String>,
+    pub version: u32
+}
+
+/* * field level change of a user, recorded in user_history */
+pub struct UserChange {
+    pub field: &'static str,
+    pub old_value: Option<String>,
+    pub new_value: Option<String>
+}
+
+#[derive(Serialize, FromRow)]
+pub struct UserHistory {
+    pub id_user_history: i64,
+    pub field: String,
+    pub old_value: Option<String>,
+    pub new_value: Option<String>,
+    pub actor: Option<String>,
+    pub created_at: i64
+}
+
+#[derive(Deserialize)]
+pub struct UserHistoryQuery {
+    pub field: Option<String>,
+    pub limit: Option<u32>,
+    pub offset: Option<u32>
+}
+/* * end field level change of a user, recorded in user_history */
+
+pub struct ServiceOkUpdateUser {
+    pub username: String,
+    pub is_password_change: bool,
+    pub pending_email: Option<String>,
+    pub version: u32
+}
+
+/* * email change link sent by mail, confirm goes to new address & revert to old address */
+#[derive(Deserialize)]
+pub struct EmailChangeTokenQuery {
+    pub token: String
+}
+
+pub struct ServiceOkEmailChange {
+    pub username: String,
+    pub email: Option<String>
+}
+/* * end email change link sent by mail, confirm goes to new address & revert to old address */
+
+/* * If-Match precondition of modifying requests, "*" matches any stored version */
+pub enum IfMatch {
+    Any,
+    Versions(Vec<u32>)
+}
+
+impl IfMatch {
+    pub fn matches(&self, version: u32) -> bool {
+        match self {
+            Self::Any => true,
+            Self::Versions(versions) => versions.contains(&version)
+        }
+    }
+}
+/* * end If-Match precondition of modifying requests, "*" matches any stored version */
+
 /* * custom validate email domain (gmail.com, icloud.com, yahoo.com, dan outlook.com) */
 fn validate_email_domain(
     email: &str
@@ -147,7 +508,7 @@ fn validate_phone_number(
 }
 /* * custom validate phone number (only receive digits) */
 /* * custom validate password (upper, lower, digit, no whitespace, and min 8 characters) */
-fn validate_password(
+pub fn validate_password(
     password: &str
 ) -> Result<(), ValidationError>
 {
@@ -229,4 +590,69 @@ where D: serde::Deserializer<'de>
         None => Ok(None)
     }
 }
-/* * end custom deserializer function for convert Option<String> to capitalize */
\ No newline at end of file
+/* * end custom deserializer function for convert Option<String> to capitalize */
+/* * custom deserializer functions for merge patch fields, present null becomes Some(None) */
+fn patch_option<'de, D>(
+    deserializer: D
+) -> Result<Option<Option<String>>, D::Error>
+where D: serde::Deserializer<'de>
+{
+    let s: Option<String> = Deserialize::deserialize(deserializer)?;
+
+    Ok(Some(s))
+}
+
+fn patch_to_option_lowercase<'de, D>(
+    deserializer: D
+) -> Result<Option<Option<String>>, D::Error>
+where D: serde::Deserializer<'de>
+{
+    to_option_lowercase(deserializer).map(Some)
+}
+
+fn patch_capitalize_option_words<'de, D>(
+    deserializer: D
+) -> Result<Option<Option<String>>, D::Error>
+where D: serde::Deserializer<'de>
+{
+    capitalize_option_words(deserializer).map(Some)
+}
+/* * end custom deserializer functions for merge patch fields, present null becomes Some(None) */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn patch(body: &str) -> UserPatchPayload {
+        serde_json::from_str(body).unwrap()
+    }
+
+    #[test]
+    fn patch_payload_tells_absent_from_null() {
+        let absent = patch("{}");
+        assert!(absent.full_name.is_none() && absent.email.is_none() && absent.phone_number.is_none());
+
+        let cleared = patch(r#"{"full_name": null, "email": null, "phone_number": null}"#);
+        assert_eq!(cleared.full_name, Some(None));
+        assert_eq!(cleared.email, Some(None));
+        assert_eq!(cleared.phone_number, Some(None));
+    }
+
+    #[test]
+    fn patch_payload_normalizes_present_values() {
+        let payload = patch(r#"{"full_name": "jANE   doe", "email": "Jane@Gmail.com", "phone_number": "0812345678", "username": "JaneDoe"}"#);
+
+        assert_eq!(payload.full_name, Some(Some(String::from("Jane Doe"))));
+        assert_eq!(payload.email, Some(Some(String::from("jane@gmail.com"))));
+        assert_eq!(payload.phone_number, Some(Some(String::from("0812345678"))));
+        assert_eq!(payload.username.as_deref(), Some("janedoe"));
+        assert!(payload.validate().is_ok());
+    }
+
+    #[test]
+    fn patch_payload_validates_only_present_values() {
+        assert!(patch(r#"{"email": null, "phone_number": null}"#).validate().is_ok());
+        assert!(patch(r#"{"email": "not-an-email"}"#).validate().is_err());
+        assert!(patch(r#"{"phone_number": "12ab"}"#).validate().is_err());
+    }
+}
@@ -2,20 +2,60 @@ use actix_web::web;
 
 use crate::user::handler::{
     get_all_users,
+    search_users,
     get_user,
+    get_user_history,
+    get_user_sessions,
+    revoke_user_session,
+    revoke_all_user_sessions,
     update_user,
-    delete_user
+    patch_user,
+    delete_user,
+    restore_user,
+    get_me,
+    patch_me,
+    delete_me,
+    confirm_email_change_page,
+    confirm_email_change,
+    revert_email_change_page,
+    revert_email_change
 };
 
 pub fn scoped_user(cfg: &mut web::ServiceConfig) {
     cfg.service(
         web::scope("/users")
             .route("", web::get().to(get_all_users))
+            .route("/search", web::get().to(search_users))
+            .service(
+                web::resource("/email-change/confirm")
+                    .get(confirm_email_change_page)
+                    .post(confirm_email_change)
+            )
+            .service(
+                web::resource("/email-change/revert")
+                    .get(revert_email_change_page)
+                    .post(revert_email_change)
+            )
+            .service(
+                web::resource("/me")
+                    .get(get_me)
+                    .route(web::patch().to(patch_me))
+                    .delete(delete_me)
+            )
             .service(
                 web::resource("/{id_user}")
                     .get(get_user)
                     .put(update_user)
+                    .route(web::patch().to(patch_user))
                     .delete(delete_user)
             )
+            .route("/{id_user}/restore", web::post().to(restore_user))
+            .route("/{id_user}/history", web::get().to(get_user_history))
+            .service(
+                web::resource("/{id_user}/sessions")
+                    .get(get_user_sessions)
+                    .delete(revoke_all_user_sessions)
+            )
+            .route("/{id_user}/sessions/{id_session}", web::delete().to(revoke_user_session))
     );
 }
\ No newline at end of file
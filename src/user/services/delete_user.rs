@@ -1,58 +1,78 @@
 use actix_web::http::StatusCode;
-use sqlx::Row;
+use chrono::Utc;
 
 use crate::{
     types::AppState, 
     errors::{
         AppError, 
         AppErrorMessage
-    }, 
-    user::helpers::get_stored_user
+    },
+    user::{
+        model::{
+            IfMatch,
+            UserChange
+        },
+        helpers::{
+            check_if_match,
+            record_user_changes,
+            precondition_failed_error
+        }
+    }
 };
 
 pub async fn delete_user_service(
     app_state: &AppState,
-    user_id_params: u32
+    user_id_params: u32,
+    if_match: Option<IfMatch>,
+    actor: &str
 ) -> Result<String, AppError> 
 {
     /* * take db pool from handler */
     let db_pool = &app_state.db_pool;
     /* * end take db pool from handler */
 
-    /* * check & get user from database */
-    let raw_sql_query = format!("SELECT * FROM user WHERE id_user = {}", user_id_params);
-    let message = Some(
-        format!("user with id '{}' not found.", user_id_params)
-    );
-    let get_existing_user = get_stored_user(db_pool, &raw_sql_query, message, None).await?;
-    /* * check & get user from database */
+    /* * check & get active user from database */
+    let sql_query = sqlx::query_as::<_, (String, u32)>("SELECT username, version FROM user WHERE id_user = ? AND deleted_at IS NULL");
+    let (username, stored_version) = sql_query
+        .bind(user_id_params)
+        .fetch_optional(db_pool)
+        .await?
+        .ok_or_else(|| {
+            let app_err_message: AppErrorMessage<Option<bool>> = AppErrorMessage {
+                code: StatusCode::NOT_FOUND.as_u16(),
+                message: format!("user with id '{}' not found.", user_id_params),
+                details: None
+            };
+            AppError::NotFound(app_err_message.into())
+        })?;
+    check_if_match(if_match.as_ref(), user_id_params, stored_version)?;
+    /* * end check & get active user from database */
 
-    /* * destroy user from database */
-    let sql_query = sqlx::query("DELETE FROM user WHERE id_user = ?");
-    let _ = sql_query
+    /* * soft delete user, row is purged after retention period */
+    let deleted_at = Utc::now().timestamp();
+    let mut transaction = db_pool.begin().await?;
+    let sql_query = sqlx::query("UPDATE user SET deleted_at = ?, updated_at = ?, version = version + 1 WHERE id_user = ? AND version = ? AND deleted_at IS NULL");
+    let query_result = sql_query
+        .bind(deleted_at)
+        .bind(deleted_at)
         .bind(user_id_params)
-        .execute(db_pool)
-        .await;
-    /* * end destroy user from database */
-    
-    /* * check is user successfully destroyed */
-    let raw_sql_query = format!("SELECT username FROM user WHERE id_user = {}", user_id_params);
-    let code = Some(StatusCode::INTERNAL_SERVER_ERROR.as_u16());
-    let is_user_still_stored = get_stored_user(db_pool, &raw_sql_query, None, code).await;
-    if let Ok(res) = is_user_still_stored {
-        let app_error_message: AppErrorMessage<Option<bool>> = AppErrorMessage {
-            code: StatusCode::INTERNAL_SERVER_ERROR.as_u16(),
-            message: format!(
-                "an unexpected error occurred while processing your request. user '{}' still exists in the database. please try again.",
-                 res.get::<&str, _>("username")
-            ),
-            details: None 
-        };
-        return Err(AppError::InternalServerError(app_error_message.into()));
+        .bind(stored_version)
+        .execute(&mut *transaction)
+        .await?;
+    if query_result.rows_affected() == 0 {
+        return Err(precondition_failed_error(user_id_params, None));
     }
-    // 
-    /* * end check is user successfully destroyed */
+    let changes = vec![UserChange { field: "deleted_at", old_value: None, new_value: Some(deleted_at.to_string()) }];
+    record_user_changes(&mut *transaction, user_id_params, Some(actor), changes).await?;
+    /* * * deleted user must not keep any signed in session */
+    let sql_query = sqlx::query("DELETE FROM sessions WHERE username = ?");
+    let _ = sql_query
+        .bind(&username)
+        .execute(&mut *transaction)
+        .await?;
+    /* * * end deleted user must not keep any signed in session */
+    transaction.commit().await?;
+    /* * end soft delete user, row is purged after retention period */
 
-    let user_username = get_existing_user.get::<String, _>("username");
-    Ok( user_username )
-}
\ No newline at end of file
+    Ok( username )
+}
@@ -0,0 +1,380 @@
+use actix_web::http::StatusCode;
+use chrono::{
+    Duration as ChronoDuration,
+    Utc
+};
+use sqlx::{
+    MySqlConnection,
+    Row
+};
+
+use crate::{
+    types::AppState,
+    errors::{
+        AppError,
+        AppErrorMessage
+    },
+    mailer::MailMessage,
+    auth::{
+        generate_secret_token,
+        hash_token
+    },
+    user::{
+        model::{
+            UserChange,
+            ServiceOkEmailChange
+        },
+        helpers::record_user_changes
+    }
+};
+
+const EMAIL_CHANGE_CONFIRM_EXPIRED: i64 = 24;
+const EMAIL_CHANGE_REVERT_EXPIRED: i64 = 7;
+
+/* * build email change AppError */
+fn email_change_error(code: StatusCode, message: String) -> AppError {
+    let app_err_message: AppErrorMessage<Option<bool>> = AppErrorMessage {
+        code: code.as_u16(),
+        message,
+        details: None
+    };
+    match code {
+        StatusCode::NOT_FOUND => AppError::NotFound(app_err_message.into()),
+        StatusCode::CONFLICT => AppError::Conflict(app_err_message.into()),
+        _ => AppError::Forbidden(app_err_message.into())
+    }
+}
+/* * end build email change AppError */
+
+/* * check email is used by another account */
+async fn is_email_taken(
+    connection: &mut MySqlConnection,
+    email: &str,
+    id_user: i32
+) -> Result<bool, sqlx::Error>
+{
+    let sql_query = sqlx::query_scalar::<_, i64>("SELECT EXISTS (SELECT 1 FROM user WHERE email = ? AND id_user <> ?)");
+    sql_query
+        .bind(email)
+        .bind(id_user)
+        .fetch_one(connection)
+        .await
+        .map(|exists| exists != 0)
+}
+/* * end check email is used by another account */
+
+/* * switch user email, unique email index turns a concurrent registration into a conflict */
+async fn set_user_email(
+    connection: &mut MySqlConnection,
+    id_user: i32,
+    email: Option<&str>,
+    time_now: i64
+) -> Result<(), AppError>
+{
+    let sql_query = sqlx::query("UPDATE user SET email = ?, updated_at = ?, version = version + 1 WHERE id_user = ?");
+    let _ = sql_query
+        .bind(email)
+        .bind(time_now)
+        .bind(id_user)
+        .execute(connection)
+        .await
+        .map_err(|e| match e.as_database_error().is_some_and(|e| e.is_unique_violation()) {
+            true => email_change_error(
+                StatusCode::CONFLICT,
+                format!("the email '{}' is already associated with an existing account. please use a different email", email.unwrap_or_default())
+            ),
+            false => AppError::from(e)
+        })?;
+
+    Ok(())
+}
+/* * end switch user email, unique email index turns a concurrent registration into a conflict */
+
+/* * pending email change, links are mailed only after the caller committed */
+pub struct EmailChangeLinks {
+    username: String,
+    old_email: Option<String>,
+    new_email: String,
+    confirm_token: String,
+    revert_token: String
+}
+
+/* * store pending email change inside the caller's transaction, pending_email is unique so two accounts cannot claim one address */
+pub async fn store_email_change(
+    connection: &mut MySqlConnection,
+    id_user: u32,
+    username: &str,
+    old_email: Option<String>,
+    new_email: String,
+    actor: &str
+) -> Result<EmailChangeLinks, AppError>
+{
+    let time_now = Utc::now().timestamp();
+    let expires_at = time_now + ChronoDuration::hours(EMAIL_CHANGE_CONFIRM_EXPIRED).num_seconds();
+    let confirm_token = generate_secret_token();
+    let revert_token = generate_secret_token();
+
+    /* * * only the latest requested email change stays pending */
+    let sql_query = sqlx::query("DELETE FROM email_changes WHERE id_user = ? AND confirmed_at IS NULL AND reverted_at IS NULL");
+    let _ = sql_query
+        .bind(id_user)
+        .execute(&mut *connection)
+        .await?;
+    /* * * end only the latest requested email change stays pending */
+    /* * * expired changes release the address, their revert link keeps working */
+    let sql_query = sqlx::query("UPDATE email_changes SET pending_email = NULL WHERE pending_email = ? AND expires_at <= ?");
+    let _ = sql_query
+        .bind(&new_email)
+        .bind(time_now)
+        .execute(&mut *connection)
+        .await?;
+    /* * * end expired changes release the address, their revert link keeps working */
+    let sql_query = sqlx::query("INSERT INTO email_changes (
+        id_user,
+        old_email,
+        new_email,
+        pending_email,
+        confirm_token,
+        revert_token,
+        actor,
+        expires_at,
+        created_at
+    ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?);");
+    let _ = sql_query
+        .bind(id_user)
+        .bind(&old_email)
+        .bind(&new_email)
+        .bind(&new_email)
+        .bind(hash_token(&confirm_token))
+        .bind(hash_token(&revert_token))
+        .bind(actor)
+        .bind(expires_at)
+        .bind(time_now)
+        .execute(&mut *connection)
+        .await
+        .map_err(|e| match e.as_database_error().is_some_and(|e| e.is_unique_violation()) {
+            true => email_change_error(
+                StatusCode::CONFLICT,
+                format!("the email '{}' is already associated with an existing account. please use a different email", new_email)
+            ),
+            false => AppError::from(e)
+        })?;
+
+    Ok( EmailChangeLinks { username: username.to_string(), old_email, new_email, confirm_token, revert_token } )
+}
+/* * end store pending email change inside the caller's transaction, pending_email is unique so two accounts cannot claim one address */
+
+/* * mail confirm link to new address & revert link to old address, change is already stored so mail failure only gets logged */
+pub async fn send_email_change_links(
+    app_state: &AppState,
+    links: EmailChangeLinks
+)
+{
+    /* * proof of ownership of new address */
+    let public_url = app_state.public_url.trim_end_matches('/');
+    let message = MailMessage {
+        to: links.new_email.clone(),
+        subject: String::from("confirm your new email address"),
+        body: format!(
+            "hi {},\n\nplease open the link below within {} hours to use this address for your account:\n{}/api/users/email-change/confirm?token={}\n\nif you did not request this change, you can ignore this email.\n",
+            links.username,
+            EMAIL_CHANGE_CONFIRM_EXPIRED,
+            public_url,
+            links.confirm_token
+        )
+    };
+    if let Err(e) = app_state.mailer.send(message).await {
+        log::error!("failed to send email change confirm link to user '{}': {}", &links.username, e);
+    }
+    /* * end proof of ownership of new address */
+
+    /* * notify old address, revert link also works after confirmation */
+    if let Some(old_email) = links.old_email {
+        let message = MailMessage {
+            to: old_email,
+            subject: String::from("your email address is being changed"),
+            body: format!(
+                "hi {},\n\na change of your account email address to {} has been requested.\n\nif this wasn't you, open the link below within {} days to keep this address and sign out every session:\n{}/api/users/email-change/revert?token={}\n",
+                links.username,
+                links.new_email,
+                EMAIL_CHANGE_REVERT_EXPIRED,
+                public_url,
+                links.revert_token
+            )
+        };
+        if let Err(e) = app_state.mailer.send(message).await {
+            log::error!("failed to send email change revert link to user '{}': {}", &links.username, e);
+        }
+    }
+    /* * end notify old address, revert link also works after confirmation */
+}
+/* * end mail confirm link to new address & revert link to old address, change is already stored so mail failure only gets logged */
+/* * end pending email change, links are mailed only after the caller committed */
+
+pub async fn confirm_email_change_service(
+    app_state: &AppState,
+    token: &str
+) -> Result<ServiceOkEmailChange, AppError>
+{
+    /* * take db pool from handler */
+    let db_pool = &app_state.db_pool;
+    let time_now = Utc::now().timestamp();
+    /* * end take db pool from handler */
+
+    /* * check confirm token is exists from db, row stays locked against a concurrent revert */
+    let mut transaction = db_pool.begin().await?;
+    let sql_query = sqlx::query("SELECT 
+        email_changes.id_email_change,
+        email_changes.id_user,
+        email_changes.old_email,
+        email_changes.new_email,
+        email_changes.actor,
+        email_changes.expires_at,
+        user.username
+        FROM email_changes INNER JOIN user ON user.id_user = email_changes.id_user
+        WHERE email_changes.confirm_token = ?
+        AND email_changes.confirmed_at IS NULL
+        AND email_changes.reverted_at IS NULL
+        AND user.deleted_at IS NULL
+        FOR UPDATE
+    ");
+    let query_result = sql_query
+        .bind(hash_token(token))
+        .fetch_optional(&mut *transaction)
+        .await?
+        .ok_or_else(|| email_change_error(StatusCode::NOT_FOUND, String::from("this link is invalid or has already been used.")))?;
+    let stored_id_email_change = query_result.get::<i64, _>("id_email_change");
+    let stored_id_user = query_result.get::<i32, _>("id_user");
+    let stored_old_email = query_result.get::<Option<String>, _>("old_email");
+    let stored_new_email = query_result.get::<String, _>("new_email");
+    let stored_actor = query_result.get::<Option<String>, _>("actor");
+    let stored_expires_at = query_result.get::<i64, _>("expires_at");
+    let stored_username = query_result.get::<String, _>("username");
+    if time_now >= stored_expires_at {
+        return Err(email_change_error(StatusCode::FORBIDDEN, String::from("this link has been expired. please request the email change again.")));
+    }
+    /* * end check confirm token is exists from db, row stays locked against a concurrent revert */
+
+    /* * new address may have been registered while change was pending */
+    if is_email_taken(&mut transaction, &stored_new_email, stored_id_user).await? {
+        return Err(email_change_error(
+            StatusCode::CONFLICT,
+            format!("the email '{}' is already associated with an existing account. please use a different email", stored_new_email)
+        ));
+    }
+    /* * end new address may have been registered while change was pending */
+
+    /* * switch user email & record history, change is confirmed only once and never after a revert */
+    let sql_query = sqlx::query("UPDATE email_changes SET confirmed_at = ?, pending_email = NULL WHERE id_email_change = ? AND confirmed_at IS NULL AND reverted_at IS NULL");
+    let query_result = sql_query
+        .bind(time_now)
+        .bind(stored_id_email_change)
+        .execute(&mut *transaction)
+        .await?;
+    if query_result.rows_affected() != 1 {
+        return Err(email_change_error(StatusCode::NOT_FOUND, String::from("this link is invalid or has already been used.")));
+    }
+    set_user_email(&mut transaction, stored_id_user, Some(&stored_new_email), time_now).await?;
+    let changes = vec![UserChange { field: "email", old_value: stored_old_email, new_value: Some(stored_new_email.clone()) }];
+    record_user_changes(&mut *transaction, stored_id_user as u32, stored_actor.as_deref(), changes).await?;
+    transaction.commit().await?;
+    /* * end switch user email & record history, change is confirmed only once and never after a revert */
+
+    Ok( ServiceOkEmailChange { username: stored_username, email: Some(stored_new_email) } )
+}
+
+/* * "this wasn't me": cancel pending change or restore old address, every session is signed out either way */
+pub async fn revert_email_change_service(
+    app_state: &AppState,
+    token: &str
+) -> Result<ServiceOkEmailChange, AppError>
+{
+    /* * take db pool from handler */
+    let db_pool = &app_state.db_pool;
+    let time_now = Utc::now().timestamp();
+    /* * end take db pool from handler */
+
+    /* * check revert token is exists from db, row stays locked against a concurrent confirm */
+    let mut transaction = db_pool.begin().await?;
+    let sql_query = sqlx::query("SELECT 
+        email_changes.id_email_change,
+        email_changes.id_user,
+        email_changes.old_email,
+        email_changes.new_email,
+        email_changes.confirmed_at,
+        email_changes.created_at,
+        user.username,
+        user.email
+        FROM email_changes INNER JOIN user ON user.id_user = email_changes.id_user
+        WHERE email_changes.revert_token = ?
+        AND email_changes.reverted_at IS NULL
+        AND user.deleted_at IS NULL
+        FOR UPDATE
+    ");
+    let query_result = sql_query
+        .bind(hash_token(token))
+        .fetch_optional(&mut *transaction)
+        .await?
+        .ok_or_else(|| email_change_error(StatusCode::NOT_FOUND, String::from("this link is invalid or has already been used.")))?;
+    let stored_id_email_change = query_result.get::<i64, _>("id_email_change");
+    let stored_id_user = query_result.get::<i32, _>("id_user");
+    let stored_old_email = query_result.get::<Option<String>, _>("old_email");
+    let stored_new_email = query_result.get::<String, _>("new_email");
+    let stored_confirmed_at = query_result.get::<Option<i64>, _>("confirmed_at");
+    let stored_created_at = query_result.get::<i64, _>("created_at");
+    let stored_username = query_result.get::<String, _>("username");
+    let stored_email = query_result.get::<Option<String>, _>("email");
+    if time_now >= stored_created_at + ChronoDuration::days(EMAIL_CHANGE_REVERT_EXPIRED).num_seconds() {
+        return Err(email_change_error(StatusCode::FORBIDDEN, String::from("this link has been expired. please contact support to secure your account.")));
+    }
+    /* * end check revert token is exists from db, row stays locked against a concurrent confirm */
+
+    let sql_query = sqlx::query("UPDATE email_changes SET reverted_at = ?, pending_email = NULL WHERE id_email_change = ? AND reverted_at IS NULL");
+    let query_result = sql_query
+        .bind(time_now)
+        .bind(stored_id_email_change)
+        .execute(&mut *transaction)
+        .await?;
+    if query_result.rows_affected() != 1 {
+        return Err(email_change_error(StatusCode::NOT_FOUND, String::from("this link is invalid or has already been used.")));
+    }
+
+    /* * confirmed change is rolled back only while new address is still in use */
+    let is_confirmed = stored_confirmed_at.is_some();
+    if is_confirmed {
+        if stored_email.as_deref() != Some(stored_new_email.as_str()) {
+            return Err(email_change_error(
+                StatusCode::CONFLICT,
+                String::from("the email address has been changed again since. please contact support to secure your account.")
+            ));
+        }
+        if let Some(old_email) = &stored_old_email {
+            if is_email_taken(&mut transaction, old_email, stored_id_user).await? {
+                return Err(email_change_error(
+                    StatusCode::CONFLICT,
+                    format!("the email '{}' has been taken by another account. please contact support to secure your account.", old_email)
+                ));
+            }
+        }
+
+        set_user_email(&mut transaction, stored_id_user, stored_old_email.as_deref(), time_now).await?;
+        let changes = vec![UserChange { field: "email", old_value: Some(stored_new_email), new_value: stored_old_email.clone() }];
+        record_user_changes(&mut *transaction, stored_id_user as u32, Some(&stored_username), changes).await?;
+    }
+    /* * end confirmed change is rolled back only while new address is still in use */
+    /* * whoever requested or confirmed the change may still hold a session */
+    let sql_query = sqlx::query("DELETE FROM sessions WHERE username = ?");
+    let _ = sql_query
+        .bind(&stored_username)
+        .execute(&mut *transaction)
+        .await?;
+    /* * end whoever requested or confirmed the change may still hold a session */
+    transaction.commit().await?;
+
+    let email = match is_confirmed {
+        true => stored_old_email,
+        false => stored_email
+    };
+    Ok( ServiceOkEmailChange { username: stored_username, email } )
+}
+/* * end "this wasn't me": cancel pending change or restore old address, every session is signed out either way */
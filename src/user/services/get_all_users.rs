@@ -1,21 +1,228 @@
+use sqlx::{
+    MySql,
+    QueryBuilder
+};
+
 use crate::{
-    types::AppState, 
-    errors::AppError, 
-    user::model::User, 
+    types::AppState,
+    errors::AppError,
+    user::{
+        model::{
+            User,
+            UserCursor,
+            UserListQuery,
+            Pagination,
+            ServiceOkUserList
+        },
+        helpers::{
+            escape_like,
+            encode_cursor,
+            decode_cursor,
+            invalid_query_error
+        }
+    }
 };
 
+const DEFAULT_LIMIT: u32 = 50;
+const MAX_LIMIT: u32 = 200;
+const SORTABLE_COLUMNS: [&str; 7] = ["id_user", "username", "full_name", "email", "created_at", "updated_at", "last_login_at"];
+const TIMESTAMP_COLUMNS: [&str; 3] = ["created_at", "updated_at", "last_login_at"];
+
+/* * soft delete, username prefix, email domain, phone number & lifecycle timestamp filters */
+fn push_user_filters(query_builder: &mut QueryBuilder<MySql>, query: &UserListQuery) {
+    match query.deleted {
+        Some(true) => { query_builder.push(" AND deleted_at IS NOT NULL"); },
+        _ => { query_builder.push(" AND deleted_at IS NULL"); }
+    }
+    if let Some(username_prefix) = &query.username_prefix {
+        query_builder.push(" AND username LIKE ").push_bind(format!("{}%", escape_like(&username_prefix.to_lowercase())));
+    }
+    if let Some(email_domain) = &query.email_domain {
+        query_builder.push(" AND email LIKE ").push_bind(format!("%@{}", escape_like(&email_domain.to_lowercase())));
+    }
+    match query.has_phone_number {
+        Some(true) => { query_builder.push(" AND phone_number IS NOT NULL"); },
+        Some(false) => { query_builder.push(" AND phone_number IS NULL"); },
+        None => ()
+    }
+    /* * * timestamp ranges are inclusive unix seconds, users without timestamp never match */
+    let timestamp_filters = [
+        ("created_at >= ", query.created_after),
+        ("created_at <= ", query.created_before),
+        ("last_login_at >= ", query.last_login_after),
+        ("last_login_at <= ", query.last_login_before)
+    ];
+    for (condition, timestamp) in timestamp_filters {
+        if let Some(timestamp) = timestamp {
+            query_builder.push(" AND ").push(condition).push_bind(timestamp);
+        }
+    }
+    /* * * end timestamp ranges are inclusive unix seconds, users without timestamp never match */
+}
+/* * end soft delete, username prefix, email domain, phone number & lifecycle timestamp filters */
+
+/* * sort value of user, nullable text columns sort as empty string & timestamps as 0 */
+fn get_sort_value(user: &User, sort: &str) -> Option<String> {
+    match sort {
+        "username" => Some(user.username.clone()),
+        "full_name" => Some(user.full_name.clone().unwrap_or_default()),
+        "email" => Some(user.email.clone().unwrap_or_default()),
+        "created_at" => Some(user.created_at.unwrap_or_default().to_string()),
+        "updated_at" => Some(user.updated_at.unwrap_or_default().to_string()),
+        "last_login_at" => Some(user.last_login_at.unwrap_or_default().to_string()),
+        _ => None
+    }
+}
+/* * end sort value of user, nullable text columns sort as empty string & timestamps as 0 */
+
+/* * decode cursor, it only continues the sort & order it was issued for */
+fn decode_list_cursor(cursor: Option<&str>, sort: &str, order: &str) -> Result<Option<UserCursor>, AppError> {
+    let cursor = cursor.map(decode_cursor).transpose()?;
+    if cursor.as_ref().is_some_and(|cursor| cursor.sort != sort || cursor.order != order) {
+        return Err(invalid_query_error(String::from("cursor was issued for a different sort or order.")));
+    }
+
+    Ok(cursor)
+}
+/* * end decode cursor, it only continues the sort & order it was issued for */
+
 pub async fn get_all_users_service(
-    app_state: &AppState
-) -> Result<Vec<User>, AppError> 
+    app_state: &AppState,
+    query: UserListQuery
+) -> Result<ServiceOkUserList, AppError>
 {
     /* * take db pool from handler */
     let db_pool = &app_state.db_pool;
     /* * end take db pool from handler */
 
-    /* * sql query retrieved all users */
-    let sql_query = sqlx::query_as::<_, User>("SELECT * FROM user");
-    let query_result = sql_query.fetch_all(db_pool).await?;
-    /* * end sql query retrieved all users */
+    /* * validating pagination & sort query */
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+    let sort = query.sort.clone().unwrap_or(String::from("id_user"));
+    if !SORTABLE_COLUMNS.contains(&sort.as_str()) {
+        return Err(invalid_query_error(format!("cannot sort by '{}'. sortable columns: {}", sort, SORTABLE_COLUMNS.join(", "))));
+    }
+    let order = query.order.clone().unwrap_or(String::from("asc")).to_lowercase();
+    if order != "asc" && order != "desc" {
+        return Err(invalid_query_error(format!("invalid order '{}'. order must be asc or desc.", order)));
+    }
+    if query.cursor.is_some() && query.offset.is_some() {
+        return Err(invalid_query_error(String::from("cursor and offset cannot be used together.")));
+    }
+    let cursor = decode_list_cursor(query.cursor.as_deref(), &sort, &order)?;
+    /* * end validating pagination & sort query */
+
+    /* * count users matching filters */
+    let mut count_builder: QueryBuilder<MySql> = QueryBuilder::new("SELECT COUNT(*) FROM user WHERE 1 = 1");
+    push_user_filters(&mut count_builder, &query);
+    let total: i64 = count_builder
+        .build_query_scalar()
+        .fetch_one(db_pool)
+        .await?;
+    /* * end count users matching filters */
+
+    /* * build filtered & sorted sql query, cursor continues after the last returned user */
+    let sort_expression = match sort.as_str() {
+        "id_user" => String::from("id_user"),
+        column if TIMESTAMP_COLUMNS.contains(&column) => format!("COALESCE({}, 0)", column),
+        column => format!("COALESCE({}, '')", column)
+    };
+    let (comparison, direction) = match order.as_str() {
+        "desc" => ("<", "DESC"),
+        _ => (">", "ASC")
+    };
+    let mut query_builder: QueryBuilder<MySql> = QueryBuilder::new("SELECT * FROM user WHERE 1 = 1");
+    push_user_filters(&mut query_builder, &query);
+    if let Some(cursor) = cursor {
+        match cursor.value {
+            Some(value) => {
+                query_builder
+                    .push(format!(" AND ({} {} ", sort_expression, comparison)).push_bind(value.clone())
+                    .push(format!(" OR ({} = ", sort_expression)).push_bind(value)
+                    .push(format!(" AND id_user {} ", comparison)).push_bind(cursor.id_user)
+                    .push("))");
+            },
+            None => {
+                query_builder.push(format!(" AND id_user {} ", comparison)).push_bind(cursor.id_user);
+            }
+        }
+    }
+    query_builder.push(format!(" ORDER BY {} {}", sort_expression, direction));
+    if sort != "id_user" {
+        query_builder.push(format!(", id_user {}", direction));
+    }
+    /* * * fetch one extra row to know whether there is a next page */
+    query_builder.push(" LIMIT ").push_bind(limit + 1);
+    if let Some(offset) = query.offset {
+        query_builder.push(" OFFSET ").push_bind(offset);
+    }
+    /* * * end fetch one extra row to know whether there is a next page */
+    /* * end build filtered & sorted sql query, cursor continues after the last returned user */
+
+    /* * sql query retrieved filtered users */
+    let mut users = query_builder
+        .build_query_as::<User>()
+        .fetch_all(db_pool)
+        .await?;
+    /* * end sql query retrieved filtered users */
+
+    /* * pagination metadata */
+    let has_more = users.len() > limit as usize;
+    users.truncate(limit as usize);
+    let next_cursor = users
+        .last()
+        .filter(|_| has_more)
+        .map(|user| encode_cursor(&UserCursor {
+            sort: sort.clone(),
+            order: order.clone(),
+            value: get_sort_value(user, &sort),
+            id_user: user.id_user,
+            offset: None
+        }));
+    let pagination = Pagination {
+        total,
+        limit,
+        offset: query.offset,
+        next_cursor,
+        has_more
+    };
+    /* * end pagination metadata */
+
+    Ok( ServiceOkUserList { users, pagination } )
+}
+
+#[cfg(test)]
+mod tests {
+    use base64::{
+        Engine,
+        engine::general_purpose::URL_SAFE_NO_PAD
+    };
+
+    use super::*;
+
+    fn cursor(sort: &str, order: &str) -> String {
+        encode_cursor(&UserCursor {
+            sort: sort.to_string(),
+            order: order.to_string(),
+            value: Some(String::from("jane")),
+            id_user: 7,
+            offset: None
+        })
+    }
+
+    #[test]
+    fn cursor_round_trips_for_its_sort_and_order() {
+        let decoded = decode_list_cursor(Some(&cursor("username", "desc")), "username", "desc").unwrap().unwrap();
+
+        assert_eq!((decoded.sort.as_str(), decoded.order.as_str()), ("username", "desc"));
+        assert_eq!((decoded.value.as_deref(), decoded.id_user, decoded.offset), (Some("jane"), 7, None));
+        assert!(decode_list_cursor(None, "username", "desc").unwrap().is_none());
+    }
 
-    Ok(query_result)
-}
\ No newline at end of file
+    #[test]
+    fn cursor_rejects_other_sort_order_and_garbage() {
+        assert!(decode_list_cursor(Some(&cursor("username", "desc")), "email", "desc").is_err());
+        assert!(decode_list_cursor(Some(&cursor("username", "desc")), "username", "asc").is_err());
+        assert!(decode_list_cursor(Some("not a cursor"), "username", "desc").is_err());
+        assert!(decode_list_cursor(Some(&URL_SAFE_NO_PAD.encode(b"{}")), "username", "desc").is_err());
+    }
+}
@@ -11,17 +11,19 @@ use crate::{
 
 pub async fn get_user_service(
     app_state: &AppState,
-    user_id_params: u32
+    user_id_params: u32,
+    include_deleted: bool
 ) -> Result<User, AppError> 
 {
     /* * take db pool from handler */
     let db_pool = &app_state.db_pool;
     /* * end take db pool from handler */
 
-    /* * sql query get one user */
-    let sql_query = sqlx::query_as::<_,User>("SELECT * FROM user WHERE id_user = ?");
+    /* * sql query get one user, soft deleted users are only visible when asked for */
+    let sql_query = sqlx::query_as::<_,User>("SELECT * FROM user WHERE id_user = ? AND (deleted_at IS NULL OR ?)");
     let query_result = sql_query
         .bind(user_id_params)
+        .bind(include_deleted)
         .fetch_one(db_pool)
         .await
         .map_err(|e| {
@@ -32,7 +34,7 @@ pub async fn get_user_service(
             };
             AppError::NotFound(app_error_message.into())
         })?;
-    /* * end sql query get one user */
+    /* * end sql query get one user, soft deleted users are only visible when asked for */
 
     Ok(query_result)
 }
\ No newline at end of file
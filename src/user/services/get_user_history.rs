@@ -0,0 +1,71 @@
+use actix_web::http::StatusCode;
+use sqlx::{
+    MySql,
+    QueryBuilder
+};
+
+use crate::{
+    types::AppState,
+    errors::{
+        AppError,
+        AppErrorMessage
+    },
+    user::model::{
+        UserHistory,
+        UserHistoryQuery
+    }
+};
+
+const DEFAULT_LIMIT: u32 = 50;
+const MAX_LIMIT: u32 = 200;
+
+pub async fn get_user_history_service(
+    app_state: &AppState,
+    user_id_params: u32,
+    query: UserHistoryQuery
+) -> Result<Vec<UserHistory>, AppError>
+{
+    /* * take db pool from handler */
+    let db_pool = &app_state.db_pool;
+    /* * end take db pool from handler */
+
+    /* * check user exists, soft deleted users keep their history until purged */
+    let sql_query = sqlx::query_scalar::<_, i64>("SELECT EXISTS (SELECT 1 FROM user WHERE id_user = ?)");
+    let is_user_exists = sql_query
+        .bind(user_id_params)
+        .fetch_one(db_pool)
+        .await? != 0;
+    if !is_user_exists {
+        let app_err_message: AppErrorMessage<Option<bool>> = AppErrorMessage {
+            code: StatusCode::NOT_FOUND.as_u16(),
+            message: format!("user with id '{}' not found.", user_id_params),
+            details: None
+        };
+        return Err(AppError::NotFound(app_err_message.into()));
+    }
+    /* * end check user exists, soft deleted users keep their history until purged */
+
+    /* * build filtered sql query, newest change first */
+    let mut query_builder: QueryBuilder<MySql> = QueryBuilder::new("SELECT * FROM user_history WHERE id_user = ");
+    query_builder.push_bind(user_id_params);
+    if let Some(field) = query.field {
+        query_builder.push(" AND field = ").push_bind(field);
+    }
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+    let offset = query.offset.unwrap_or(0);
+    query_builder
+        .push(" ORDER BY id_user_history DESC LIMIT ")
+        .push_bind(limit)
+        .push(" OFFSET ")
+        .push_bind(offset);
+    /* * end build filtered sql query, newest change first */
+
+    /* * sql query retrieved user history */
+    let query_result = query_builder
+        .build_query_as::<UserHistory>()
+        .fetch_all(db_pool)
+        .await?;
+    /* * end sql query retrieved user history */
+
+    Ok(query_result)
+}
@@ -1,4 +1,5 @@
 use actix_web::http::StatusCode;
+use chrono::Utc;
 use sqlx::Row;
 use validator::Validate;
 
@@ -8,6 +9,7 @@ use crate::{
         model::UserPayload,
         helpers::{
             check_user_insert_availability,
+            is_email_pending,
             hashing_password, get_stored_user, 
         }
     },
@@ -37,6 +39,12 @@ pub async fn insert_user_service(
         &payload.email,
         &payload.phone_number
     ).await?;
+    /* * * address requested by a pending email change is reserved until it expires */
+    let email_exists = match &payload.email {
+        Some(email) if !email_exists => is_email_pending(db_pool, email, None).await?,
+        _ => email_exists
+    };
+    /* * * end address requested by a pending email change is reserved until it expires */
     if username_exists {
         let app_err_message: AppErrorMessage<Option<bool>> = AppErrorMessage {
             code: StatusCode::CONFLICT.as_u16(),
@@ -75,30 +83,39 @@ pub async fn insert_user_service(
     /* * end hashing user password */
 
     /* * storing user to user table */
+    let time_now = Utc::now().timestamp();
     let sql_query = sqlx::query("INSERT INTO user (
         username, 
         password, 
         full_name, 
         email, 
-        phone_number
-    ) VALUES (?, ?, ?, ?, ?);");
+        phone_number,
+        created_at,
+        updated_at
+    ) VALUES (?, ?, ?, ?, ?, ?, ?);");
     let _ = sql_query
         .bind(&payload.username)
         .bind(&hashed_password)
         .bind(&payload.full_name)
         .bind(&payload.email)
         .bind(&payload.phone_number)
+        .bind(time_now)
+        .bind(time_now)
         .execute(db_pool)
         .await?;
     /* * end storing user to user table */
     /* * storing user to credential table */
     let sql_query = sqlx::query("INSERT INTO credentials (
         username, 
-        password
-    ) VALUES (?, ?);");
+        password,
+        created_at,
+        updated_at
+    ) VALUES (?, ?, ?, ?);");
     let _ = sql_query
         .bind(&payload.username)
         .bind(&hashed_password)
+        .bind(time_now)
+        .bind(time_now)
         .execute(db_pool)
         .await?;
     /* * end storing user to credential table */
@@ -1,5 +1,11 @@
 pub mod get_all_users;
 pub mod get_user;
+pub mod get_user_history;
+pub mod search_users;
 pub mod insert_user;
+pub mod patch_user;
 pub mod update_user;
-pub mod delete_user;
\ No newline at end of file
+pub mod delete_user;
+pub mod email_change;
+pub mod restore_user;
+pub mod purge_users;
\ No newline at end of file
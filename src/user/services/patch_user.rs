@@ -0,0 +1,204 @@
+use actix_web::http::StatusCode;
+use chrono::Utc;
+use validator::Validate;
+use sqlx::{
+    MySql,
+    QueryBuilder
+};
+
+use crate::{
+    types::AppState,
+    user::{
+        model::{
+            User,
+            UserPatchPayload,
+            ServiceOkPatchUser,
+            UserChange,
+            IfMatch
+        },
+        services::email_change::{
+            store_email_change,
+            send_email_change_links
+        },
+        helpers::{
+            hashing_password,
+            record_user_changes,
+            is_email_pending,
+            check_if_match,
+            precondition_failed_error
+        }
+    },
+    errors::{
+        AppError,
+        AppErrorMessage
+    },
+};
+
+/* * build conflict AppError */
+fn conflict_error(message: String) -> AppError {
+    let app_err_message: AppErrorMessage<Option<bool>> = AppErrorMessage {
+        code: StatusCode::CONFLICT.as_u16(),
+        message,
+        details: None
+    };
+    AppError::Conflict(app_err_message.into())
+}
+/* * end build conflict AppError */
+
+pub async fn patch_user_service(
+    app_state: &AppState,
+    user_id_params: u32,
+    payload: UserPatchPayload,
+    if_match: Option<IfMatch>,
+    actor: &str
+) -> Result<ServiceOkPatchUser, AppError>
+{
+    /* * validating provided fields only */
+    payload.validate()?;
+    /* * end validating provided fields only */
+
+    /* * take db pool from handler */
+    let db_pool = &app_state.db_pool;
+    /* * end take db pool from handler */
+
+    /* * checking and get stored user data */
+    let stored_user = sqlx::query_as::<_, User>("SELECT * FROM user WHERE id_user = ? AND deleted_at IS NULL")
+        .bind(user_id_params)
+        .fetch_optional(db_pool)
+        .await?
+        .ok_or_else(|| {
+            let app_err_message: AppErrorMessage<Option<bool>> = AppErrorMessage {
+                code: StatusCode::NOT_FOUND.as_u16(),
+                message: format!("the user with id '{}' was not found in the database.", user_id_params),
+                details: None
+            };
+            AppError::NotFound(app_err_message.into())
+        })?;
+    check_if_match(if_match.as_ref(), user_id_params, stored_user.version)?;
+    /* * end checking and get stored user data */
+
+    /* * keep only fields that differ from stored user */
+    let username = payload.username.filter(|username| *username != stored_user.username);
+    let full_name = payload.full_name.filter(|full_name| *full_name != stored_user.full_name);
+    let email = payload.email.filter(|email| *email != stored_user.email);
+    let phone_number = payload.phone_number.filter(|phone_number| *phone_number != stored_user.phone_number);
+    let password = payload.password;
+    /* * end keep only fields that differ from stored user */
+
+    /* * checking availability of changed unique fields */
+    let is_taken = |column: &'static str, value: String| async move {
+        let query = format!("SELECT EXISTS (SELECT 1 FROM user WHERE {} = ? AND id_user <> ?)", column);
+        sqlx::query_scalar::<_, i64>(&query)
+            .bind(value)
+            .bind(user_id_params)
+            .fetch_one(db_pool)
+            .await
+            .map(|exists| exists != 0)
+    };
+    if let Some(username) = &username {
+        if is_taken("username", username.clone()).await? {
+            return Err(conflict_error(format!("the username '{}' is already taken. please choose a different username.", username)));
+        }
+    }
+    if let Some(Some(email)) = &email {
+        if is_taken("email", email.clone()).await? || is_email_pending(db_pool, email, Some(user_id_params)).await? {
+            return Err(conflict_error(format!("the email '{}' is already associated with an existing account. please use a different email", email)));
+        }
+    }
+    if let Some(Some(phone_number)) = &phone_number {
+        if is_taken("phone_number", phone_number.clone()).await? {
+            return Err(conflict_error(format!("the phone number '{}' is already registered. please use a different phone number.", phone_number)));
+        }
+    }
+    /* * end checking availability of changed unique fields */
+
+    /* * new email address stays pending until confirmed, removing email applies immediately */
+    let (email, pending_email) = match email {
+        Some(Some(email)) => (None, Some(email)),
+        email => (email, None)
+    };
+    /* * end new email address stays pending until confirmed, removing email applies immediately */
+
+    /* * build update & history of changed fields */
+    let mut changes = Vec::new();
+    let mut query_builder: QueryBuilder<MySql> = QueryBuilder::new("UPDATE user SET ");
+    let mut assignments = query_builder.separated(", ");
+    if let Some(username) = &username {
+        assignments.push("username = ").push_bind_unseparated(username.clone());
+        changes.push(UserChange { field: "username", old_value: Some(stored_user.username.clone()), new_value: Some(username.clone()) });
+    }
+    if let Some(full_name) = &full_name {
+        assignments.push("full_name = ").push_bind_unseparated(full_name.clone());
+        changes.push(UserChange { field: "full_name", old_value: stored_user.full_name.clone(), new_value: full_name.clone() });
+    }
+    if let Some(email) = &email {
+        assignments.push("email = ").push_bind_unseparated(email.clone());
+        changes.push(UserChange { field: "email", old_value: stored_user.email.clone(), new_value: email.clone() });
+    }
+    if let Some(phone_number) = &phone_number {
+        assignments.push("phone_number = ").push_bind_unseparated(phone_number.clone());
+        changes.push(UserChange { field: "phone_number", old_value: stored_user.phone_number.clone(), new_value: phone_number.clone() });
+    }
+    let hashed_password = password.as_deref().map(hashing_password).transpose()?;
+    if let Some(hashed_password) = &hashed_password {
+        assignments.push("password = ").push_bind_unseparated(hashed_password.clone());
+        changes.push(UserChange { field: "password", old_value: Some(String::new()), new_value: Some(String::new()) });
+    }
+    let time_now = Utc::now().timestamp();
+    assignments.push("updated_at = ").push_bind_unseparated(time_now);
+    assignments.push("version = version + 1");
+    query_builder
+        .push(" WHERE id_user = ").push_bind(user_id_params)
+        .push(" AND version = ").push_bind(stored_user.version);
+    let changed_fields = changes.iter().map(|change| change.field).collect::<Vec<_>>();
+    /* * end build update & history of changed fields */
+
+    /* * update stored user when something changed, username change cascades to credentials & sessions */
+    let stored_username = username.unwrap_or(stored_user.username);
+    let mut version = stored_user.version;
+    let mut transaction = db_pool.begin().await?;
+    if !changed_fields.is_empty() {
+        let query_result = query_builder
+            .build()
+            .execute(&mut *transaction)
+            .await?;
+        /* * * version moved on between read & write, another request won */
+        if query_result.rows_affected() == 0 {
+            return Err(precondition_failed_error(user_id_params, None));
+        }
+        /* * * end version moved on between read & write, another request won */
+        if let Some(hashed_password) = &hashed_password {
+            let sql_query = sqlx::query("UPDATE credentials SET password = ?, password_reset_required = FALSE, updated_at = ? WHERE username = ?");
+            let _ = sql_query
+                .bind(hashed_password)
+                .bind(time_now)
+                .bind(&stored_username)
+                .execute(&mut *transaction)
+                .await?;
+            /* * * every existing session must sign in again with the new password */
+            let sql_query = sqlx::query("DELETE FROM sessions WHERE username = ?");
+            let _ = sql_query
+                .bind(&stored_username)
+                .execute(&mut *transaction)
+                .await?;
+            /* * * end every existing session must sign in again with the new password */
+        }
+        record_user_changes(&mut *transaction, user_id_params, Some(actor), changes).await?;
+        version += 1;
+    }
+    /* * end update stored user when something changed, username change cascades to credentials & sessions */
+
+    /* * requested email address commits with the update, links are mailed afterwards */
+    let email_change_links = match &pending_email {
+        Some(pending_email) => Some(store_email_change(&mut transaction, user_id_params, &stored_username, stored_user.email, pending_email.clone(), actor).await?),
+        None => None
+    };
+    transaction.commit().await?;
+    if let Some(email_change_links) = email_change_links {
+        send_email_change_links(app_state, email_change_links).await;
+    }
+    /* * end requested email address commits with the update, links are mailed afterwards */
+
+    let is_password_change = hashed_password.is_some();
+    Ok( ServiceOkPatchUser { username: stored_username, is_password_change, changed_fields, pending_email, version } )
+}
@@ -0,0 +1,66 @@
+use std::time::Duration;
+use actix_web::web;
+use chrono::Utc;
+
+use crate::{
+    types::AppState,
+    errors::AppError,
+    audit::{
+        record_audit_log,
+        AuditEntry
+    },
+    auth::ClientInfo
+};
+
+const PURGE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/* * permanently delete users soft deleted longer than retention period, credentials & sessions cascade */
+pub async fn purge_deleted_users_service(app_state: &AppState) -> Result<u64, AppError> {
+    let Some(retention) = app_state.retention.deleted_user_retention else {
+        return Ok(0);
+    };
+    let purge_before = Utc::now().timestamp() - retention.num_seconds();
+
+    let sql_query = sqlx::query("DELETE FROM user WHERE deleted_at IS NOT NULL AND deleted_at < ?");
+    let query_result = sql_query
+        .bind(purge_before)
+        .execute(&app_state.db_pool)
+        .await?;
+
+    Ok( query_result.rows_affected() )
+}
+/* * end permanently delete users soft deleted longer than retention period, credentials & sessions cascade */
+
+/* * run purge every hour in background, nothing is scheduled when retention is disabled */
+pub fn schedule_deleted_user_purge(app_state: web::Data<AppState>) {
+    if app_state.retention.deleted_user_retention.is_none() {
+        return;
+    }
+
+    actix_web::rt::spawn(async move {
+        let mut interval = actix_web::rt::time::interval(PURGE_INTERVAL);
+        let client = ClientInfo { ip_address: None, user_agent: None };
+        loop {
+            interval.tick().await;
+            let purge_deleted_users_service = purge_deleted_users_service(&app_state).await;
+            /* * * only purges that removed users or failed are worth an audit entry */
+            if let Ok(0) = purge_deleted_users_service {
+                continue;
+            }
+            let audit_entry = AuditEntry {
+                event: "user.purge",
+                actor: None,
+                target: None,
+                success: purge_deleted_users_service.is_ok(),
+                reason: match &purge_deleted_users_service {
+                    Ok(purged) => Some(format!("{} soft deleted users purged.", purged)),
+                    Err(e) => Some(e.message())
+                },
+                client: &client
+            };
+            record_audit_log(&app_state.db_pool, audit_entry).await;
+            /* * * end only purges that removed users or failed are worth an audit entry */
+        }
+    });
+}
+/* * end run purge every hour in background, nothing is scheduled when retention is disabled */
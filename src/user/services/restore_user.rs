@@ -0,0 +1,70 @@
+use actix_web::http::StatusCode;
+use chrono::Utc;
+
+use crate::{
+    types::AppState,
+    errors::{
+        AppError,
+        AppErrorMessage
+    },
+    user::{
+        model::{
+            IfMatch,
+            UserChange
+        },
+        helpers::{
+            check_if_match,
+            record_user_changes,
+            precondition_failed_error
+        }
+    }
+};
+
+pub async fn restore_user_service(
+    app_state: &AppState,
+    user_id_params: u32,
+    if_match: Option<IfMatch>,
+    actor: &str
+) -> Result<String, AppError>
+{
+    /* * take db pool from handler */
+    let db_pool = &app_state.db_pool;
+    /* * end take db pool from handler */
+
+    /* * check & get soft deleted user from database */
+    let sql_query = sqlx::query_as::<_, (String, i64, u32)>("SELECT username, deleted_at, version FROM user WHERE id_user = ? AND deleted_at IS NOT NULL");
+    let (username, stored_deleted_at, stored_version) = sql_query
+        .bind(user_id_params)
+        .fetch_optional(db_pool)
+        .await?
+        .ok_or_else(|| {
+            let app_err_message: AppErrorMessage<Option<bool>> = AppErrorMessage {
+                code: StatusCode::NOT_FOUND.as_u16(),
+                message: format!("deleted user with id '{}' not found. it is either active or already purged.", user_id_params),
+                details: None
+            };
+            AppError::NotFound(app_err_message.into())
+        })?;
+    check_if_match(if_match.as_ref(), user_id_params, stored_version)?;
+    /* * end check & get soft deleted user from database */
+
+    /* * restore user */
+    let time_now = Utc::now().timestamp();
+    let mut transaction = db_pool.begin().await?;
+    let sql_query = sqlx::query("UPDATE user SET deleted_at = NULL, updated_at = ?, version = version + 1 WHERE id_user = ? AND version = ? AND deleted_at IS NOT NULL");
+    let query_result = sql_query
+        .bind(time_now)
+        .bind(user_id_params)
+        .bind(stored_version)
+        .execute(&mut *transaction)
+        .await?;
+    if query_result.rows_affected() == 0 {
+        return Err(precondition_failed_error(user_id_params, None));
+    }
+    let changes = vec![UserChange { field: "deleted_at", old_value: Some(stored_deleted_at.to_string()), new_value: None }];
+    record_user_changes(&mut *transaction, user_id_params, Some(actor), changes).await?;
+    transaction.commit().await?;
+    /* * end restore user */
+
+    Ok( username )
+}
@@ -0,0 +1,248 @@
+use std::collections::HashMap;
+use regex::Regex;
+use sqlx::{
+    MySql,
+    QueryBuilder
+};
+
+use crate::{
+    types::AppState,
+    errors::AppError,
+    user::{
+        model::{
+            UserCursor,
+            UserSearchQuery,
+            UserSearchRow,
+            UserSearchResult,
+            Pagination,
+            ServiceOkUserSearch
+        },
+        helpers::{
+            escape_like,
+            encode_cursor,
+            decode_cursor,
+            invalid_query_error,
+            highlight
+        }
+    }
+};
+
+const DEFAULT_LIMIT: u32 = 50;
+const MAX_LIMIT: u32 = 200;
+const SEARCH_SORT: &str = "relevance";
+const SEARCH_ORDER: &str = "desc";
+
+/* * fulltext columns, each list is backed by its own FULLTEXT index */
+const PUBLIC_SEARCH_COLUMNS: &str = "full_name, username";
+const ADMIN_SEARCH_COLUMNS: &str = "full_name, username, email";
+/* * end fulltext columns, each list is backed by its own FULLTEXT index */
+
+/* * parsed search terms, boolean mode fulltext expression & phone number pattern */
+struct SearchTerms {
+    terms: Vec<String>,
+    fulltext: String,
+    columns: &'static str,
+    phone_number: Option<String>
+}
+
+impl SearchTerms {
+    /* * * fulltext operators are stripped, every term is a required prefix match */
+    /* * * only admins match on email & phone number, otherwise search is an enumeration oracle */
+    fn parse(q: &str, is_admin: bool) -> Self {
+        let terms = q
+            .split(|character: char| !character.is_alphanumeric())
+            .filter(|term| !term.is_empty())
+            .map(str::to_lowercase)
+            .collect::<Vec<_>>();
+        let fulltext = terms
+            .iter()
+            .map(|term| format!("+{}*", term))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let digits = q.chars().filter(char::is_ascii_digit).collect::<String>();
+        let phone_number = (is_admin && !digits.is_empty()).then(|| format!("%{}%", escape_like(&digits)));
+        let columns = match is_admin {
+            true => ADMIN_SEARCH_COLUMNS,
+            false => PUBLIC_SEARCH_COLUMNS
+        };
+
+        Self { terms, fulltext, columns, phone_number }
+    }
+    /* * * end only admins match on email & phone number, otherwise search is an enumeration oracle */
+    /* * * end fulltext operators are stripped, every term is a required prefix match */
+
+    fn push_condition(&self, query_builder: &mut QueryBuilder<MySql>) {
+        query_builder
+            .push(format!(" AND (MATCH({}) AGAINST (", self.columns))
+            .push_bind(self.fulltext.clone())
+            .push(" IN BOOLEAN MODE)");
+        if let Some(phone_number) = &self.phone_number {
+            query_builder.push(" OR phone_number LIKE ").push_bind(phone_number.clone());
+        }
+        query_builder.push(")");
+    }
+
+    fn highlight_regex(&self) -> Option<Regex> {
+        let pattern = self.terms
+            .iter()
+            .map(|term| regex::escape(term))
+            .collect::<Vec<_>>()
+            .join("|");
+        Regex::new(&format!("(?i){}", pattern)).ok()
+    }
+}
+/* * end parsed search terms, boolean mode fulltext expression & phone number pattern */
+
+pub async fn search_users_service(
+    app_state: &AppState,
+    query: UserSearchQuery,
+    is_admin: bool
+) -> Result<ServiceOkUserSearch, AppError>
+{
+    /* * take db pool from handler */
+    let db_pool = &app_state.db_pool;
+    /* * end take db pool from handler */
+
+    /* * validating search & pagination query */
+    let search_terms = SearchTerms::parse(&query.q, is_admin);
+    if search_terms.terms.is_empty() {
+        return Err(invalid_query_error(String::from("q must contain at least one letter or digit.")));
+    }
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+    if query.cursor.is_some() && query.offset.is_some() {
+        return Err(invalid_query_error(String::from("cursor and offset cannot be used together.")));
+    }
+    let cursor = query.cursor.as_deref().map(decode_cursor).transpose()?;
+    if cursor.as_ref().is_some_and(|cursor| cursor.sort != SEARCH_SORT || cursor.offset.is_none()) {
+        return Err(invalid_query_error(String::from("cursor was not issued by user search.")));
+    }
+    /* * * relevance is not a stable key, search cursor carries the next offset */
+    let offset = cursor
+        .and_then(|cursor| cursor.offset)
+        .or(query.offset)
+        .unwrap_or(0);
+    /* * * end relevance is not a stable key, search cursor carries the next offset */
+    /* * end validating search & pagination query */
+
+    /* * count matched users */
+    let mut count_builder: QueryBuilder<MySql> = QueryBuilder::new("SELECT COUNT(*) FROM user WHERE deleted_at IS NULL");
+    search_terms.push_condition(&mut count_builder);
+    let total: i64 = count_builder
+        .build_query_scalar()
+        .fetch_one(db_pool)
+        .await?;
+    /* * end count matched users */
+
+    /* * build ranked sql query, phone number match adds one to fulltext relevance */
+    let mut query_builder: QueryBuilder<MySql> = QueryBuilder::new(format!("SELECT *, (MATCH({}) AGAINST (", search_terms.columns));
+    query_builder
+        .push_bind(search_terms.fulltext.clone())
+        .push(" IN BOOLEAN MODE)");
+    if let Some(phone_number) = &search_terms.phone_number {
+        query_builder
+            .push(" + COALESCE(phone_number LIKE ")
+            .push_bind(phone_number.clone())
+            .push(", 0)");
+    }
+    query_builder.push(") AS score FROM user WHERE deleted_at IS NULL");
+    search_terms.push_condition(&mut query_builder);
+    query_builder
+        .push(" ORDER BY score DESC, id_user ASC LIMIT ")
+        .push_bind(limit + 1)
+        .push(" OFFSET ")
+        .push_bind(offset);
+    /* * end build ranked sql query, phone number match adds one to fulltext relevance */
+
+    /* * sql query retrieved matched users */
+    let mut rows = query_builder
+        .build_query_as::<UserSearchRow>()
+        .fetch_all(db_pool)
+        .await?;
+    /* * end sql query retrieved matched users */
+
+    /* * pagination metadata */
+    let has_more = rows.len() > limit as usize;
+    rows.truncate(limit as usize);
+    let next_cursor = rows
+        .last()
+        .filter(|_| has_more)
+        .map(|row| encode_cursor(&UserCursor {
+            sort: String::from(SEARCH_SORT),
+            order: String::from(SEARCH_ORDER),
+            value: None,
+            id_user: row.user.id_user,
+            offset: Some(offset + limit)
+        }));
+    let pagination = Pagination {
+        total,
+        limit,
+        offset: Some(offset),
+        next_cursor,
+        has_more
+    };
+    /* * end pagination metadata */
+
+    /* * highlight matched terms of searchable fields */
+    let highlight_regex = search_terms.highlight_regex();
+    let results = rows
+        .into_iter()
+        .map(|row| {
+            let fields = [
+                ("full_name", row.user.full_name.as_deref()),
+                ("username", Some(row.user.username.as_str())),
+                ("email", row.user.email.as_deref()),
+                ("phone_number", row.user.phone_number.as_deref())
+            ];
+            let highlights = fields
+                .into_iter()
+                .filter_map(|(field, value)| {
+                    let highlighted = highlight(value?, highlight_regex.as_ref()?)?;
+                    Some((field.to_string(), highlighted))
+                })
+                .collect::<HashMap<_, _>>();
+
+            UserSearchResult { user: row.user, score: row.score, highlights }
+        })
+        .collect::<Vec<_>>();
+    /* * end highlight matched terms of searchable fields */
+
+    Ok( ServiceOkUserSearch { results, pagination } )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_strips_fulltext_operators_into_prefix_terms() {
+        let search_terms = SearchTerms::parse("+Jane -\"DOE\"* (o'brien)", false);
+
+        assert_eq!(search_terms.terms, vec!["jane", "doe", "o", "brien"]);
+        assert_eq!(search_terms.fulltext, "+jane* +doe* +o* +brien*");
+        assert_eq!(search_terms.columns, PUBLIC_SEARCH_COLUMNS);
+        assert!(search_terms.phone_number.is_none());
+        assert!(SearchTerms::parse("+-*~\"()", false).terms.is_empty());
+    }
+
+    #[test]
+    fn parse_matches_email_and_phone_number_for_admins_only() {
+        let admin_terms = SearchTerms::parse("+62 812-3456", true);
+        let public_terms = SearchTerms::parse("+62 812-3456", false);
+
+        assert_eq!(admin_terms.columns, ADMIN_SEARCH_COLUMNS);
+        assert_eq!(admin_terms.phone_number.as_deref(), Some("%628123456%"));
+        assert_eq!(public_terms.columns, PUBLIC_SEARCH_COLUMNS);
+        assert!(public_terms.phone_number.is_none());
+    }
+
+    #[test]
+    fn highlight_escapes_html_and_marks_terms_case_insensitively() {
+        let highlight_regex = SearchTerms::parse("jane", false).highlight_regex().unwrap();
+
+        assert_eq!(
+            highlight("<b>JANE</b> & Jane's", &highlight_regex).as_deref(),
+            Some("&lt;b&gt;<mark>JANE</mark>&lt;/b&gt; &amp; <mark>Jane</mark>&#x27;s")
+        );
+        assert!(highlight("john", &highlight_regex).is_none());
+    }
+}
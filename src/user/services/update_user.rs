@@ -1,27 +1,44 @@
 use actix_web::http::StatusCode;
+use chrono::Utc;
 use validator::Validate;
 use sqlx::Row;
 
 use crate::{
     types::AppState, 
     user::{
-        model::UserPayload,
+        model::{
+            UserPayload,
+            ServiceOkUpdateUser,
+            UserChange,
+            IfMatch
+        },
+        services::email_change::{
+            store_email_change,
+            send_email_change_links
+        },
         helpers::{
             get_stored_user, 
-            hashing_password
+            hashing_password,
+            record_user_changes,
+            is_email_pending,
+            check_if_match,
+            precondition_failed_error
         }
     },
     errors::{
         AppError, 
         AppErrorMessage
     }, 
+    auth::verify_password
 };
 
 pub async fn update_user_service(
     app_state: &AppState,
     user_id_params: u32,
-    payload: UserPayload
-) -> Result<String, AppError> 
+    payload: UserPayload,
+    if_match: Option<IfMatch>,
+    actor: &str
+) -> Result<ServiceOkUpdateUser, AppError> 
 {
     /* * validating user input */
     payload.validate()?;
@@ -32,7 +49,7 @@ pub async fn update_user_service(
     /* * end take db pool from handler */
 
     /* * checking and get stored user data */
-    let sql_query = format!("SELECT * FROM user where id_user = {}", user_id_params);
+    let sql_query = format!("SELECT * FROM user where id_user = {} AND deleted_at IS NULL", user_id_params);
     let message = Some(
         format!("the user with id '{}' was not found in the database.", user_id_params)
     );
@@ -40,6 +57,10 @@ pub async fn update_user_service(
     let stored_username = stored_user.get::<String,_>("username");
     let stored_email= stored_user.get::<Option<&str>,_>("email");
     let stored_phone_number= stored_user.get::<Option<&str>,_>("phone_number");
+    let stored_full_name = stored_user.get::<Option<String>,_>("full_name");
+    let stored_version = stored_user.get::<u32,_>("version");
+    let stored_password = stored_user.get::<String,_>("password");
+    check_if_match(if_match.as_ref(), user_id_params, stored_version)?;
     /* * end checking and get stored user data */
 
     /* * checking availability username */
@@ -76,6 +97,17 @@ pub async fn update_user_service(
                 return Err(AppError::Conflict(app_err_message.into()));
             }
         }
+        if Some(payload_email.as_str()) != stored_email && is_email_pending(db_pool, payload_email, Some(user_id_params)).await? {
+            let app_err_message: AppErrorMessage<Option<bool>> = AppErrorMessage {
+                code: StatusCode::CONFLICT.as_u16(),
+                message: format!(
+                    "the email '{}' is already associated with an existing account. please use a different email", 
+                    payload_email
+                ),
+                details: None
+            };
+            return Err(AppError::Conflict(app_err_message.into()));
+        }
     }
     /* * checking availability email */
     /* * checking availability phone_number */
@@ -98,68 +130,99 @@ pub async fn update_user_service(
     }
     /* * checking availability phone number */
 
-    /* * hashing password */
-    let hashed_password = hashing_password(&payload.password)?;
-    /* * end hashing password */
+    /* * new email address stays pending until confirmed, removing email applies immediately */
+    let (email, pending_email) = match &payload.email {
+        Some(payload_email) if Some(payload_email.as_str()) != stored_email => (stored_email.map(str::to_string), Some(payload_email.clone())),
+        payload_email => (payload_email.clone(), None)
+    };
+    /* * end new email address stays pending until confirmed, removing email applies immediately */
 
-    /* * update stored user data  */
+    /* * hashing password, resubmitting the stored password keeps its hash & sessions */
+    let is_password_change = !verify_password(&payload.password, &stored_password).unwrap_or(false);
+    let hashed_password = match is_password_change {
+        true => hashing_password(&payload.password)?,
+        false => stored_password
+    };
+    /* * end hashing password, resubmitting the stored password keeps its hash & sessions */
+
+    /* * update stored user data, credentials, sessions & history commit in one transaction */
+    let time_now = Utc::now().timestamp();
+    let mut transaction = db_pool.begin().await?;
     let sql_query = sqlx::query("UPDATE user SET 
         username = ?, 
         password = ?,
         email = ?,
         phone_number = ?,
-        full_name = ?
-        WHERE id_user = ?
+        full_name = ?,
+        updated_at = ?,
+        version = version + 1
+        WHERE id_user = ? AND version = ?
    ");
-   let _ = sql_query
+   let query_result = sql_query
         .bind(&payload.username)
         .bind(&hashed_password)
-        .bind(&payload.email)
+        .bind(&email)
         .bind(&payload.phone_number)
         .bind(&payload.full_name)
+        .bind(time_now)
         .bind(user_id_params)
-        .execute(db_pool)
+        .bind(stored_version)
+        .execute(&mut *transaction)
         .await?;
-    /* * end update stored user data  */
+    /* * * version moved on between read & write, another request won */
+    if query_result.rows_affected() == 0 {
+        return Err(precondition_failed_error(user_id_params, None));
+    }
+    /* * * end version moved on between read & write, another request won */
+    /* * end update stored user data, credentials, sessions & history commit in one transaction */
     /* * update stored credentials data */
     let sql_query = sqlx::query("UPDATE credentials SET
         username = ?,
-        password = ?
+        password = ?,
+        password_reset_required = password_reset_required AND NOT ?,
+        updated_at = ?
         WHERE username = ?
     ");
     let _ = sql_query
         .bind(&payload.username)
         .bind(&hashed_password)
+        .bind(is_password_change)
+        .bind(time_now)
         .bind(&payload.username)
-        .execute(db_pool)
+        .execute(&mut *transaction)
         .await?;
     /* * end update stored credentials data */
+    /* * every existing session must sign in again with the new password */
+    if is_password_change {
+        let sql_query = sqlx::query("DELETE FROM sessions WHERE username = ?");
+        let _ = sql_query
+            .bind(&payload.username)
+            .execute(&mut *transaction)
+            .await?;
+    }
+    /* * end every existing session must sign in again with the new password */
     
-    /* * checking is user update stored  */
-    let raw_sql_query = format!("SELECT password FROM user WHERE id_user = {}", user_id_params);
-    let message = Some(
-        format!(
-            "an unknown error occurred while processing your request. the user with id '{}' has not been found in the database. please try again.",
-            user_id_params
-        )
-    );
-    let is_user_update_stored = get_stored_user(db_pool, &raw_sql_query, message, None).await?;
-    let stored_user_update_password = is_user_update_stored.get::<&str,_>("password");
-    /* * end checking is user update stored  */
-    /* * checking is credentials update stored  */
-    let raw_sql_query = format!("SELECT password FROM credentials WHERE username = '{}'", &payload.username);
-    let message = Some(
-        format!(
-            "an unknown error occurred while processing your request. the user '{}' has not been found in the database. please try again.",
-            &payload.username
-        )
-    );
-    let is_credentials_update_stored = get_stored_user(db_pool, &raw_sql_query, message, None).await?;
-    let stored_credentials_update_password = is_credentials_update_stored.get::<&str,_>("password");
-    /* * end checking is credentials update stored  */
+    /* * checking is user & credentials update stored, read inside the uncommitted transaction */
+    let sql_query = sqlx::query_as::<_, (String, Option<String>)>("SELECT user.password, credentials.password FROM user LEFT JOIN credentials ON credentials.username = user.username WHERE user.id_user = ?");
+    let (stored_user_update_password, stored_credentials_update_password) = sql_query
+        .bind(user_id_params)
+        .fetch_optional(&mut *transaction)
+        .await?
+        .ok_or_else(|| {
+            let app_err_message: AppErrorMessage<Option<bool>> = AppErrorMessage {
+                code: StatusCode::NOT_FOUND.as_u16(),
+                message: format!(
+                    "an unknown error occurred while processing your request. the user with id '{}' has not been found in the database. please try again.",
+                    user_id_params
+                ),
+                details: None
+            };
+            AppError::NotFound(app_err_message.into())
+        })?;
+    /* * end checking is user & credentials update stored, read inside the uncommitted transaction */
 
     /* * final validate authentication user credentials  */
-    if stored_user_update_password != stored_credentials_update_password {
+    if Some(stored_user_update_password) != stored_credentials_update_password {
         let app_err_message = AppErrorMessage {
             code: StatusCode::INTERNAL_SERVER_ERROR.as_u16(),
             message: String::from("oops! update user failed. unable to update user."),
@@ -169,6 +232,37 @@ pub async fn update_user_service(
     }
     /* * end final validate authentication user credentials  */
 
+    /* * history of replaced fields, password only when it changed */
+    let mut changes = Vec::new();
+    if payload.username != stored_username {
+        changes.push(UserChange { field: "username", old_value: Some(stored_username.clone()), new_value: Some(payload.username.clone()) });
+    }
+    if payload.full_name != stored_full_name {
+        changes.push(UserChange { field: "full_name", old_value: stored_full_name, new_value: payload.full_name.clone() });
+    }
+    if email.as_deref() != stored_email {
+        changes.push(UserChange { field: "email", old_value: stored_email.map(str::to_string), new_value: email.clone() });
+    }
+    if payload.phone_number.as_deref() != stored_phone_number {
+        changes.push(UserChange { field: "phone_number", old_value: stored_phone_number.map(str::to_string), new_value: payload.phone_number.clone() });
+    }
+    if is_password_change {
+        changes.push(UserChange { field: "password", old_value: Some(String::new()), new_value: Some(String::new()) });
+    }
+    record_user_changes(&mut *transaction, user_id_params, Some(actor), changes).await?;
+    /* * end history of replaced fields, password only when it changed */
+
+    /* * requested email address commits with the update, links are mailed afterwards */
+    let email_change_links = match &pending_email {
+        Some(pending_email) => Some(store_email_change(&mut transaction, user_id_params, &payload.username, email, pending_email.clone(), actor).await?),
+        None => None
+    };
+    transaction.commit().await?;
+    if let Some(email_change_links) = email_change_links {
+        send_email_change_links(app_state, email_change_links).await;
+    }
+    /* * end requested email address commits with the update, links are mailed afterwards */
+
     /* * */
-    Ok( stored_username )
+    Ok( ServiceOkUpdateUser { username: payload.username, is_password_change, pending_email, version: stored_version + 1 } )
 }
\ No newline at end of file
@@ -0,0 +1,141 @@
+#![allow(dead_code)]
+
+use actix_web::{
+    web,
+    http::StatusCode
+};
+use serde_json::Value;
+
+use rst04_jwt::{
+    AppState,
+    ClientsConfig,
+    CsrfConfig,
+    MtlsConfig,
+    PreconditionConfig,
+    RetentionConfig,
+    SessionConfig,
+    TokenConfig,
+    WebauthnConfig,
+    establish_connection,
+    build_mailer,
+    build_credential_verifier,
+    build_token_format
+};
+
+pub const RP_ID: &str = "localhost";
+pub const RP_ORIGIN: &str = "http://localhost:3001";
+pub const PASSWORD: &str = "Passw0rd!test";
+
+pub async fn build_app_state(credential_verifiers: &str) -> web::Data<AppState> {
+    build_app_state_with(credential_verifiers, None).await
+}
+
+/* * build app state against the database from DATABASE_URL, migrations are applied first */
+pub async fn build_app_state_with(credential_verifiers: &str, htpasswd_path: Option<String>) -> web::Data<AppState> {
+    dotenv::dotenv().ok();
+    let db_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set to run integration tests.");
+    let db_pool = establish_connection(&db_url).await;
+    sqlx::migrate!("./migrations")
+        .run(&db_pool)
+        .await
+        .expect("failed to run migrations.");
+
+    let credential_verifier = build_credential_verifier(
+        credential_verifiers,
+        &db_pool,
+        Some(ldap_url()),
+        Some(ldap_bind_dn_template()),
+        false,
+        htpasswd_path,
+        "admin"
+    ).expect("invalid credential verifier config.");
+
+    web::Data::new(
+        AppState {
+            db_pool,
+            secret_access_token: String::from("test_secret_access_token"),
+            secret_refresh_token: String::from("test_secret_refresh_token"),
+            public_url: String::from(RP_ORIGIN),
+            token_format: build_token_format("jwt", "test_secret_access_token", "test_secret_refresh_token", None, None).unwrap(),
+            tokens: TokenConfig::from_minutes(5, 10, 60).unwrap(),
+            sessions: SessionConfig::from_minutes(30, 60).unwrap(),
+            retention: RetentionConfig::from_days(30).unwrap(),
+            preconditions: PreconditionConfig { if_match_required: false },
+            webauthn: WebauthnConfig {
+                rp_id: String::from(RP_ID),
+                rp_name: String::from("backend_https"),
+                rp_origin: String::from(RP_ORIGIN)
+            },
+            csrf: CsrfConfig::from_list(RP_ORIGIN).unwrap(),
+            clients: ClientsConfig::from_list("body", "").unwrap(),
+            mtls: MtlsConfig::from_list("").unwrap(),
+            mailer: build_mailer("log", None, "no-reply@localhost").unwrap(),
+            credential_verifier
+        }
+    )
+}
+/* * end build app state against the database from DATABASE_URL, migrations are applied first */
+
+/* * openldap container from docker-compose.yml */
+pub fn ldap_url() -> String {
+    std::env::var("LDAP_TEST_URL").unwrap_or(String::from("ldap://localhost:1389"))
+}
+
+pub fn ldap_bind_dn_template() -> String {
+    std::env::var("LDAP_TEST_BIND_DN_TEMPLATE").unwrap_or(String::from("cn={username},ou=users,dc=example,dc=org"))
+}
+/* * end openldap container from docker-compose.yml */
+
+/* * test app with every api scope mounted like main.rs */
+#[macro_export]
+macro_rules! init_app {
+    ($app_state:expr) => {
+        actix_web::test::init_service(
+            actix_web::App::new()
+                .app_data($app_state.clone())
+                .service(
+                    actix_web::web::scope("/api")
+                        .configure(rst04_jwt::scoped_ping)
+                        .configure(rst04_jwt::scoped_auth)
+                        .configure(rst04_jwt::scoped_user)
+                        .configure(rst04_jwt::scoped_audit)
+                )
+        ).await
+    };
+}
+
+/* * sign up user with password through the api, returns status & json body */
+#[macro_export]
+macro_rules! signup {
+    ($app:expr, $username:expr, $password:expr) => {{
+        let request = actix_web::test::TestRequest::post()
+            .uri("/api/auth/signup")
+            .set_json(serde_json::json!({ "user": { "username": $username, "password": $password } }))
+            .to_request();
+        $crate::common::read_response(actix_web::test::call_service(&$app, request).await).await
+    }};
+}
+
+/* * sign in with password through the api, returns status & json body */
+#[macro_export]
+macro_rules! signin {
+    ($app:expr, $username:expr, $password:expr) => {{
+        let request = actix_web::test::TestRequest::post()
+            .uri("/api/auth/signin")
+            .set_json(serde_json::json!({ "credentials": { "username": $username, "password": $password } }))
+            .to_request();
+        $crate::common::read_response(actix_web::test::call_service(&$app, request).await).await
+    }};
+}
+
+/* * random username, usernames only allow alphanumeric characters */
+pub fn unique_username(prefix: &str) -> String {
+    format!("{}{}", prefix, rand::random::<u32>())
+}
+
+/* * status code & json body of a test response */
+pub async fn read_response(response: actix_web::dev::ServiceResponse) -> (StatusCode, Value) {
+    let status = response.status();
+    let body = actix_web::test::read_body(response).await;
+    (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+}
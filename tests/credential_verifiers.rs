@@ -0,0 +1,135 @@
+mod common;
+
+use std::collections::HashSet;
+use actix_web::{
+    test,
+    http::StatusCode
+};
+use ldap3::LdapConnAsync;
+
+use common::{
+    PASSWORD,
+    build_app_state_with,
+    ldap_url,
+    ldap_bind_dn_template,
+    unique_username,
+    read_response
+};
+
+const BACKEND_PASSWORD: &str = "Backend!pass123";
+
+/* * htpasswd file with a single bcrypt user */
+fn write_htpasswd(username: &str, password: &str) -> String {
+    let path = std::env::temp_dir().join(format!("htpasswd-{}", rand::random::<u32>()));
+    let hash = bcrypt::hash(password, 4).unwrap();
+    std::fs::write(&path, format!("{}:{}\n", username, hash)).unwrap();
+
+    path.to_string_lossy().to_string()
+}
+
+/* * add inetOrgPerson entry through admin bind, dn follows LDAP bind dn template */
+async fn add_ldap_user(username: &str, password: &str) {
+    let admin_dn = std::env::var("LDAP_TEST_ADMIN_DN").unwrap_or(String::from("cn=admin,dc=example,dc=org"));
+    let admin_password = std::env::var("LDAP_TEST_ADMIN_PASSWORD").unwrap_or(String::from("adminpassword"));
+    let (conn, mut ldap) = LdapConnAsync::new(&ldap_url()).await.expect("failed to connect to ldap server.");
+    ldap3::drive!(conn);
+
+    ldap.simple_bind(&admin_dn, &admin_password).await.unwrap().success().expect("ldap admin bind failed.");
+    let dn = ldap_bind_dn_template().replace("{username}", username);
+    ldap.add(&dn, vec![
+        ("objectClass", HashSet::from(["inetOrgPerson"])),
+        ("cn", HashSet::from([username])),
+        ("sn", HashSet::from([username])),
+        ("userPassword", HashSet::from([password]))
+    ]).await.unwrap().success().expect("failed to add ldap user.");
+    ldap.unbind().await.unwrap();
+}
+
+/* * role of signed in user from /users/me */
+macro_rules! get_my_role {
+    ($app:expr, $access_token:expr) => {{
+        let request = test::TestRequest::get()
+            .uri("/api/users/me")
+            .insert_header(("Authorization", format!("Bearer {}", $access_token)))
+            .to_request();
+        let (status, body) = read_response(test::call_service(&$app, request).await).await;
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        body["data"]["role"].as_str().unwrap().to_string()
+    }};
+}
+
+#[actix_web::test]
+#[ignore = "requires MySQL/MariaDB from docker-compose.yml and DATABASE_URL"]
+async fn htpasswd_provisions_bootstrap_admin() {
+    let username = unique_username("bootstrap");
+    let app_state = build_app_state_with("db,htpasswd", Some(write_htpasswd(&username, BACKEND_PASSWORD))).await;
+    let app = init_app!(app_state);
+
+    let (status, body) = signin!(app, &username, BACKEND_PASSWORD);
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(get_my_role!(app, body["data"]["access_token"].as_str().unwrap()), "admin");
+
+    let (status, body) = signin!(app, &username, PASSWORD);
+    assert_eq!(status, StatusCode::UNAUTHORIZED, "{}", body);
+}
+
+#[actix_web::test]
+#[ignore = "requires MySQL/MariaDB from docker-compose.yml and DATABASE_URL"]
+async fn htpasswd_cannot_take_over_db_account() {
+    let username = unique_username("dbowned");
+    let htpasswd_path = write_htpasswd(&username, BACKEND_PASSWORD);
+
+    /* * db password is wrong, chain must not fall through to htpasswd */
+    let app_state = build_app_state_with("db,htpasswd", Some(htpasswd_path.clone())).await;
+    let app = init_app!(app_state);
+    let (status, body) = signup!(app, &username, PASSWORD);
+    assert_eq!(status, StatusCode::CREATED, "{}", body);
+    let (status, body) = signin!(app, &username, BACKEND_PASSWORD);
+    assert_eq!(status, StatusCode::UNAUTHORIZED, "{}", body);
+
+    /* * htpasswd alone must not provision into the db account */
+    let app_state = build_app_state_with("htpasswd", Some(htpasswd_path)).await;
+    let htpasswd_app = init_app!(app_state);
+    let (status, body) = signin!(htpasswd_app, &username, BACKEND_PASSWORD);
+    assert_eq!(status, StatusCode::UNAUTHORIZED, "{}", body);
+
+    /* * db owner still signs in and keeps role */
+    let (status, body) = signin!(app, &username, PASSWORD);
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(get_my_role!(app, body["data"]["access_token"].as_str().unwrap()), "user");
+}
+
+#[actix_web::test]
+#[ignore = "requires MySQL/MariaDB & OpenLDAP from docker-compose.yml and DATABASE_URL"]
+async fn ldap_user_signs_in_and_is_provisioned() {
+    let username = unique_username("ldap");
+    add_ldap_user(&username, BACKEND_PASSWORD).await;
+    let app_state = build_app_state_with("db,ldap", None).await;
+    let app = init_app!(app_state);
+
+    let (status, body) = signin!(app, &username, BACKEND_PASSWORD);
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(get_my_role!(app, body["data"]["access_token"].as_str().unwrap()), "user");
+
+    /* * provisioned account is owned by ldap, repeated signin goes to ldap again */
+    let (status, body) = signin!(app, &username, BACKEND_PASSWORD);
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let (status, body) = signin!(app, &username, PASSWORD);
+    assert_eq!(status, StatusCode::UNAUTHORIZED, "{}", body);
+}
+
+#[actix_web::test]
+#[ignore = "requires MySQL/MariaDB & OpenLDAP from docker-compose.yml and DATABASE_URL"]
+async fn ldap_cannot_take_over_db_account() {
+    let username = unique_username("shadow");
+    let app_state = build_app_state_with("db,ldap", None).await;
+    let app = init_app!(app_state);
+    let (status, body) = signup!(app, &username, PASSWORD);
+    assert_eq!(status, StatusCode::CREATED, "{}", body);
+    add_ldap_user(&username, BACKEND_PASSWORD).await;
+
+    let (status, body) = signin!(app, &username, BACKEND_PASSWORD);
+    assert_eq!(status, StatusCode::UNAUTHORIZED, "{}", body);
+    let (status, body) = signin!(app, &username, PASSWORD);
+    assert_eq!(status, StatusCode::OK, "{}", body);
+}
@@ -0,0 +1,266 @@
+mod common;
+
+use actix_web::{
+    test,
+    http::StatusCode
+};
+use base64::{
+    Engine,
+    engine::general_purpose::URL_SAFE_NO_PAD
+};
+use ciborium::Value as CborValue;
+use p256::ecdsa::{
+    Signature,
+    SigningKey,
+    signature::Signer
+};
+use rand::rngs::OsRng;
+use serde_json::{
+    json,
+    Value
+};
+use sha2::{
+    Digest,
+    Sha256
+};
+
+use common::{
+    RP_ID,
+    RP_ORIGIN,
+    PASSWORD,
+    build_app_state,
+    unique_username,
+    read_response
+};
+
+/* * software authenticator, ES256 "none" attestation like a platform passkey */
+struct SoftAuthenticator {
+    signing_key: SigningKey,
+    credential_id: Vec<u8>,
+    sign_count: u32
+}
+
+impl SoftAuthenticator {
+    fn new() -> Self {
+        Self {
+            signing_key: SigningKey::random(&mut OsRng),
+            credential_id: rand::random::<[u8; 16]>().to_vec(),
+            sign_count: 0
+        }
+    }
+
+    fn credential_id(&self) -> String {
+        URL_SAFE_NO_PAD.encode(&self.credential_id)
+    }
+
+    fn client_data_json(ceremony_type: &str, challenge: &str, origin: &str) -> Vec<u8> {
+        serde_json::to_vec(&json!({
+            "type": ceremony_type,
+            "challenge": challenge,
+            "origin": origin
+        })).unwrap()
+    }
+
+    /* * rpIdHash | flags | signCount | attestedCredentialData */
+    fn authenticator_data(&self, attested: bool) -> Vec<u8> {
+        let mut auth_data = Sha256::digest(RP_ID.as_bytes()).to_vec();
+        auth_data.push(if attested { 0x45 } else { 0x05 });
+        auth_data.extend_from_slice(&self.sign_count.to_be_bytes());
+
+        if attested {
+            let public_key = self.signing_key.verifying_key().to_encoded_point(false);
+            let cose_key = CborValue::Map(vec![
+                (CborValue::Integer(1.into()), CborValue::Integer(2.into())),
+                (CborValue::Integer(3.into()), CborValue::Integer((-7).into())),
+                (CborValue::Integer((-1).into()), CborValue::Integer(1.into())),
+                (CborValue::Integer((-2).into()), CborValue::Bytes(public_key.x().unwrap().to_vec())),
+                (CborValue::Integer((-3).into()), CborValue::Bytes(public_key.y().unwrap().to_vec()))
+            ]);
+            auth_data.extend_from_slice(&[0u8; 16]);
+            auth_data.extend_from_slice(&(self.credential_id.len() as u16).to_be_bytes());
+            auth_data.extend_from_slice(&self.credential_id);
+            ciborium::ser::into_writer(&cose_key, &mut auth_data).unwrap();
+        }
+
+        auth_data
+    }
+
+    /* * navigator.credentials.create() */
+    fn create(&self, challenge: &str) -> Value {
+        let client_data_json = Self::client_data_json("webauthn.create", challenge, RP_ORIGIN);
+        let attestation_object = CborValue::Map(vec![
+            (CborValue::Text(String::from("fmt")), CborValue::Text(String::from("none"))),
+            (CborValue::Text(String::from("attStmt")), CborValue::Map(vec![])),
+            (CborValue::Text(String::from("authData")), CborValue::Bytes(self.authenticator_data(true)))
+        ]);
+        let mut attestation_object_bytes = Vec::new();
+        ciborium::ser::into_writer(&attestation_object, &mut attestation_object_bytes).unwrap();
+
+        json!({
+            "credentials": {
+                "name": "software authenticator",
+                "id": self.credential_id(),
+                "response": {
+                    "clientDataJSON": URL_SAFE_NO_PAD.encode(client_data_json),
+                    "attestationObject": URL_SAFE_NO_PAD.encode(attestation_object_bytes)
+                }
+            }
+        })
+    }
+
+    /* * navigator.credentials.get(), signature over authenticatorData || sha256(clientDataJSON) */
+    fn get(&mut self, challenge: &str, origin: &str) -> Value {
+        self.sign_count += 1;
+        let client_data_json = Self::client_data_json("webauthn.get", challenge, origin);
+        let authenticator_data = self.authenticator_data(false);
+        let mut signed_data = authenticator_data.clone();
+        signed_data.extend_from_slice(&Sha256::digest(&client_data_json));
+        let signature: Signature = self.signing_key.sign(&signed_data);
+
+        json!({
+            "credentials": {
+                "id": self.credential_id(),
+                "response": {
+                    "clientDataJSON": URL_SAFE_NO_PAD.encode(client_data_json),
+                    "authenticatorData": URL_SAFE_NO_PAD.encode(authenticator_data),
+                    "signature": URL_SAFE_NO_PAD.encode(signature.to_der().as_bytes())
+                }
+            }
+        })
+    }
+}
+/* * end software authenticator, ES256 "none" attestation like a platform passkey */
+
+/* * sign up & sign in with password, returns access_token */
+macro_rules! signup_and_signin {
+    ($app:expr, $username:expr) => {{
+        let (status, body) = signup!($app, $username, PASSWORD);
+        assert_eq!(status, StatusCode::CREATED, "{}", body);
+
+        let (status, body) = signin!($app, $username, PASSWORD);
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        body["data"]["access_token"].as_str().unwrap().to_string()
+    }};
+}
+
+/* * register software authenticator through start & finish ceremony */
+macro_rules! register_passkey {
+    ($app:expr, $access_token:expr, $authenticator:expr) => {{
+        let request = test::TestRequest::post()
+            .uri("/api/auth/passkeys/register/start")
+            .insert_header(("Authorization", format!("Bearer {}", $access_token)))
+            .to_request();
+        let (status, body) = read_response(test::call_service(&$app, request).await).await;
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        let challenge = body["data"]["challenge"].as_str().unwrap().to_string();
+
+        let request = test::TestRequest::post()
+            .uri("/api/auth/passkeys/register/finish")
+            .insert_header(("Authorization", format!("Bearer {}", $access_token)))
+            .set_json($authenticator.create(&challenge))
+            .to_request();
+        let (status, body) = read_response(test::call_service(&$app, request).await).await;
+        assert_eq!(status, StatusCode::CREATED, "{}", body);
+        challenge
+    }};
+}
+
+/* * start passkey signin, returns challenge */
+macro_rules! signin_start {
+    ($app:expr, $username:expr) => {{
+        let request = test::TestRequest::post()
+            .uri("/api/auth/passkeys/signin/start")
+            .set_json(json!({ "credentials": { "username": $username } }))
+            .to_request();
+        let (status, body) = read_response(test::call_service(&$app, request).await).await;
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        body["data"]["challenge"].as_str().unwrap().to_string()
+    }};
+}
+
+macro_rules! signin_finish {
+    ($app:expr, $assertion:expr) => {{
+        let request = test::TestRequest::post()
+            .uri("/api/auth/passkeys/signin/finish")
+            .set_json($assertion)
+            .to_request();
+        read_response(test::call_service(&$app, request).await).await
+    }};
+}
+
+#[actix_web::test]
+#[ignore = "requires MySQL/MariaDB from docker-compose.yml and DATABASE_URL"]
+async fn passkey_register_and_signin() {
+    let app_state = build_app_state("db").await;
+    let app = init_app!(app_state);
+    let username = unique_username("passkey");
+    let access_token = signup_and_signin!(app, &username);
+    let mut authenticator = SoftAuthenticator::new();
+    register_passkey!(app, access_token, authenticator);
+
+    let challenge = signin_start!(app, &username);
+    let (status, body) = signin_finish!(app, authenticator.get(&challenge, RP_ORIGIN));
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert!(body["data"]["access_token"].is_string());
+
+    /* * passkey is listed for its owner */
+    let request = test::TestRequest::get()
+        .uri("/api/auth/passkeys")
+        .insert_header(("Authorization", format!("Bearer {}", access_token)))
+        .to_request();
+    let (status, body) = read_response(test::call_service(&app, request).await).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["data"][0]["credential_id"], authenticator.credential_id());
+}
+
+#[actix_web::test]
+#[ignore = "requires MySQL/MariaDB from docker-compose.yml and DATABASE_URL"]
+async fn passkey_challenge_is_single_use() {
+    let app_state = build_app_state("db").await;
+    let app = init_app!(app_state);
+    let username = unique_username("passkey");
+    let access_token = signup_and_signin!(app, &username);
+    let mut authenticator = SoftAuthenticator::new();
+
+    /* * replayed registration is rejected, challenge was consumed */
+    let challenge = register_passkey!(app, access_token, authenticator);
+    let request = test::TestRequest::post()
+        .uri("/api/auth/passkeys/register/finish")
+        .insert_header(("Authorization", format!("Bearer {}", access_token)))
+        .set_json(authenticator.create(&challenge))
+        .to_request();
+    let (status, body) = read_response(test::call_service(&app, request).await).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED, "{}", body);
+
+    /* * replayed assertion is rejected even with a fresh signature counter */
+    let challenge = signin_start!(app, &username);
+    let (status, body) = signin_finish!(app, authenticator.get(&challenge, RP_ORIGIN));
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let (status, body) = signin_finish!(app, authenticator.get(&challenge, RP_ORIGIN));
+    assert_eq!(status, StatusCode::UNAUTHORIZED, "{}", body);
+}
+
+#[actix_web::test]
+#[ignore = "requires MySQL/MariaDB from docker-compose.yml and DATABASE_URL"]
+async fn passkey_signin_rejects_wrong_origin_and_cloned_authenticator() {
+    let app_state = build_app_state("db").await;
+    let app = init_app!(app_state);
+    let username = unique_username("passkey");
+    let access_token = signup_and_signin!(app, &username);
+    let mut authenticator = SoftAuthenticator::new();
+    register_passkey!(app, access_token, authenticator);
+
+    /* * assertion made for another origin */
+    let challenge = signin_start!(app, &username);
+    let (status, body) = signin_finish!(app, authenticator.get(&challenge, "https://evil.example"));
+    assert_eq!(status, StatusCode::UNAUTHORIZED, "{}", body);
+
+    /* * signature counter going backwards means a cloned authenticator */
+    let challenge = signin_start!(app, &username);
+    let (status, body) = signin_finish!(app, authenticator.get(&challenge, RP_ORIGIN));
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    authenticator.sign_count = 0;
+    let challenge = signin_start!(app, &username);
+    let (status, body) = signin_finish!(app, authenticator.get(&challenge, RP_ORIGIN));
+    assert_eq!(status, StatusCode::UNAUTHORIZED, "{}", body);
+}